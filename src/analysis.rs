@@ -1,223 +1,434 @@
-use crate::models::{AnalysisMethod, AnalyzeRequest, AnalysisResponse};
-use anyhow::{Result, anyhow};
-use std::collections::HashMap;
-
-// Machine Learning & Stats
-use linfa::prelude::*;
-use linfa_trees::DecisionTree;
-use ndarray::{Array, Array1, Array2};
-
-/// Main analysis router. It receives a request and calls the appropriate analysis function.
-pub fn run_analysis(req: &AnalyzeRequest) -> Result<AnalysisResponse> {
-    match req.method {
-        AnalysisMethod::ComprehensiveStatistics => run_comprehensive_analysis(&req.numbers),
-        AnalysisMethod::Numerology => run_numerology_analysis(&req.numbers),
-        AnalysisMethod::MlDecisionTree => run_ml_analysis(&req.numbers),
-        AnalysisMethod::MarkovChain => run_markov_chain_analysis(&req.numbers),
-    }
-}
-
-// --- Method 1: Statistical Analysis (UPDATED) ---
-fn run_comprehensive_analysis(numbers_str: &[String]) -> Result<AnalysisResponse> {
-    if numbers_str.len() < 10 {
-        return Err(anyhow!("ข้อมูลไม่เพียงพอ AI ต้องการชุดตัวเลขอย่างน้อย 10 ชุด แต่พบเพียง {}.", numbers_str.len()));
-    }
-
-    let mut counts = HashMap::new();
-    let mut numbers_f64 = Vec::new();
-    for s in numbers_str {
-        if let Ok(num) = s.parse::<f64>() {
-            numbers_f64.push(num);
-        }
-        *counts.entry(s.clone()).or_insert(0) += 1;
-    }
-
-    if numbers_f64.len() < 5 {
-        return Err(anyhow!("ไม่สามารถแยกวิเคราะห์ตัวเลขที่ถูกต้องเพียงพอสำหรับการวิเคราะห์ทางสถิติ"));
-    }
-
-    numbers_f64.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-
-    let mean = numbers_f64.iter().sum::<f64>() / (numbers_f64.len() as f64);
-    let median = numbers_f64[numbers_f64.len() / 2];
-    let min = *numbers_f64.first().unwrap_or(&0.0);
-    let max = *numbers_f64.last().unwrap_or(&0.0);
-    let variance = numbers_f64.iter().map(|&val| (val - mean).powi(2)).sum::<f64>() / (numbers_f64.len() as f64);
-    let std_dev = variance.sqrt();
-
-    let mut sorted_counts: Vec<_> = counts.into_iter().collect();
-    sorted_counts.sort_by(|a, b| b.1.cmp(&a.1));
-
-    let top_prediction = sorted_counts.first().map(|(val, _)| val.clone()).unwrap_or_else(|| "N/A".to_string());
-
-    let mut prediction_output = HashMap::new();
-    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(top_prediction));
-    prediction_output.insert("METHOD".to_string(), serde_json::json!("ฐานนิยมทางสถิติ"));
-
-    let alternatives: Vec<String> = sorted_counts.iter().skip(1).take(4).map(|(val, _)| val.clone()).collect();
-    if !alternatives.is_empty() {
-        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
-    }
-
-    let statistical_summary = HashMap::from([
-        ("ขนาดชุดข้อมูล".to_string(), numbers_str.len().to_string()),
-        ("ค่าเฉลี่ย".to_string(), format!("{:.2}", mean)),
-        ("มัธยฐาน".to_string(), format!("{:.2}", median)),
-        ("ฐานนิยม (พบบ่อยที่สุด)".to_string(), top_prediction.clone()),
-        ("ส่วนเบี่ยงเบนมาตรฐาน".to_string(), format!("{:.2}", std_dev)),
-        ("พิสัย".to_string(), format!("{} - {}", min, max)),
-    ]);
-    
-    let detailed_explanation = HashMap::from([
-        ("หลักการ".to_string(), "โมเดลนี้ใช้การวิเคราะห์ความถี่ (Frequency Analysis) โดยการทำนายคือ 'ฐานนิยม' (ตัวเลขที่เกิดขึ้นบ่อยที่สุด) และแสดงตัวเลขที่มีความถี่รองลงมาเป็นทางเลือก".to_string()),
-        ("ตรรกะการทำนาย".to_string(), format!("ตัวเลข '{}' ปรากฏบ่อยที่สุดในข้อมูลย้อนหลังที่ให้มา", top_prediction)),
-    ]);
-
-    Ok(AnalysisResponse {
-        statistical_summary,
-        pattern_analysis: HashMap::new(),
-        prediction_output,
-        detailed_explanation,
-    })
-}
-
-// --- Method 2: Numerology (Digital Root) (UPDATED) ---
-fn calculate_digital_root(number_str: &str) -> u32 {
-    let mut sum: u32 = number_str.chars().filter_map(|c| c.to_digit(10)).sum();
-    while sum > 9 {
-        sum = sum.to_string().chars().filter_map(|c| c.to_digit(10)).sum();
-    }
-    sum
-}
-
-fn run_numerology_analysis(numbers_str: &[String]) -> Result<AnalysisResponse> {
-    if numbers_str.is_empty() { return Err(anyhow!("ไม่สามารถวิเคราะห์รายการตัวเลขที่ว่างเปล่าได้")); }
-
-    let mut root_counts = HashMap::new();
-    for num_str in numbers_str {
-        *root_counts.entry(calculate_digital_root(num_str)).or_insert(0) += 1;
-    }
-
-    let mut sorted_roots: Vec<_> = root_counts.iter().collect();
-    sorted_roots.sort_by(|a, b| b.1.cmp(a.1));
-
-    let most_common_root = sorted_roots.first().map(|(&root, _)| root).unwrap_or(0);
-    let prediction = numbers_str.iter().rev()
-        .find(|n| calculate_digital_root(n) == most_common_root)
-        .cloned().unwrap_or_else(|| "N/A".to_string());
-
-    let mut prediction_output = HashMap::new();
-    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(prediction.clone()));
-    prediction_output.insert("METHOD".to_string(), serde_json::json!("การวิเคราะห์ Digital Root"));
-
-    let alternatives: Vec<String> = sorted_roots.iter().skip(1).take(4)
-        .filter_map(|(root, _)| {
-            numbers_str.iter().rev().find(|n| calculate_digital_root(n) == **root).cloned()
-        })
-        .collect();
-
-    if !alternatives.is_empty() {
-        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
-    }
-
-    Ok(AnalysisResponse {
-        statistical_summary: HashMap::from([
-            ("ประเภทการวิเคราะห์".to_string(), "ศาสตร์แห่งตัวเลข (Digital Root)".to_string()),
-            ("รากที่พบบ่อยที่สุด".to_string(), most_common_root.to_string()),
-        ]),
-        pattern_analysis: HashMap::from([("ความถี่ของ Digital Root".to_string(), serde_json::json!(root_counts))]),
-        prediction_output,
-        detailed_explanation: HashMap::from([
-            ("หลักการ".to_string(), "การวิเคราะห์นี้คำนวณ 'digital root' (ผลรวมเลขหลักเดียว) ของแต่ละตัวเลข การทำนายคือตัวเลขล่าสุดที่ตรงกับ digital root ที่พบบ่อยที่สุด และแสดงตัวเลขทางเลือกจากรากที่พบบ่อยรองลงมา".to_string())
-        ]),
-    })
-}
-
-// --- Method 3: Machine Learning (Decision Tree) ---
-fn run_ml_analysis(numbers_str: &[String]) -> Result<AnalysisResponse> {
-    if numbers_str.len() < 10 {
-        return Err(anyhow!("แมชชีนเลิร์นนิงต้องการข้อมูลอย่างน้อย 10 จุดข้อมูลในการฝึกฝน"));
-    }
-    let num_len = numbers_str.first().map_or(0, |n| n.len());
-    if num_len == 0 || !numbers_str.iter().all(|n| n.len() == num_len && n.chars().all(|c| c.is_digit(10))) {
-        return Err(anyhow!("ตัวเลขทั้งหมดต้องมีความยาวเท่ากันและประกอบด้วยตัวเลขเท่านั้นสำหรับการวิเคราะห์ ML"));
-    }
-
-    let mut feature_rows = Vec::with_capacity(numbers_str.len());
-    let mut labels = Vec::with_capacity(numbers_str.len());
-
-    for pair in numbers_str.windows(2) {
-        let features: Vec<f64> = pair[0].chars().map(|c| (c.to_digit(10).unwrap_or(0)) as f64).collect();
-        let label = pair[1].chars().last().unwrap_or('0').to_digit(10).unwrap_or(0) as usize;
-        feature_rows.push(features);
-        labels.push(label);
-    }
-    
-    if feature_rows.is_empty() { return Err(anyhow!("ไม่สามารถสร้างคู่ฝึกฝนจากข้อมูลได้")); }
-
-    let (n_samples, n_features) = (labels.len(), num_len);
-    let flat_features: Vec<f64> = feature_rows.into_iter().flatten().collect();
-    let records = Array::from_shape_vec((n_samples, n_features), flat_features)?;
-    let targets = Array1::from(labels);
-    let dataset = Dataset::new(records, targets);
-
-    let model = DecisionTree::params().fit(&dataset)?;
-    
-    let last_number_features: Vec<f64> = numbers_str.last().unwrap().chars().map(|c| c.to_digit(10).unwrap() as f64).collect();
-    let last_number_array = Array2::from_shape_vec((1, n_features), last_number_features)?;
-    let predicted_last_digit = model.predict(&last_number_array);
-
-    Ok(AnalysisResponse {
-        statistical_summary: HashMap::from([("โมเดล".to_string(), "Decision Tree Classifier".to_string())]),
-        pattern_analysis: HashMap::from([("เป้าหมายการทำนาย".to_string(), serde_json::json!("ตัวเลขสุดท้ายของหมายเลขถัดไป"))]),
-        prediction_output: HashMap::from([
-            ("PREDICTION".to_string(), serde_json::json!(format!("ตัวเลขใดๆ ที่ลงท้ายด้วย '{}'", predicted_last_digit[0]))),
-            ("METHOD".to_string(), serde_json::json!("แมชชีนเลิร์นนิง (Decision Tree)")),
-        ]),
-        detailed_explanation: HashMap::from([("หลักการ".to_string(), "โมเดล Decision Tree ได้รับการฝึกฝนเพื่อทำนาย 'เลขท้าย' ของหมายเลขถัดไปโดยพิจารณาจากเลขของหมายเลขก่อนหน้า เนื่องจากลักษณะของโมเดลนี้จะให้ผลลัพธ์ที่ชัดเจนตามกฎที่เรียนรู้มา จึงไม่มีการแสดง 'ทางเลือกอื่น' เหมือนโมเดลเชิงสถิติ".to_string())]),
-    })
-}
-
-// --- Method 4: Markov Chain ---
-fn run_markov_chain_analysis(numbers_str: &[String]) -> Result<AnalysisResponse> {
-    if numbers_str.len() < 2 {
-        return Err(anyhow!("การวิเคราะห์แบบมาร์คอฟเชนต้องการข้อมูลอย่างน้อย 2 จุดข้อมูล"));
-    }
-
-    let mut transitions: HashMap<String, HashMap<String, u32>> = HashMap::new();
-    for window in numbers_str.windows(2) {
-        *transitions.entry(window[0].clone()).or_default().entry(window[1].clone()).or_default() += 1;
-    }
-
-    let last_number = numbers_str.last().unwrap();
-    let mut prediction_output = HashMap::new();
-
-    if let Some(possible_next) = transitions.get(last_number) {
-        let mut sorted_options: Vec<_> = possible_next.iter().collect();
-        sorted_options.sort_by(|a, b| b.1.cmp(a.1));
-
-        if let Some((top_prediction, _)) = sorted_options.first() {
-            prediction_output.insert("PREDICTION".to_string(), serde_json::json!(top_prediction.to_string()));
-            
-            let alternatives: Vec<String> = sorted_options.iter().skip(1).take(4).map(|(s, _)| s.to_string()).collect();
-            if !alternatives.is_empty() {
-                prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
-            }
-        } else {
-             prediction_output.insert("PREDICTION".to_string(), serde_json::json!("ไม่พบการเปลี่ยนแปลงในอดีต"));
-        }
-    } else {
-        prediction_output.insert("PREDICTION".to_string(), serde_json::json!("ไม่พบการเปลี่ยนแปลงในอดีตสำหรับเลขตัวล่าสุด"));
-    }
-    
-    prediction_output.insert("METHOD".to_string(), serde_json::json!("การวิเคราะห์แบบมาร์คอฟเชน"));
-
-    Ok(AnalysisResponse {
-        statistical_summary: HashMap::from([("จำนวนสถานะ (ตัวเลขที่ไม่ซ้ำกัน)".to_string(), transitions.len().to_string())]),
-        pattern_analysis: HashMap::new(), // Pattern is implicit in the prediction
-        prediction_output,
-        detailed_explanation: HashMap::from([
-            ("หลักการ".to_string(), format!("การวิเคราะห์นี้จะคำนวณความน่าจะเป็นในอดีตของการเปลี่ยนจากตัวเลขหนึ่งไปยังตัวเลขถัดไป การทำนายคือตัวเลขที่ตามหลัง '{}' บ่อยที่สุดในอดีต และแสดงทางเลือกอื่นๆ ที่มีความเป็นไปได้รองลงมา", last_number))
-        ]),
-    })
+use crate::history::StoredHistory;
+use crate::models::{
+    AnalysisMethod, AnalysisParams, AnalyzeRequest, AnalysisResponse, DataQualityReport, DrawRecord, DreamMatch, EvaluationReport,
+    FeatureExportRequest, HistoryPrize, InputOrder, LeaderboardRow, LedgerEntry, LottoResult, LottoType, NoiseSimulationReport, NoiseSimulationRequest, StrategySimulationReport, StrategySimulationRequest, TargetSpec,
+    TicketCheckReport, TicketCheckRequest,
+};
+use classifiers::ModelKind;
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+// --- Analyzer Submodules ---
+mod bayesian;
+mod classifiers;
+mod consensus;
+mod cooccurrence;
+mod descriptive;
+mod dreams;
+mod entropy;
+mod evaluation;
+mod features;
+mod gaps;
+mod markov;
+mod ml;
+mod numerology;
+mod permutations;
+mod positional;
+mod probability;
+mod quality;
+mod randomness;
+mod scoring;
+mod seasonal;
+mod serial;
+mod simulation;
+mod tickets;
+
+/// Main analysis router. It receives a request and calls the appropriate analysis function.
+pub fn run_analysis(req: &AnalyzeRequest) -> Result<AnalysisResponse> {
+    let dates = req.dates.as_deref().map(|d| parse_draw_dates(d, req.numbers.len())).transpose()?;
+    let (numbers, dates) = chronological(&req.numbers, dates, req.order, is_sequential(req.method))?;
+    let target = req.target.unwrap_or(TargetSpec::Whole);
+    let numbers = apply_target(&numbers, target)?;
+    let mut response = dispatch(req.method, &numbers, dates.as_deref(), &req.params, &req.draws)?;
+    if let Some(digits) = req.params.permutation_digits {
+        permutations::expand_permutations(&mut response, &numbers, digits)?;
+    }
+    response.target = Some(target);
+    Ok(response)
+}
+
+/// Reduces every number to the part being analyzed, so that no method mixes numbers of different
+/// lengths. The numbers must be all digits and long enough for the target; the whole number is only
+/// analyzed when all numbers have the same length.
+fn apply_target(numbers: &[String], target: TargetSpec) -> Result<Vec<String>> {
+    if let Some(n) = numbers.iter().find(|n| n.is_empty() || !n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("'{}' ไม่ใช่ตัวเลขที่ถูกต้อง ตัวเลขทั้งหมดต้องประกอบด้วยตัวเลข 0-9 เท่านั้น", n));
+    }
+    let min_len = numbers.iter().map(|n| n.len()).min().unwrap_or(0);
+    let required = match target {
+        TargetSpec::Whole => {
+            if numbers.iter().any(|n| n.len() != min_len) {
+                return Err(anyhow!("ตัวเลขมีความยาวไม่เท่ากัน กรุณาเลือกส่วนของตัวเลขที่จะวิเคราะห์ (target) เช่น {} ตัวท้าย", min_len));
+            }
+            0
+        }
+        TargetSpec::Last { digits } | TargetSpec::First { digits } if digits == 0 => {
+            return Err(anyhow!("จำนวนหลักของ target ต้องมากกว่า 0"));
+        }
+        TargetSpec::Position { index: 0 } => return Err(anyhow!("ตำแหน่งหลัก (index) เริ่มนับจาก 1")),
+        TargetSpec::Last { digits } | TargetSpec::First { digits } => digits,
+        TargetSpec::Position { index } => index,
+    };
+    if !numbers.is_empty() && min_len < required {
+        return Err(anyhow!("ตัวเลขที่สั้นที่สุดมีเพียง {} หลัก จึงไม่สามารถวิเคราะห์แบบ '{}' ได้", min_len, target));
+    }
+
+    Ok(numbers.iter()
+        .map(|n| match target {
+            TargetSpec::Whole => n.clone(),
+            TargetSpec::Last { digits } => n[n.len() - digits..].to_string(),
+            TargetSpec::First { digits } => n[..digits].to_string(),
+            TargetSpec::Position { index } => n[index - 1..index].to_string(),
+        })
+        .collect())
+}
+
+/// Whether a method depends on the order of the draws, e.g. by learning transitions or counting
+/// draws since a number last appeared.
+fn is_sequential(method: AnalysisMethod) -> bool {
+    match method {
+        AnalysisMethod::MlDecisionTree
+        | AnalysisMethod::MarkovChain
+        | AnalysisMethod::HotCold
+        | AnalysisMethod::SerialDependence
+        | AnalysisMethod::MlRandomForest
+        | AnalysisMethod::MlGradientBoosting
+        | AnalysisMethod::Consensus
+        | AnalysisMethod::Numerology
+        | AnalysisMethod::Seasonal
+        | AnalysisMethod::DreamNumbers => true,
+        AnalysisMethod::ComprehensiveStatistics
+        | AnalysisMethod::RandomnessTests
+        | AnalysisMethod::DigitHeatmap
+        | AnalysisMethod::BayesianDirichlet
+        | AnalysisMethod::Cooccurrence
+        | AnalysisMethod::EntropyDiagnostics => false,
+    }
+}
+
+/// Puts the numbers, and their dates, in chronological order (oldest first). Dates decide the order
+/// when given; otherwise `order` says how the numbers are listed. Sequential methods reject input whose
+/// order is unknown, or whose dates repeat, rather than guessing.
+fn chronological(numbers: &[String], dates: Option<Vec<NaiveDate>>, order: Option<InputOrder>, sequential: bool) -> Result<(Vec<String>, Option<Vec<NaiveDate>>)> {
+    match (dates, order) {
+        (Some(dates), _) => {
+            let mut dated: Vec<(NaiveDate, &String)> = dates.into_iter().zip(numbers).collect();
+            dated.sort_by_key(|(date, _)| *date);
+            if sequential {
+                if let Some(w) = dated.windows(2).find(|w| w[0].0 == w[1].0) {
+                    return Err(anyhow!("ลำดับของข้อมูลไม่ชัดเจน: มีหลายงวดในวันที่ {}", w[0].0));
+                }
+            }
+            let (dates, numbers) = dated.into_iter().map(|(d, n)| (d, n.clone())).unzip();
+            Ok((numbers, Some(dates)))
+        }
+        (None, Some(InputOrder::OldestFirst)) => Ok((numbers.to_vec(), None)),
+        (None, Some(InputOrder::NewestFirst)) => Ok((numbers.iter().rev().cloned().collect(), None)),
+        (None, None) if sequential => Err(anyhow!("วิธีนี้ขึ้นกับลำดับของงวด กรุณาระบุ order (oldest_first หรือ newest_first) หรือส่งวันที่ (dates) ของแต่ละงวด")),
+        (None, None) => Ok((numbers.to_vec(), None)),
+    }
+}
+
+/// Runs a single analysis method. Also used by methods that run other analyzers, such as the consensus.
+/// `dates` holds one date per number for the calendar-aware methods, and `draws` holds the
+/// multi-prize draws needed by the methods that compare prize tiers.
+fn dispatch(method: AnalysisMethod, numbers: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams, draws: &[DrawRecord]) -> Result<AnalysisResponse> {
+    match method {
+        AnalysisMethod::ComprehensiveStatistics => descriptive::run_comprehensive_analysis(numbers),
+        AnalysisMethod::Numerology => numerology::run_numerology_analysis(numbers),
+        AnalysisMethod::MlDecisionTree => ml::run_decision_tree_analysis(numbers, dates, params),
+        AnalysisMethod::MarkovChain => markov::run_markov_chain_analysis(numbers, params),
+        AnalysisMethod::RandomnessTests => randomness::run_randomness_analysis(numbers),
+        AnalysisMethod::DigitHeatmap => positional::run_digit_heatmap_analysis(numbers),
+        AnalysisMethod::HotCold => gaps::run_hot_cold_analysis(numbers, params.recent_window),
+        AnalysisMethod::SerialDependence => serial::run_serial_dependence_analysis(numbers, params.max_lag),
+        AnalysisMethod::MlRandomForest => ml::run_ensemble_analysis(numbers, dates, params, ModelKind::RandomForest),
+        AnalysisMethod::MlGradientBoosting => ml::run_ensemble_analysis(numbers, dates, params, ModelKind::GradientBoosting),
+        AnalysisMethod::Consensus => consensus::run_consensus_analysis(numbers, dates, params),
+        AnalysisMethod::BayesianDirichlet => bayesian::run_bayesian_analysis(numbers, params),
+        AnalysisMethod::Cooccurrence => cooccurrence::run_cooccurrence_analysis(draws, params),
+        AnalysisMethod::Seasonal => seasonal::run_seasonal_analysis(numbers, dates),
+        AnalysisMethod::DreamNumbers => dreams::run_dream_number_analysis(numbers, params),
+        AnalysisMethod::EntropyDiagnostics => entropy::run_entropy_diagnostics(numbers),
+    }
+}
+
+/// Fills the request's numbers, dates and multi-prize draws from the stored history when it refers to
+/// one; the posted numbers are replaced. Draws are put in chronological order, by date when every date
+/// can be read and otherwise by reversing the scraper's newest-first order.
+pub fn resolve_history_reference(req: &mut AnalyzeRequest, history: &StoredHistory) -> Result<()> {
+    let Some(reference) = &req.history else { return Ok(()) };
+    let results = history.get(&reference.lotto_type)
+        .filter(|results| !results.is_empty())
+        .ok_or_else(|| anyhow!("ยังไม่มีประวัติสลาก{}บนเซิร์ฟเวอร์ กรุณาดึงข้อมูลก่อน", reference.lotto_type))?;
+    if reference.last == Some(0) {
+        return Err(anyhow!("จำนวนงวดล่าสุด (last) ต้องมากกว่า 0"));
+    }
+
+    let mut ordered: Vec<_> = results.iter().rev().collect();
+    let parsed: Option<Vec<NaiveDate>> = ordered.iter().map(|r| parse_draw_date(&r.draw_date)).collect();
+    if let Some(parsed) = &parsed {
+        let mut dated: Vec<_> = parsed.iter().zip(ordered).collect();
+        dated.sort_by_key(|(date, _)| **date);
+        ordered = dated.into_iter().map(|(_, r)| r).collect();
+    }
+
+    let digits_only = |s: &str| s.chars().filter(|c| c.is_ascii_digit()).collect::<String>();
+    let selected: Vec<_> = ordered.into_iter()
+        .map(|r| {
+            let number = digits_only(match reference.prize {
+                HistoryPrize::Prize1 => &r.prize1,
+                HistoryPrize::Prize2 => &r.prize2,
+            });
+            (r, number)
+        })
+        .filter(|(_, number)| !number.is_empty())
+        .collect();
+    let skip = selected.len().saturating_sub(reference.last.unwrap_or(selected.len()));
+    let selected = &selected[skip..];
+
+    let [name1, name2] = reference.lotto_type.prize_names();
+    req.numbers = selected.iter().map(|(_, number)| number.clone()).collect();
+    req.order = Some(InputOrder::OldestFirst);
+    req.dates = parsed.map(|_| selected.iter().map(|(r, _)| r.draw_date.clone()).collect());
+    req.draws = selected.iter()
+        .map(|(r, _)| {
+            // Every other stored tier is kept too, so co-occurrence compares all of them, not just the two main prizes.
+            let mut prizes: BTreeMap<String, Vec<String>> = r.other_prizes.iter()
+                .map(|(tier, numbers)| (tier.clone(), numbers.iter().map(|n| digits_only(n)).filter(|n| !n.is_empty()).collect()))
+                .collect();
+            prizes.insert(name1.to_string(), vec![digits_only(&r.prize1)]);
+            prizes.insert(name2.to_string(), vec![digits_only(&r.prize2)]);
+            DrawRecord { date: Some(r.draw_date.clone()), prizes }
+        })
+        .collect();
+    Ok(())
+}
+
+/// Cross-validates an ML method on its own, without producing a prediction.
+pub fn run_evaluation(req: &AnalyzeRequest) -> Result<EvaluationReport> {
+    let kind = match req.method {
+        AnalysisMethod::MlDecisionTree => ModelKind::DecisionTree,
+        AnalysisMethod::MlRandomForest => ModelKind::RandomForest,
+        AnalysisMethod::MlGradientBoosting => ModelKind::GradientBoosting,
+        _ => return Err(anyhow!("การประเมินผลแบบตรวจสอบไขว้ใช้ได้กับโมเดลแมชชีนเลิร์นนิงเท่านั้น")),
+    };
+    let dates = req.dates.as_deref().map(|d| parse_draw_dates(d, req.numbers.len())).transpose()?;
+    let (numbers, dates) = chronological(&req.numbers, dates, req.order, true)?;
+    let target = req.target.unwrap_or(TargetSpec::Whole);
+    let mut report = ml::evaluate_ml_model(&apply_target(&numbers, target)?, dates.as_deref(), &req.params, kind)?;
+    report.target = Some(target);
+    Ok(report)
+}
+
+/// Runs an analyzer on random histories to show how often its patterns also appear in pure noise.
+pub fn run_noise_simulation(req: &NoiseSimulationRequest) -> Result<NoiseSimulationReport> {
+    simulation::run_noise_simulation(req)
+}
+
+/// Estimates the return and variance of buying the same tickets on every draw.
+pub fn run_strategy_simulation(req: &StrategySimulationRequest) -> Result<StrategySimulationReport> {
+    simulation::run_strategy_simulation(req)
+}
+
+/// Checks tickets against a stored draw of the requested lottery type under the official payout tables.
+pub fn check_tickets(req: &TicketCheckRequest, history: &StoredHistory) -> Result<TicketCheckReport> {
+    let results = history.get(&req.lotto_type)
+        .filter(|results| !results.is_empty())
+        .ok_or_else(|| anyhow!("ยังไม่มีประวัติสลาก{}บนเซิร์ฟเวอร์ กรุณาดึงข้อมูลก่อน", req.lotto_type))?;
+    tickets::check(req, results)
+}
+
+/// Looks up dream-book keywords; `query` holds keywords separated by commas or spaces.
+pub fn lookup_dream_numbers(query: Option<&str>) -> Result<Vec<DreamMatch>> {
+    let terms: Vec<String> = query.unwrap_or("")
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect();
+    Ok(dreams::lookup(&dreams::load_dream_book()?, &terms))
+}
+
+/// Scans scraped draws for duplicate rows and dates, repeated prizes, gaps in the calendar and prizes
+/// with the wrong number of digits.
+pub fn check_data_quality(lotto_type: &LottoType, results: &[LottoResult]) -> DataQualityReport {
+    quality::check_results(lotto_type, results)
+}
+
+/// Data-quality reports of the stored history, for one lottery type or for every stored type.
+pub fn history_quality_reports(history: &StoredHistory, lotto_type: Option<&LottoType>) -> Vec<DataQualityReport> {
+    let mut reports: Vec<DataQualityReport> = history.iter()
+        .filter(|(t, _)| lotto_type.is_none_or(|only| only == *t))
+        .map(|(t, results)| check_data_quality(t, results))
+        .collect();
+    reports.sort_by_key(|r| r.lotto_type.to_string());
+    reports
+}
+
+/// The ledger entry of a finished analysis, or `None` when the method predicts no number.
+pub fn record_prediction(req: &AnalyzeRequest, response: &AnalysisResponse) -> Option<LedgerEntry> {
+    scoring::record(req, response)
+}
+
+/// Scores the saved predictions whose draws have now been scraped. Returns how many were scored.
+pub fn score_predictions(entries: &mut [LedgerEntry], lotto_type: &LottoType, results: &[LottoResult]) -> usize {
+    scoring::score(entries, lotto_type, results)
+}
+
+/// Real-world hit rate of every method's saved predictions.
+pub fn prediction_leaderboard(entries: &[LedgerEntry]) -> Vec<LeaderboardRow> {
+    scoring::leaderboard(entries)
+}
+
+/// Builds the ML feature matrix for the given draws so it can be exported to external notebooks.
+pub fn export_features(req: &FeatureExportRequest) -> Result<features::FeatureMatrix> {
+    let builder = features::FeatureBuilder::new(req.lags.unwrap_or(features::DEFAULT_FEATURE_LAGS));
+    if req.numbers.len() < builder.min_draws() {
+        return Err(anyhow!("การสร้างฟีเจอร์ต้องการข้อมูลอย่างน้อย {} งวด", builder.min_draws()));
+    }
+    let dates = req.dates.as_deref().map(|d| parse_draw_dates(d, req.numbers.len())).transpose()?;
+    let (numbers, dates) = chronological(&req.numbers, dates, req.order, true)?;
+    let rows = parse_digit_rows(&numbers)?;
+    Ok(builder.build(&rows, dates.as_deref()))
+}
+
+/// Parses a draw date as provided by the scraper: ISO format (YYYY-MM-DD, optionally followed by a
+/// time), or DD/MM/YYYY where a Buddhist-era year (e.g. 2567) is converted to the Gregorian calendar.
+fn parse_draw_date(date_str: &str) -> Option<NaiveDate> {
+    let date_str = date_str.trim();
+    let iso_part = date_str.split(['T', ' ']).next().unwrap_or(date_str);
+    if let Ok(date) = NaiveDate::parse_from_str(iso_part, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let parts: Vec<u32> = date_str.split('/').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let [day, month, year] = parts[..] else { return None };
+    // The year is converted before the date is checked: 29/02/2567 is a leap day, although 2567 is not a leap year.
+    let year = if year > 2400 { year - 543 } else { year };
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+/// Parses the dates sent alongside the numbers. There must be exactly one date per number.
+fn parse_draw_dates(dates: &[String], expected_len: usize) -> Result<Vec<NaiveDate>> {
+    if dates.len() != expected_len {
+        return Err(anyhow!("จำนวนวันที่ ({}) ต้องเท่ากับจำนวนตัวเลข ({})", dates.len(), expected_len));
+    }
+    dates.iter()
+        .map(|d| parse_draw_date(d).ok_or_else(|| anyhow!("รูปแบบวันที่ไม่ถูกต้อง: '{}'", d)))
+        .collect()
+}
+
+/// Splits every number into its digits. All numbers must consist of digits only and share the
+/// same length, so that each digit position can be compared across draws.
+fn parse_digit_rows(numbers_str: &[String]) -> Result<Vec<Vec<u32>>> {
+    let num_len = numbers_str.first().map_or(0, |n| n.len());
+    if num_len == 0 || !numbers_str.iter().all(|n| n.len() == num_len && n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("ตัวเลขทั้งหมดต้องมีความยาวเท่ากันและประกอบด้วยตัวเลขเท่านั้นสำหรับการวิเคราะห์รายหลัก"));
+    }
+    Ok(numbers_str.iter().map(|n| n.chars().filter_map(|c| c.to_digit(10)).collect()).collect())
+}
+
+/// Sums the digits repeatedly until a single digit remains. Shared by the numerology analyzer and the ML features.
+fn calculate_digital_root(number_str: &str) -> u32 {
+    let mut sum: u32 = number_str.chars().filter_map(|c| c.to_digit(10)).sum();
+    while sum > 9 {
+        sum = sum.to_string().chars().filter_map(|c| c.to_digit(10)).sum();
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn targets_cut_each_number() {
+        let numbers = strings(&["123456", "987654"]);
+        assert_eq!(apply_target(&numbers, TargetSpec::Last { digits: 2 }).unwrap(), ["56", "54"]);
+        assert_eq!(apply_target(&numbers, TargetSpec::First { digits: 3 }).unwrap(), ["123", "987"]);
+        assert_eq!(apply_target(&numbers, TargetSpec::Position { index: 1 }).unwrap(), ["1", "9"]);
+        assert_eq!(apply_target(&numbers, TargetSpec::Whole).unwrap(), numbers);
+    }
+
+    #[test]
+    fn targets_reject_what_they_cannot_cut() {
+        let mixed = strings(&["123456", "12"]);
+        assert!(apply_target(&mixed, TargetSpec::Whole).is_err());
+        assert_eq!(apply_target(&mixed, TargetSpec::Last { digits: 2 }).unwrap(), ["56", "12"]);
+        assert!(apply_target(&mixed, TargetSpec::Last { digits: 3 }).is_err());
+        assert!(apply_target(&mixed, TargetSpec::Last { digits: 0 }).is_err());
+        assert!(apply_target(&mixed, TargetSpec::Position { index: 0 }).is_err());
+        assert!(apply_target(&strings(&["12a4"]), TargetSpec::Whole).is_err());
+    }
+
+    #[test]
+    fn dates_decide_the_order() {
+        let numbers = strings(&["333333", "111111", "222222"]);
+        let dates = vec![date(2024, 2, 1), date(2024, 1, 1), date(2024, 1, 16)];
+        let (sorted, sorted_dates) = chronological(&numbers, Some(dates), Some(InputOrder::NewestFirst), true).unwrap();
+        assert_eq!(sorted, ["111111", "222222", "333333"]);
+        assert_eq!(sorted_dates.unwrap(), [date(2024, 1, 1), date(2024, 1, 16), date(2024, 2, 1)]);
+
+        let same_day = vec![date(2024, 1, 1), date(2024, 1, 1), date(2024, 1, 16)];
+        assert!(chronological(&numbers, Some(same_day.clone()), None, true).is_err());
+        assert!(chronological(&numbers, Some(same_day), None, false).is_ok());
+    }
+
+    #[test]
+    fn without_dates_the_order_must_be_given_for_sequential_methods() {
+        let numbers = strings(&["333333", "222222", "111111"]);
+        let (reversed, _) = chronological(&numbers, None, Some(InputOrder::NewestFirst), true).unwrap();
+        assert_eq!(reversed, ["111111", "222222", "333333"]);
+        let (kept, _) = chronological(&numbers, None, Some(InputOrder::OldestFirst), true).unwrap();
+        assert_eq!(kept, numbers);
+        assert!(chronological(&numbers, None, None, true).is_err());
+        assert!(chronological(&numbers, None, None, false).is_ok());
+    }
+
+    #[test]
+    fn history_draws_keep_every_stored_tier() {
+        let draw = |date: &str, prize1: &str, prize2: &str| LottoResult {
+            draw_date: date.to_string(),
+            prize1: prize1.to_string(),
+            prize2: prize2.to_string(),
+            other_prizes: BTreeMap::from([
+                ("รางวัลเลขหน้า 3 ตัว".to_string(), vec!["123".to_string(), "456".to_string()]),
+                ("รางวัลเลขท้าย 3 ตัว".to_string(), vec!["789".to_string(), "012".to_string()]),
+            ]),
+        };
+        let history = StoredHistory::from([(LottoType::Thai, vec![draw("2024-01-16", "222222", "22"), draw("2024-01-01", "111111", "11")])]);
+        let mut req: AnalyzeRequest = serde_json::from_value(serde_json::json!({
+            "method": "cooccurrence",
+            "history": {"lotto_type": "thai", "prize": "prize1"},
+        })).unwrap();
+        resolve_history_reference(&mut req, &history).unwrap();
+
+        assert_eq!(req.numbers, ["111111", "222222"]);
+        let prizes = &req.draws[0].prizes;
+        let tiers: Vec<&str> = prizes.keys().map(String::as_str).collect();
+        for tier in ["รางวัลที่ 1", "รางวัลเลขท้าย 2 ตัว", "รางวัลเลขหน้า 3 ตัว", "รางวัลเลขท้าย 3 ตัว"] {
+            assert!(tiers.contains(&tier), "{}", tier);
+        }
+        assert_eq!(prizes["รางวัลเลขท้าย 3 ตัว"], ["789", "012"]);
+        assert_eq!(prizes["รางวัลเลขท้าย 2 ตัว"], ["11"]);
+    }
+
+    #[test]
+    fn draw_dates_accept_iso_and_buddhist_era() {
+        assert_eq!(parse_draw_date("2024-01-16"), Some(date(2024, 1, 16)));
+        assert_eq!(parse_draw_date(" 2024-01-16T00:00:00Z "), Some(date(2024, 1, 16)));
+        assert_eq!(parse_draw_date("16/01/2567"), Some(date(2024, 1, 16)));
+        assert_eq!(parse_draw_date("16/01/2024"), Some(date(2024, 1, 16)));
+        assert_eq!(parse_draw_date("29/02/2567"), Some(date(2024, 2, 29)));
+        assert_eq!(parse_draw_date("30/02/2567"), None);
+        assert_eq!(parse_draw_date("16 มกราคม 2567"), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,220 @@
+use super::{parse_digit_rows, probability};
+use crate::models::{AnalysisParams, AnalysisResponse};
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+use statrs::distribution::{Beta, ContinuousCDF};
+
+/// Symmetric Dirichlet pseudo-count per category when the request does not set `prior_alpha`.
+const DEFAULT_PRIOR_ALPHA: f64 = 1.0;
+/// Probability mass of the credible intervals.
+const CREDIBLE_LEVEL: f64 = 0.95;
+/// How many of the most likely endings are listed besides the flagged ones.
+const RANKING_SIZE: usize = 10;
+
+/// Posterior summary of one category (a digit at a position, or an ending).
+struct CategoryPosterior {
+    category: String,
+    count: usize,
+    mean: f64,
+    lower: f64,
+    upper: f64,
+}
+
+impl CategoryPosterior {
+    /// "สูงกว่าปกติ" or "ต่ำกว่าปกติ" when the whole interval lies on one side of the uniform rate.
+    fn flag(&self, uniform: f64) -> Option<&'static str> {
+        if self.lower > uniform {
+            Some("สูงกว่าปกติ")
+        } else if self.upper < uniform {
+            Some("ต่ำกว่าปกติ")
+        } else {
+            None
+        }
+    }
+
+    fn to_json(&self, uniform: f64) -> serde_json::Value {
+        serde_json::json!({
+            "category": self.category,
+            "count": self.count,
+            "posterior_mean": self.mean,
+            "credible_interval": [self.lower, self.upper],
+            "flag": self.flag(uniform),
+        })
+    }
+}
+
+/// Updates a symmetric Dirichlet(alpha) prior with the observed counts. The marginal posterior of
+/// each category is Beta(alpha + c, (K * alpha + n) - (alpha + c)), from which the mean and the
+/// central credible interval are taken.
+fn dirichlet_posterior(counts: &[(String, usize)], alpha: f64) -> Result<Vec<CategoryPosterior>> {
+    let n: usize = counts.iter().map(|(_, c)| c).sum();
+    let concentration = alpha * counts.len() as f64 + n as f64;
+    let tail = (1.0 - CREDIBLE_LEVEL) / 2.0;
+    counts.iter()
+        .map(|(category, count)| {
+            let a = alpha + *count as f64;
+            let beta = Beta::new(a, concentration - a).map_err(|e| anyhow!("ไม่สามารถสร้างการแจกแจงเบตา: {}", e))?;
+            Ok(CategoryPosterior {
+                category: category.clone(),
+                count: *count,
+                mean: a / concentration,
+                lower: beta.inverse_cdf(tail),
+                upper: beta.inverse_cdf(1.0 - tail),
+            })
+        })
+        .collect()
+}
+
+/// Counts of every possible `digits`-long ending, including the ones never drawn.
+fn ending_counts(numbers_str: &[String], digits: usize) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for n in numbers_str {
+        *counts.entry(&n[n.len() - digits..]).or_default() += 1;
+    }
+    (0..10usize.pow(digits as u32))
+        .map(|v| {
+            let ending = format!("{:0width$}", v, width = digits);
+            let count = counts.get(ending.as_str()).copied().unwrap_or(0);
+            (ending, count)
+        })
+        .collect()
+}
+
+// --- Method 12: Bayesian Dirichlet-Multinomial Frequencies ---
+/// Posterior digit and ending frequencies under a symmetric Dirichlet prior. Only categories whose
+/// credible interval excludes the uniform rate are flagged.
+pub(super) fn run_bayesian_analysis(numbers_str: &[String], params: &AnalysisParams) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 10 {
+        return Err(anyhow!("การวิเคราะห์แบบเบย์ต้องการข้อมูลอย่างน้อย 10 งวด แต่พบเพียง {}", numbers_str.len()));
+    }
+    let rows = parse_digit_rows(numbers_str)?;
+    let num_len = rows[0].len();
+    if num_len < 2 {
+        return Err(anyhow!("ตัวเลขต้องมีอย่างน้อย 2 หลักสำหรับการวิเคราะห์เลขท้าย"));
+    }
+    let alpha = params.prior_alpha.unwrap_or(DEFAULT_PRIOR_ALPHA);
+    if alpha <= 0.0 {
+        return Err(anyhow!("ค่า prior_alpha ต้องมากกว่า 0"));
+    }
+
+    let mut pattern_analysis = HashMap::new();
+    let mut flag_counts = Vec::new();
+    let mut categories_tested = 0;
+
+    for pos in 0..num_len {
+        let counts: Vec<(String, usize)> = (0..10u32)
+            .map(|d| (d.to_string(), rows.iter().filter(|r| r[pos] == d).count()))
+            .collect();
+        let posterior = dirichlet_posterior(&counts, alpha)?;
+        categories_tested += posterior.len();
+        flag_counts.push(posterior.iter().filter(|p| p.flag(0.1).is_some()).count());
+        pattern_analysis.insert(
+            format!("หลักที่ {}", pos + 1),
+            serde_json::json!(posterior.iter().map(|p| p.to_json(0.1)).collect::<Vec<_>>()),
+        );
+    }
+
+    let mut two_digit_posterior = Vec::new();
+    for digits in [2, 3].into_iter().filter(|&d| d <= num_len) {
+        let uniform = 1.0 / 10f64.powi(digits as i32);
+        let mut posterior = dirichlet_posterior(&ending_counts(numbers_str, digits), alpha)?;
+        posterior.sort_by(|a, b| b.mean.partial_cmp(&a.mean).unwrap().then(a.category.cmp(&b.category)));
+        categories_tested += posterior.len();
+
+        let flagged: Vec<_> = posterior.iter().filter(|p| p.flag(uniform).is_some()).collect();
+        flag_counts.push(flagged.len());
+        pattern_analysis.insert(
+            format!("เลขท้าย {} ตัวที่แตกต่างจากการสุ่ม", digits),
+            serde_json::json!(flagged.iter().map(|p| p.to_json(uniform)).collect::<Vec<_>>()),
+        );
+        pattern_analysis.insert(
+            format!("เลขท้าย {} ตัวที่มีค่าเฉลี่ยภายหลังสูงสุด", digits),
+            serde_json::json!(posterior.iter().take(RANKING_SIZE).map(|p| p.to_json(uniform)).collect::<Vec<_>>()),
+        );
+        if digits == 2 {
+            two_digit_posterior = posterior;
+        }
+    }
+
+    let top = &two_digit_posterior[0];
+    let total_flagged: usize = flag_counts.iter().sum();
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(top.category));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("Bayesian Dirichlet-Multinomial"));
+    let alternatives: Vec<&String> = two_digit_posterior.iter().skip(1).take(4).map(|p| &p.category).collect();
+    prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
+
+    let top_note = match top.flag(0.01) {
+        Some(_) => format!("ช่วงความเชื่อมั่นของเลขท้าย '{}' ไม่ครอบคลุมอัตราการสุ่ม 1% จึงมีหลักฐานว่าออกบ่อยกว่าปกติ", top.category),
+        None => format!("ช่วงความเชื่อมั่นของเลขท้าย '{}' ยังครอบคลุมอัตราการสุ่ม 1% จึงไม่มีหลักฐานว่าออกบ่อยกว่าเลขอื่น", top.category),
+    };
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("ขนาดชุดข้อมูล".to_string(), numbers_str.len().to_string()),
+            ("Prior".to_string(), format!("Dirichlet สมมาตร (α = {})", alpha)),
+            ("ระดับช่วงความเชื่อมั่น".to_string(), format!("{:.0}%", CREDIBLE_LEVEL * 100.0)),
+            ("จำนวนหมวดที่ถูกตั้งข้อสังเกต".to_string(), format!("{} จาก {}", total_flagged, categories_tested)),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), "กำหนดการแจกแจงก่อนหน้าแบบ Dirichlet ให้ความถี่ของเลขแต่ละหลักและเลขท้าย แล้วปรับด้วยข้อมูลย้อนหลัง ได้ค่าเฉลี่ยภายหลัง (posterior mean) และช่วงความเชื่อมั่น 95% ของแต่ละเลข เลขที่ออกซ้ำเพียงครั้งหรือสองครั้งจะถูกดึงเข้าหาอัตราการสุ่ม และมีช่วงความเชื่อมั่นที่กว้าง".to_string()),
+            ("การตั้งข้อสังเกต".to_string(), "เลขจะถูกตั้งข้อสังเกตเฉพาะเมื่อช่วงความเชื่อมั่นทั้งช่วงอยู่สูงหรือต่ำกว่าอัตราการสุ่ม (10% ต่อเลขโดด 1% ต่อเลขท้าย 2 ตัว และ 0.1% ต่อเลขท้าย 3 ตัว) เมื่อตรวจสอบหลายหมวดพร้อมกัน บางหมวดอาจถูกตั้งข้อสังเกตได้โดยบังเอิญแม้ข้อมูลจะสุ่มจริง สามารถวัดความถี่ของกรณีนี้ได้ด้วยการจำลองผ่าน /simulate/noise".to_string()),
+            ("ตรรกะการทำนาย".to_string(), top_note),
+        ]),
+        evaluation: None,
+        prediction_distribution: Some(probability::from_probabilities(
+            two_digit_posterior.iter().map(|p| (p.category.clone(), p.mean)).collect(),
+            100,
+            numbers_str.len(),
+        )),
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(values: &[usize]) -> Vec<(String, usize)> {
+        values.iter().enumerate().map(|(d, &c)| (d.to_string(), c)).collect()
+    }
+
+    #[test]
+    fn posterior_means_add_the_prior_to_the_counts() {
+        let posterior = dirichlet_posterior(&counts(&[8, 2]), 1.0).unwrap();
+        assert!((posterior[0].mean - 0.75).abs() < 1e-12);
+        assert!((posterior[1].mean - 0.25).abs() < 1e-12);
+        for p in &posterior {
+            assert!(p.lower < p.mean && p.mean < p.upper);
+        }
+    }
+
+    #[test]
+    fn intervals_match_the_beta_marginal() {
+        // Under a flat prior over two categories with no data the marginal is Beta(1, 1), i.e. uniform.
+        // The beta quantiles are found numerically, so they are only close to exact.
+        let posterior = dirichlet_posterior(&counts(&[0, 0]), 1.0).unwrap();
+        assert!((posterior[0].lower - 0.025).abs() < 1e-4);
+        assert!((posterior[0].upper - 0.975).abs() < 1e-4);
+    }
+
+    #[test]
+    fn more_draws_narrow_the_interval_and_flag_skewed_categories() {
+        let width = |n: usize| {
+            let p = &dirichlet_posterior(&counts(&[n; 10]), 1.0).unwrap()[0];
+            p.upper - p.lower
+        };
+        assert!(width(1_000) < width(100));
+
+        let mut skewed = [100; 10];
+        skewed[3] = 300;
+        skewed[7] = 10;
+        let posterior = dirichlet_posterior(&counts(&skewed), 1.0).unwrap();
+        assert_eq!(posterior[3].flag(0.1), Some("สูงกว่าปกติ"));
+        assert_eq!(posterior[7].flag(0.1), Some("ต่ำกว่าปกติ"));
+        assert_eq!(posterior[0].flag(0.1), None);
+    }
+}
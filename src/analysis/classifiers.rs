@@ -0,0 +1,318 @@
+use crate::models::AnalysisParams;
+use anyhow::Result;
+use std::collections::HashMap;
+
+// Machine Learning
+use linfa::prelude::*;
+use linfa_trees::{DecisionTree, TreeNode};
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::Rng;
+
+pub(super) const NUM_CLASSES: usize = 10;
+/// Accuracy of guessing a digit uniformly at random.
+pub(super) const CHANCE_BASELINE: f64 = 1.0 / NUM_CLASSES as f64;
+
+/// The classifier family an ML analyzer trains.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum ModelKind {
+    DecisionTree,
+    RandomForest,
+    GradientBoosting,
+}
+
+impl ModelKind {
+    pub(super) fn display_name(self) -> &'static str {
+        match self {
+            ModelKind::DecisionTree => "Decision Tree",
+            ModelKind::RandomForest => "Random Forest",
+            ModelKind::GradientBoosting => "Gradient Boosting",
+        }
+    }
+}
+
+/// Hyperparameters resolved from the request, with per-model defaults.
+/// A single decision tree is grown without a depth limit unless the request sets one.
+pub(super) struct ModelConfig {
+    pub(super) n_trees: usize,
+    pub(super) max_depth: Option<usize>,
+    pub(super) learning_rate: f64,
+    pub(super) feature_fraction: f64,
+    pub(super) test_fraction: f64,
+    pub(super) seed: u64,
+}
+
+impl ModelConfig {
+    pub(super) fn from_params(kind: ModelKind, params: &AnalysisParams) -> Self {
+        let (default_trees, default_depth) = match kind {
+            ModelKind::DecisionTree => (1, None),
+            ModelKind::RandomForest => (100, Some(6)),
+            ModelKind::GradientBoosting => (50, Some(3)),
+        };
+        ModelConfig {
+            n_trees: params.n_trees.unwrap_or(default_trees).clamp(1, 1000),
+            max_depth: params.max_depth.or(default_depth).map(|d| d.clamp(1, 32)),
+            learning_rate: params.learning_rate.unwrap_or(0.1).clamp(1e-4, 1.0),
+            feature_fraction: params.feature_fraction.unwrap_or(0.7).clamp(0.05, 1.0),
+            test_fraction: params.test_fraction.unwrap_or(0.2).clamp(0.05, 0.5),
+            seed: params.seed.unwrap_or(42),
+        }
+    }
+}
+
+/// A fitted classifier that returns a probability for each digit 0-9.
+pub(super) trait DigitClassifier {
+    fn predict_proba(&self, x: &Array2<f64>) -> Vec<[f64; NUM_CLASSES]>;
+
+    fn predict(&self, x: &Array2<f64>) -> Vec<usize> {
+        self.predict_proba(x).iter().map(argmax).collect()
+    }
+}
+
+pub(super) fn argmax(p: &[f64; NUM_CLASSES]) -> usize {
+    (0..NUM_CLASSES).max_by(|&a, &b| p[a].partial_cmp(&p[b]).unwrap().then(b.cmp(&a))).unwrap_or(0)
+}
+
+// --- Decision Tree ---
+
+/// A single decision tree that also remembers the class mix of the training samples in each leaf,
+/// so it can report leaf class probabilities instead of only the majority class.
+struct SingleTree {
+    tree: DecisionTree<f64, usize>,
+    /// Training class counts keyed by the path to each leaf (`false` = left, `true` = right).
+    leaf_counts: HashMap<Vec<bool>, [f64; NUM_CLASSES]>,
+}
+
+impl SingleTree {
+    fn fit(x: &Array2<f64>, y: &Array1<usize>, config: &ModelConfig) -> Result<Self> {
+        let tree = DecisionTree::params()
+            .max_depth(config.max_depth)
+            .fit(&Dataset::new(x.clone(), y.clone()))?;
+        let mut leaf_counts: HashMap<Vec<bool>, [f64; NUM_CLASSES]> = HashMap::new();
+        for (row, &label) in x.rows().into_iter().zip(y.iter()) {
+            let (_, path) = find_leaf(tree.root_node(), &row);
+            leaf_counts.entry(path).or_insert([0.0; NUM_CLASSES])[label] += 1.0;
+        }
+        Ok(SingleTree { tree, leaf_counts })
+    }
+}
+
+/// Follows the same path as linfa's own prediction (left when the feature is below the split value)
+/// and returns the leaf together with the path taken to reach it.
+fn find_leaf<'a>(root: &'a TreeNode<f64, usize>, row: &ArrayView1<f64>) -> (&'a TreeNode<f64, usize>, Vec<bool>) {
+    let mut node = root;
+    let mut path = Vec::new();
+    while !node.is_leaf() {
+        let (feature, split_value, _) = node.split();
+        let go_right = row[feature] >= split_value;
+        match node.children()[go_right as usize] {
+            Some(child) => node = child,
+            None => break,
+        }
+        path.push(go_right);
+    }
+    (node, path)
+}
+
+impl DigitClassifier for SingleTree {
+    /// The probability of a digit is its share of the training samples that reached the same leaf.
+    fn predict_proba(&self, x: &Array2<f64>) -> Vec<[f64; NUM_CLASSES]> {
+        x.rows()
+            .into_iter()
+            .map(|row| {
+                let (leaf, path) = find_leaf(self.tree.root_node(), &row);
+                match self.leaf_counts.get(&path) {
+                    Some(counts) => {
+                        let total: f64 = counts.iter().sum();
+                        counts.map(|c| c / total)
+                    }
+                    None => {
+                        let mut one_hot = [0.0; NUM_CLASSES];
+                        one_hot[leaf.prediction().unwrap_or_default()] = 1.0;
+                        one_hot
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+// --- Random Forest ---
+
+/// Bagged decision trees, each grown on a bootstrap sample and a random subset of the features.
+struct RandomForest {
+    trees: Vec<(DecisionTree<f64, usize>, Vec<usize>)>,
+}
+
+impl RandomForest {
+    fn fit(x: &Array2<f64>, y: &Array1<usize>, config: &ModelConfig, rng: &mut StdRng) -> Result<Self> {
+        let (n_samples, n_features) = x.dim();
+        let n_selected = ((n_features as f64 * config.feature_fraction).round() as usize).clamp(1, n_features);
+        let mut trees = Vec::with_capacity(config.n_trees);
+        for _ in 0..config.n_trees {
+            let rows: Vec<usize> = (0..n_samples).map(|_| rng.gen_range(0..n_samples)).collect();
+            let mut cols = sample(rng, n_features, n_selected).into_vec();
+            cols.sort_unstable();
+            let records = x.select(Axis(0), &rows).select(Axis(1), &cols);
+            let targets = y.select(Axis(0), &rows);
+            let tree = DecisionTree::params()
+                .max_depth(config.max_depth)
+                .fit(&Dataset::new(records, targets))?;
+            trees.push((tree, cols));
+        }
+        Ok(RandomForest { trees })
+    }
+}
+
+impl DigitClassifier for RandomForest {
+    /// The probability of a digit is the share of trees voting for it.
+    fn predict_proba(&self, x: &Array2<f64>) -> Vec<[f64; NUM_CLASSES]> {
+        let mut votes = vec![[0.0; NUM_CLASSES]; x.nrows()];
+        for (tree, cols) in &self.trees {
+            let predictions = tree.predict(&x.select(Axis(1), cols));
+            for (row, &digit) in predictions.iter().enumerate() {
+                votes[row][digit] += 1.0;
+            }
+        }
+        let n_trees = self.trees.len() as f64;
+        votes.iter().map(|v| v.map(|count| count / n_trees)).collect()
+    }
+}
+
+// --- Gradient Boosting ---
+
+/// A CART regression tree used as the weak learner for gradient boosting.
+enum RegressionNode {
+    Leaf(f64),
+    Split { feature: usize, threshold: f64, left: Box<RegressionNode>, right: Box<RegressionNode> },
+}
+
+impl RegressionNode {
+    fn fit(x: &Array2<f64>, target: &[f64], rows: &[usize], depth: usize, max_depth: usize) -> Self {
+        let mean = rows.iter().map(|&i| target[i]).sum::<f64>() / rows.len().max(1) as f64;
+        if depth >= max_depth || rows.len() < 4 {
+            return RegressionNode::Leaf(mean);
+        }
+
+        // Find the split that minimises the summed squared error of the two children.
+        let mut best: Option<(f64, usize, f64)> = None;
+        for feature in 0..x.ncols() {
+            let mut sorted = rows.to_vec();
+            sorted.sort_by(|&a, &b| x[[a, feature]].partial_cmp(&x[[b, feature]]).unwrap());
+            let total_sum: f64 = sorted.iter().map(|&i| target[i]).sum();
+            let mut left_sum = 0.0;
+            for split in 1..sorted.len() {
+                left_sum += target[sorted[split - 1]];
+                let (lo, hi) = (x[[sorted[split - 1], feature]], x[[sorted[split], feature]]);
+                if lo == hi {
+                    continue;
+                }
+                let (n_left, n_right) = (split as f64, (sorted.len() - split) as f64);
+                let right_sum = total_sum - left_sum;
+                // Maximising this is equivalent to minimising the children's squared error.
+                let score = left_sum * left_sum / n_left + right_sum * right_sum / n_right;
+                if best.is_none_or(|(s, _, _)| score > s) {
+                    best = Some((score, feature, (lo + hi) / 2.0));
+                }
+            }
+        }
+
+        match best {
+            Some((_, feature, threshold)) => {
+                let (left_rows, right_rows): (Vec<usize>, Vec<usize>) =
+                    rows.iter().partition(|&&i| x[[i, feature]] <= threshold);
+                RegressionNode::Split {
+                    feature,
+                    threshold,
+                    left: Box::new(RegressionNode::fit(x, target, &left_rows, depth + 1, max_depth)),
+                    right: Box::new(RegressionNode::fit(x, target, &right_rows, depth + 1, max_depth)),
+                }
+            }
+            None => RegressionNode::Leaf(mean),
+        }
+    }
+
+    fn predict(&self, x: &Array2<f64>, row: usize) -> f64 {
+        match self {
+            RegressionNode::Leaf(value) => *value,
+            RegressionNode::Split { feature, threshold, left, right } => {
+                if x[[row, *feature]] <= *threshold { left.predict(x, row) } else { right.predict(x, row) }
+            }
+        }
+    }
+}
+
+fn softmax(scores: &[f64; NUM_CLASSES]) -> [f64; NUM_CLASSES] {
+    let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let exp = scores.map(|s| (s - max).exp());
+    let sum: f64 = exp.iter().sum();
+    exp.map(|e| e / sum)
+}
+
+/// Multiclass gradient boosting with a softmax loss: every round fits one regression tree per digit
+/// to the residuals between the one-hot targets and the current predicted probabilities.
+struct GradientBoosting {
+    initial_scores: [f64; NUM_CLASSES],
+    rounds: Vec<Vec<RegressionNode>>,
+    learning_rate: f64,
+}
+
+impl GradientBoosting {
+    fn fit(x: &Array2<f64>, y: &Array1<usize>, config: &ModelConfig) -> Self {
+        let n_samples = x.nrows();
+        let rows: Vec<usize> = (0..n_samples).collect();
+
+        // Start from the log of the (add-one smoothed) class priors.
+        let mut initial_scores = [0.0; NUM_CLASSES];
+        for (k, score) in initial_scores.iter_mut().enumerate() {
+            let count = y.iter().filter(|&&label| label == k).count() as f64;
+            *score = ((count + 1.0) / (n_samples as f64 + NUM_CLASSES as f64)).ln();
+        }
+
+        let mut scores = vec![initial_scores; n_samples];
+        let mut rounds = Vec::with_capacity(config.n_trees);
+        for _ in 0..config.n_trees {
+            let probabilities: Vec<[f64; NUM_CLASSES]> = scores.iter().map(softmax).collect();
+            let round: Vec<RegressionNode> = (0..NUM_CLASSES)
+                .map(|k| {
+                    let residuals: Vec<f64> = (0..n_samples)
+                        .map(|i| if y[i] == k { 1.0 } else { 0.0 } - probabilities[i][k])
+                        .collect();
+                    RegressionNode::fit(x, &residuals, &rows, 0, config.max_depth.unwrap_or(3))
+                })
+                .collect();
+            for (i, sample_scores) in scores.iter_mut().enumerate() {
+                for (k, tree) in round.iter().enumerate() {
+                    sample_scores[k] += config.learning_rate * tree.predict(x, i);
+                }
+            }
+            rounds.push(round);
+        }
+        GradientBoosting { initial_scores, rounds, learning_rate: config.learning_rate }
+    }
+}
+
+impl DigitClassifier for GradientBoosting {
+    fn predict_proba(&self, x: &Array2<f64>) -> Vec<[f64; NUM_CLASSES]> {
+        (0..x.nrows())
+            .map(|row| {
+                let mut scores = self.initial_scores;
+                for round in &self.rounds {
+                    for (k, tree) in round.iter().enumerate() {
+                        scores[k] += self.learning_rate * tree.predict(x, row);
+                    }
+                }
+                softmax(&scores)
+            })
+            .collect()
+    }
+}
+
+pub(super) fn fit_classifier(kind: ModelKind, x: &Array2<f64>, y: &Array1<usize>, config: &ModelConfig, rng: &mut StdRng) -> Result<Box<dyn DigitClassifier>> {
+    Ok(match kind {
+        ModelKind::DecisionTree => Box::new(SingleTree::fit(x, y, config)?),
+        ModelKind::RandomForest => Box::new(RandomForest::fit(x, y, config, rng)?),
+        ModelKind::GradientBoosting => Box::new(GradientBoosting::fit(x, y, config)),
+    })
+}
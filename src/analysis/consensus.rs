@@ -0,0 +1,343 @@
+use super::classifiers::ModelKind;
+use super::{dispatch, ml, probability};
+use crate::models::{AnalysisMethod, AnalysisParams, AnalysisResponse, ConsensusWeighting, PredictionDistribution};
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Length of the ending voted on when the request does not set `consensus_digits`.
+const DEFAULT_CONSENSUS_DIGITS: usize = 2;
+/// Number of recent draws replayed for backtest weights when the request does not set `backtest_draws`.
+const DEFAULT_BACKTEST_DRAWS: usize = 5;
+/// Every replayed draw reruns all members, ML ensembles included, so a request replays at most this many.
+const MAX_BACKTEST_DRAWS: usize = 12;
+/// How many endings are listed in the consensus ranking.
+const RANKING_SIZE: usize = 10;
+/// A method agrees with an ending when the ending is among its own this many most likely endings.
+const AGREEMENT_DEPTH: usize = 5;
+
+/// Every analyzer asked for a vote. Diagnostic methods without a prediction distribution, and methods
+/// missing their input (dates, dream keywords), are reported as skipped rather than left out silently.
+const MEMBERS: [AnalysisMethod; 14] = [
+    AnalysisMethod::ComprehensiveStatistics,
+    AnalysisMethod::Numerology,
+    AnalysisMethod::MlDecisionTree,
+    AnalysisMethod::MarkovChain,
+    AnalysisMethod::RandomnessTests,
+    AnalysisMethod::DigitHeatmap,
+    AnalysisMethod::HotCold,
+    AnalysisMethod::SerialDependence,
+    AnalysisMethod::MlRandomForest,
+    AnalysisMethod::MlGradientBoosting,
+    AnalysisMethod::BayesianDirichlet,
+    AnalysisMethod::Seasonal,
+    AnalysisMethod::DreamNumbers,
+    AnalysisMethod::EntropyDiagnostics,
+];
+
+/// Methods that never vote, with the reason reported next to the skipped members.
+const NON_MEMBERS: [(AnalysisMethod, &str); 2] = [
+    (AnalysisMethod::Cooccurrence, "เปรียบเทียบหลายรางวัลในงวดเดียวกัน จึงไม่ได้ทำนายตัวเลขถัดไปจากชุดตัวเลขเดียว"),
+    (AnalysisMethod::Consensus, "เป็นการรวมผลของวิธีอื่น"),
+];
+
+/// The request name of a method, e.g. "markov_chain".
+fn method_key(method: AnalysisMethod) -> String {
+    serde_json::json!(method).as_str().unwrap_or_default().to_string()
+}
+
+/// One method's vote: its candidates folded into `digits`-long endings, as shares summing to 1,
+/// largest share first.
+struct Ballot {
+    method: AnalysisMethod,
+    shares: Vec<(String, f64)>,
+}
+
+impl Ballot {
+    fn share_of(&self, ending: &str) -> f64 {
+        self.shares.iter().find(|(e, _)| e == ending).map_or(0.0, |(_, s)| *s)
+    }
+}
+
+/// Probability of every `digits`-long ending under independent per-position digit probabilities.
+fn ending_marginals(positions: &[[f64; 10]], digits: usize) -> HashMap<String, f64> {
+    let mut endings = HashMap::from([(String::new(), 1.0)]);
+    for probabilities in &positions[positions.len() - digits..] {
+        endings = endings.into_iter()
+            .flat_map(|(prefix, p)| (0..10).map(move |d| (format!("{}{}", prefix, d), p * probabilities[d])))
+            .collect();
+    }
+    endings
+}
+
+/// The member's distribution of the next draw. The ML members only fit their final models: the
+/// held-out split and cross-validation of a full analysis do not change the vote and would be
+/// repeated for every ballot of a backtest.
+fn member_distribution(method: AnalysisMethod, numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams) -> Result<PredictionDistribution> {
+    let kind = match method {
+        AnalysisMethod::MlDecisionTree => Some(ModelKind::DecisionTree),
+        AnalysisMethod::MlRandomForest => Some(ModelKind::RandomForest),
+        AnalysisMethod::MlGradientBoosting => Some(ModelKind::GradientBoosting),
+        _ => None,
+    };
+    match kind {
+        Some(kind) => ml::predict_distribution(numbers_str, dates, params, kind),
+        None => dispatch(method, numbers_str, dates, params, &[])?.prediction_distribution
+            .ok_or_else(|| anyhow!("วิธีนี้ไม่ได้ให้การแจกแจงความน่าจะเป็นของตัวเลข")),
+    }
+}
+
+/// Runs `method` and folds its whole distribution into endings. Methods that predict fewer digits
+/// than the ending (e.g. the decision tree's single digit) cannot vote on it.
+fn cast_ballot(method: AnalysisMethod, numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams, digits: usize) -> Result<Ballot> {
+    let distribution = member_distribution(method, numbers_str, dates, params)?;
+
+    let mut totals: HashMap<String, f64> = HashMap::new();
+    match &distribution.digit_probabilities {
+        Some(positions) if positions.len() >= digits => totals = ending_marginals(positions, digits),
+        Some(_) => {}
+        None => {
+            for (outcome, probability) in &distribution.outcomes {
+                // Labels may carry a note after the number, e.g. "123456 (ราก 3)".
+                let number = outcome.split_whitespace().next().unwrap_or_default();
+                if number.len() >= digits && number.chars().all(|c| c.is_ascii_digit()) {
+                    *totals.entry(number[number.len() - digits..].to_string()).or_default() += probability;
+                }
+            }
+        }
+    }
+    totals.retain(|_, p| *p > 0.0);
+    let total: f64 = totals.values().sum();
+    if total <= 0.0 {
+        return Err(anyhow!("ตัวเลือกของวิธีนี้สั้นกว่าเลขท้าย {} ตัว", digits));
+    }
+    let mut shares: Vec<(String, f64)> = totals.into_iter().map(|(e, p)| (e, p / total)).collect();
+    shares.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    Ok(Ballot { method, shares })
+}
+
+/// Backtest record of one method over the replayed draws.
+struct BacktestScore {
+    draws: usize,
+    total_share: f64,
+    top_hits: usize,
+}
+
+/// Draws replayed for backtest weights: `backtest_draws`, kept between 1 and `MAX_BACKTEST_DRAWS`.
+fn backtest_draws(params: &AnalysisParams) -> usize {
+    params.backtest_draws.unwrap_or(DEFAULT_BACKTEST_DRAWS).clamp(1, MAX_BACKTEST_DRAWS)
+}
+
+/// Replays the last `draws` draws: each method votes using only the history before a draw and is
+/// credited with the share it gave the ending that was actually drawn.
+fn backtest(numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams, digits: usize, draws: usize) -> HashMap<AnalysisMethod, BacktestScore> {
+    let mut scores: HashMap<AnalysisMethod, BacktestScore> = HashMap::new();
+    for t in numbers_str.len().saturating_sub(draws).max(1)..numbers_str.len() {
+        let actual = &numbers_str[t][numbers_str[t].len() - digits..];
+        for method in MEMBERS {
+            // Histories too short for a method simply do not count towards its score.
+            if let Ok(ballot) = cast_ballot(method, &numbers_str[..t], dates.map(|d| &d[..t]), params, digits) {
+                let score = scores.entry(method).or_insert(BacktestScore { draws: 0, total_share: 0.0, top_hits: 0 });
+                score.draws += 1;
+                score.total_share += ballot.share_of(actual);
+                score.top_hits += usize::from(ballot.shares[0].0 == actual);
+            }
+        }
+    }
+    scores
+}
+
+// --- Method 11: Consensus (Weighted Voting) ---
+/// Runs every analyzer on the same draws and combines their candidates by weighted voting on the
+/// last `consensus_digits` digits. The input must be ordered from the oldest draw to the newest.
+pub(super) fn run_consensus_analysis(numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 10 {
+        return Err(anyhow!("การวิเคราะห์แบบฉันทามติต้องการข้อมูลอย่างน้อย 10 งวด แต่พบเพียง {}", numbers_str.len()));
+    }
+    let digits = params.consensus_digits.unwrap_or(DEFAULT_CONSENSUS_DIGITS).max(1);
+    if numbers_str.iter().any(|n| n.len() < digits || !n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("ตัวเลขทั้งหมดต้องประกอบด้วยตัวเลขอย่างน้อย {} หลักสำหรับการโหวตเลขท้าย", digits));
+    }
+    let weighting = params.consensus_weighting.unwrap_or_default();
+    let uniform_share = 1.0 / 10f64.powi(digits as i32);
+
+    let mut ballots = Vec::new();
+    let mut skipped: HashMap<String, String> = NON_MEMBERS.iter()
+        .map(|(method, reason)| (method_key(*method), reason.to_string()))
+        .collect();
+    for method in MEMBERS {
+        match cast_ballot(method, numbers_str, dates, params, digits) {
+            Ok(ballot) => ballots.push(ballot),
+            Err(e) => { skipped.insert(method_key(method), e.to_string()); }
+        }
+    }
+
+    let mut pattern_analysis = HashMap::new();
+    let weights: HashMap<AnalysisMethod, f64> = match weighting {
+        ConsensusWeighting::Equal => ballots.iter().map(|b| (b.method, 1.0)).collect(),
+        ConsensusWeighting::Manual => {
+            let manual = params.consensus_weights.as_ref()
+                .ok_or_else(|| anyhow!("การถ่วงน้ำหนักแบบกำหนดเองต้องระบุ consensus_weights"))?;
+            ballots.iter().map(|b| (b.method, manual.get(&b.method).copied().unwrap_or(0.0).max(0.0))).collect()
+        }
+        ConsensusWeighting::Backtest => {
+            let scores = backtest(numbers_str, dates, params, digits, backtest_draws(params));
+            pattern_analysis.insert(
+                "ผลการทดสอบย้อนหลัง".to_string(),
+                serde_json::json!(scores.iter()
+                    .map(|(method, s)| (method_key(*method), serde_json::json!({
+                        "draws": s.draws,
+                        "mean_share_on_actual": s.total_share / s.draws as f64,
+                        "top_pick_hits": s.top_hits,
+                    })))
+                    .collect::<HashMap<_, _>>()),
+            );
+            // The mean share is shrunk towards a uniform guess, so one lucky draw cannot dominate the vote.
+            ballots.iter()
+                .map(|b| (b.method, scores.get(&b.method).map_or(uniform_share, |s| (s.total_share + uniform_share) / (s.draws + 1) as f64)))
+                .collect()
+        }
+    };
+    let total_weight: f64 = weights.values().sum();
+    if total_weight <= 0.0 {
+        return Err(anyhow!("ไม่มีวิธีการวิเคราะห์ใดได้รับน้ำหนักในการโหวต"));
+    }
+
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    let mut supporters: HashMap<String, Vec<String>> = HashMap::new();
+    let mut top_picks: HashMap<String, Vec<String>> = HashMap::new();
+    for ballot in &ballots {
+        let weight = weights[&ballot.method] / total_weight;
+        if weight == 0.0 {
+            continue;
+        }
+        for (rank, (ending, share)) in ballot.shares.iter().enumerate() {
+            *scores.entry(ending.clone()).or_default() += weight * share;
+            if rank < AGREEMENT_DEPTH {
+                supporters.entry(ending.clone()).or_default().push(method_key(ballot.method));
+            }
+        }
+        top_picks.entry(ballot.shares[0].0.clone()).or_default().push(method_key(ballot.method));
+    }
+
+    let mut ranking: Vec<(String, f64)> = scores.into_iter().collect();
+    ranking.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+
+    pattern_analysis.insert(
+        "การจัดอันดับฉันทามติ".to_string(),
+        serde_json::json!(ranking.iter().take(RANKING_SIZE)
+            .map(|(ending, score)| serde_json::json!({
+                "candidate": ending,
+                "score": score,
+                "agreeing_methods": supporters.get(ending).cloned().unwrap_or_default(),
+                "top_pick_of": top_picks.get(ending).cloned().unwrap_or_default(),
+            }))
+            .collect::<Vec<_>>()),
+    );
+    pattern_analysis.insert(
+        "น้ำหนักของแต่ละวิธี".to_string(),
+        serde_json::json!(weights.iter().map(|(m, w)| (method_key(*m), w / total_weight)).collect::<HashMap<_, _>>()),
+    );
+    pattern_analysis.insert(
+        "คำทำนายของแต่ละวิธี".to_string(),
+        serde_json::json!(ballots.iter().map(|b| (method_key(b.method), b.shares[0].0.clone())).collect::<HashMap<_, _>>()),
+    );
+    if !skipped.is_empty() {
+        pattern_analysis.insert("วิธีที่ไม่ได้ร่วมโหวต".to_string(), serde_json::json!(skipped));
+    }
+
+    let top = &ranking[0].0;
+    let agreeing = top_picks.get(top).map_or(0, Vec::len);
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(top));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("ฉันทามติ (Weighted Voting)"));
+    let alternatives: Vec<&String> = ranking.iter().skip(1).take(4).map(|(e, _)| e).collect();
+    if !alternatives.is_empty() {
+        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
+    }
+
+    let weighting_label = match weighting {
+        ConsensusWeighting::Equal => "เท่ากันทุกวิธี",
+        ConsensusWeighting::Manual => "กำหนดเอง",
+        ConsensusWeighting::Backtest => "จากผลการทดสอบย้อนหลัง",
+    };
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("จำนวนวิธีที่ร่วมโหวต".to_string(), ballots.len().to_string()),
+            ("จำนวนวิธีที่ไม่ได้ร่วมโหวต".to_string(), skipped.len().to_string()),
+            ("การถ่วงน้ำหนัก".to_string(), weighting_label.to_string()),
+            ("ความยาวเลขท้ายที่โหวต".to_string(), digits.to_string()),
+            ("จำนวนวิธีที่เลือกเลขอันดับหนึ่งตรงกัน".to_string(), format!("{} จาก {}", agreeing, ballots.len())),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), format!("รันทุกวิธีการวิเคราะห์กับข้อมูลชุดเดียวกัน แล้วรวมความน่าจะเป็นของตัวเลือกแต่ละตัวเป็นเลขท้าย {} ตัว แต่ละวิธีมีคะแนนโหวตรวมเท่ากับ 1 ซึ่งกระจายตามความน่าจะเป็นของตัวเลือก คูณด้วยน้ำหนักของวิธีนั้น", digits)),
+            ("การถ่วงน้ำหนัก".to_string(), "น้ำหนักอาจเท่ากันทุกวิธี กำหนดเองผ่าน consensus_weights หรือคำนวณจากการทดสอบย้อนหลัง ซึ่งให้แต่ละวิธีทำนายงวดล่าสุดหลายงวดโดยใช้เฉพาะข้อมูลก่อนหน้า แล้ววัดส่วนแบ่งคะแนนที่วิธีนั้นให้กับเลขที่ออกจริง".to_string()),
+            ("ข้อควรระวัง".to_string(), "หลายวิธีใช้ข้อมูลชุดเดียวกันและมักเห็นรูปแบบเดียวกัน การที่หลายวิธีเห็นตรงกันจึงไม่ได้แปลว่าเลขนั้นมีโอกาสออกมากขึ้น".to_string()),
+        ]),
+        evaluation: None,
+        prediction_distribution: Some(probability::from_probabilities(ranking, 10u64.saturating_pow(digits as u32), numbers_str.len())),
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(draws: usize) -> (Vec<String>, Vec<NaiveDate>) {
+        let mut state: u64 = 7;
+        let numbers = (0..draws)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                format!("{:06}", (state >> 33) % 1_000_000)
+            })
+            .collect();
+        let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let dates = (0..draws).map(|i| start + chrono::Duration::days(15 * i as i64)).collect();
+        (numbers, dates)
+    }
+
+    /// Few trees keep the ML members fast; the tests only check who votes.
+    fn params() -> AnalysisParams {
+        AnalysisParams { n_trees: Some(5), ..Default::default() }
+    }
+
+    #[test]
+    fn every_method_is_voting_or_reported_as_skipped() {
+        let (numbers, dates) = history(60);
+        let response = run_consensus_analysis(&numbers, Some(&dates), &params()).unwrap();
+        let voted = response.pattern_analysis["คำทำนายของแต่ละวิธี"].as_object().unwrap();
+        let skipped = response.pattern_analysis["วิธีที่ไม่ได้ร่วมโหวต"].as_object().unwrap();
+        for method in MEMBERS.into_iter().chain(NON_MEMBERS.map(|(m, _)| m)) {
+            let key = method_key(method);
+            assert!(voted.contains_key(&key) ^ skipped.contains_key(&key), "{}", key);
+        }
+        assert_eq!(voted.len() + skipped.len(), MEMBERS.len() + NON_MEMBERS.len());
+        assert!(skipped.contains_key("entropy_diagnostics"));
+        assert!(skipped.contains_key("dream_numbers"));
+    }
+
+    #[test]
+    fn backtest_work_is_bounded() {
+        assert_eq!(backtest_draws(&AnalysisParams::default()), DEFAULT_BACKTEST_DRAWS);
+        assert_eq!(backtest_draws(&AnalysisParams { backtest_draws: Some(1_000_000), ..Default::default() }), MAX_BACKTEST_DRAWS);
+        assert_eq!(backtest_draws(&AnalysisParams { backtest_draws: Some(0), ..Default::default() }), 1);
+
+        let (numbers, _) = history(40);
+        let replayed = backtest(&numbers, None, &params(), 2, backtest_draws(&AnalysisParams { backtest_draws: Some(1_000), ..params() }));
+        assert!(replayed.values().all(|s| s.draws <= MAX_BACKTEST_DRAWS));
+        assert!(replayed.values().any(|s| s.draws == MAX_BACKTEST_DRAWS));
+    }
+
+    #[test]
+    fn dates_reach_the_calendar_members() {
+        let (numbers, dates) = history(60);
+        let with_dates = run_consensus_analysis(&numbers, Some(&dates), &params()).unwrap();
+        let without_dates = run_consensus_analysis(&numbers, None, &params()).unwrap();
+        assert!(with_dates.pattern_analysis["คำทำนายของแต่ละวิธี"].get("seasonal").is_some());
+        assert!(without_dates.pattern_analysis["วิธีที่ไม่ได้ร่วมโหวต"].get("seasonal").is_some());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::models::{AnalysisParams, AnalysisResponse, CooccurrenceUnit, DrawRecord};
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeSet, HashMap};
+
+use statrs::distribution::{Binomial, DiscreteCDF, Hypergeometric};
+
+/// Family-wise significance level, divided by the number of combinations tested (Bonferroni).
+const ALPHA: f64 = 0.05;
+/// Combinations seen fewer times than this are not listed, since their lift is mostly noise.
+const MIN_SUPPORT: usize = 2;
+/// How many combinations are listed per group of tiers.
+const RANKING_SIZE: usize = 10;
+
+/// The items a tier contributes to one draw under the chosen unit, e.g. its digits or its endings.
+fn tier_items(numbers: &[String], unit: CooccurrenceUnit) -> BTreeSet<String> {
+    numbers.iter()
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        .flat_map(|n| match unit {
+            CooccurrenceUnit::Digit => n.chars().map(String::from).collect::<Vec<_>>(),
+            CooccurrenceUnit::LastTwo if n.len() >= 2 => vec![n[n.len() - 2..].to_string()],
+            CooccurrenceUnit::LastTwo => Vec::new(),
+        })
+        .collect()
+}
+
+/// A combination of items, one from each tier, and how often the tiers shared it in the same draw.
+struct Combination {
+    items: Vec<String>,
+    count: usize,
+    expected: f64,
+    lift: f64,
+    p_value: f64,
+}
+
+impl Combination {
+    fn to_json(&self, corrected_alpha: f64) -> serde_json::Value {
+        serde_json::json!({
+            "items": self.items,
+            "count": self.count,
+            "expected": self.expected,
+            "lift": self.lift,
+            "p_value": self.p_value,
+            "significant": self.p_value < corrected_alpha,
+        })
+    }
+}
+
+/// Counts every combination across `tiers` (one item per tier) over the draws where all of them were
+/// drawn. Returns (draws used, combinations, number of combinations that could have occurred).
+fn combinations(draw_items: &[HashMap<&str, BTreeSet<String>>], tiers: &[&str]) -> (usize, Vec<Combination>, usize) {
+    let mut draws_used = 0;
+    let mut marginals: Vec<HashMap<&str, usize>> = vec![HashMap::new(); tiers.len()];
+    let mut joint: HashMap<Vec<&str>, usize> = HashMap::new();
+
+    for items in draw_items {
+        let sets: Option<Vec<&BTreeSet<String>>> = tiers.iter().map(|t| items.get(t).filter(|s| !s.is_empty())).collect();
+        let Some(sets) = sets else { continue };
+        draws_used += 1;
+        for (marginal, set) in marginals.iter_mut().zip(&sets) {
+            for item in set.iter() {
+                *marginal.entry(item.as_str()).or_default() += 1;
+            }
+        }
+        let mut combos: Vec<Vec<&str>> = vec![Vec::new()];
+        for set in &sets {
+            combos = combos.into_iter()
+                .flat_map(|prefix| set.iter().map(move |item| {
+                    let mut combo = prefix.clone();
+                    combo.push(item.as_str());
+                    combo
+                }))
+                .collect();
+        }
+        for combo in combos {
+            *joint.entry(combo).or_default() += 1;
+        }
+    }
+
+    let n = draws_used as f64;
+    let possible = marginals.iter().map(HashMap::len).product();
+    let combinations = joint.into_iter()
+        .filter(|(_, count)| *count >= MIN_SUPPORT)
+        .map(|(items, count)| {
+            let counts: Vec<usize> = items.iter().zip(&marginals).map(|(item, m)| m[item]).collect();
+            let expected = n * counts.iter().map(|&c| c as f64 / n).product::<f64>();
+            // Pairs use Fisher's exact test (hypergeometric upper tail); larger groups compare the joint
+            // count with a binomial whose rate assumes every tier is independent.
+            let p_value = match counts.as_slice() {
+                [a, b] => Hypergeometric::new(draws_used as u64, *a as u64, *b as u64).map_or(1.0, |d| d.sf(count as u64 - 1)),
+                _ => Binomial::new(expected / n, draws_used as u64).map_or(1.0, |d| d.sf(count as u64 - 1)),
+            };
+            Combination {
+                items: items.iter().map(|s| s.to_string()).collect(),
+                count,
+                expected,
+                lift: count as f64 / expected,
+                p_value,
+            }
+        })
+        .collect();
+    (draws_used, combinations, possible)
+}
+
+// --- Method 13: Co-occurrence Across Prize Tiers ---
+/// Which digits or 2-digit endings appear together across prize tiers of the same draw, as pairs
+/// and triples of tiers, with lift and significance.
+pub(super) fn run_cooccurrence_analysis(draws: &[DrawRecord], params: &AnalysisParams) -> Result<AnalysisResponse> {
+    if draws.len() < 10 {
+        return Err(anyhow!("การวิเคราะห์การเกิดร่วมกันต้องการข้อมูลแบบหลายรางวัล (draws) อย่างน้อย 10 งวด แต่พบเพียง {}", draws.len()));
+    }
+    let unit = params.cooccurrence_unit.unwrap_or_default();
+    let draw_items: Vec<HashMap<&str, BTreeSet<String>>> = draws.iter()
+        .map(|d| d.prizes.iter().map(|(tier, numbers)| (tier.as_str(), tier_items(numbers, unit))).collect())
+        .collect();
+    let tiers: Vec<&str> = draws.iter()
+        .flat_map(|d| d.prizes.keys().map(String::as_str))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if tiers.len() < 2 {
+        return Err(anyhow!("ข้อมูลต้องมีรางวัลอย่างน้อย 2 ประเภทเพื่อวิเคราะห์การเกิดร่วมกัน"));
+    }
+
+    let mut groups: Vec<Vec<&str>> = Vec::new();
+    for i in 0..tiers.len() {
+        for j in i + 1..tiers.len() {
+            groups.push(vec![tiers[i], tiers[j]]);
+            groups.extend((j + 1..tiers.len()).map(|k| vec![tiers[i], tiers[j], tiers[k]]));
+        }
+    }
+
+    let mut results = Vec::new();
+    let mut tests = 0;
+    for group in &groups {
+        let (draws_used, mut combos, possible) = combinations(&draw_items, group);
+        if draws_used == 0 {
+            continue;
+        }
+        tests += possible;
+        combos.sort_by(|a, b| a.p_value.partial_cmp(&b.p_value).unwrap().then(b.lift.partial_cmp(&a.lift).unwrap()));
+        results.push((group, draws_used, combos));
+    }
+    if results.is_empty() {
+        return Err(anyhow!("ไม่มีงวดใดที่มีรางวัลอย่างน้อย 2 ประเภทพร้อมกัน"));
+    }
+    let corrected_alpha = ALPHA / tests.max(1) as f64;
+
+    let mut pattern_analysis = HashMap::new();
+    for (group, draws_used, combos) in &results {
+        let mut entry = serde_json::json!({
+            "draws": draws_used,
+            "top_combinations": combos.iter().take(RANKING_SIZE).map(|c| c.to_json(corrected_alpha)).collect::<Vec<_>>(),
+        });
+        // Digit pairs are small enough to show as a full 10 x 10 count matrix.
+        if let (CooccurrenceUnit::Digit, 2) = (unit, group.len()) {
+            let mut matrix = vec![vec![0usize; 10]; 10];
+            for c in combos.iter() {
+                if let (Ok(x), Ok(y)) = (c.items[0].parse::<usize>(), c.items[1].parse::<usize>()) {
+                    matrix[x][y] = c.count;
+                }
+            }
+            entry["matrix"] = serde_json::json!(matrix);
+        }
+        pattern_analysis.insert(group.join(" × "), entry);
+    }
+
+    let strongest = results.iter()
+        .flat_map(|(group, _, combos)| combos.iter().map(move |c| (group, c)))
+        .min_by(|a, b| a.1.p_value.partial_cmp(&b.1.p_value).unwrap());
+    let significant_count: usize = results.iter()
+        .map(|(_, _, combos)| combos.iter().filter(|c| c.p_value < corrected_alpha).count())
+        .sum();
+
+    let prediction = match strongest {
+        Some((group, c)) if c.p_value < corrected_alpha => group.iter().zip(&c.items)
+            .map(|(tier, item)| format!("{} ({})", item, tier))
+            .collect::<Vec<_>>()
+            .join(" + "),
+        _ => "ไม่พบการเกิดร่วมกันที่มีนัยสำคัญ".to_string(),
+    };
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(prediction));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("การเกิดร่วมกันข้ามรางวัล (Co-occurrence)"));
+
+    let unit_label = match unit {
+        CooccurrenceUnit::Digit => "เลขโดด",
+        CooccurrenceUnit::LastTwo => "เลขท้าย 2 ตัว",
+    };
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("จำนวนงวด".to_string(), draws.len().to_string()),
+            ("ประเภทรางวัล".to_string(), tiers.join(", ")),
+            ("หน่วยที่เปรียบเทียบ".to_string(), unit_label.to_string()),
+            ("จำนวนการทดสอบ".to_string(), tests.to_string()),
+            ("ระดับนัยสำคัญหลังปรับ Bonferroni".to_string(), format!("{:.2e}", corrected_alpha)),
+            ("จำนวนชุดที่มีนัยสำคัญ".to_string(), significant_count.to_string()),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), format!("สำหรับรางวัลทุกคู่และทุกสามประเภท การวิเคราะห์นี้นับว่า{}ใดปรากฏพร้อมกันในงวดเดียวกันกี่ครั้ง แล้วเทียบกับจำนวนที่คาดไว้หากรางวัลแต่ละประเภทเป็นอิสระต่อกัน", unit_label)),
+            ("Lift และนัยสำคัญ".to_string(), "lift คืออัตราส่วนระหว่างจำนวนครั้งที่เกิดร่วมกันจริงกับจำนวนที่คาดไว้ (1.0 หมายถึงเป็นอิสระต่อกัน) ค่า p ของคู่ใช้ Fisher's exact test และของชุดสามใช้การแจกแจงทวินาม เนื่องจากทดสอบหลายชุดพร้อมกัน ระดับนัยสำคัญจึงถูกปรับด้วยวิธี Bonferroni".to_string()),
+        ]),
+        evaluation: None,
+        prediction_distribution: None,
+        target: None,
+    })
+}
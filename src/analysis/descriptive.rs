@@ -0,0 +1,203 @@
+use super::{parse_digit_rows, probability};
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+use statrs::statistics::{Data, Median, OrderStatistics, Statistics};
+
+/// Sample statistics of a list of values. Quantiles use the median-unbiased estimator of statrs
+/// (Hyndman and Fan type 8), and the variance divides by n - 1.
+struct Summary {
+    mean: f64,
+    median: f64,
+    quantiles: [(f64, f64); 4],
+    min: f64,
+    max: f64,
+    variance: f64,
+    skewness: f64,
+    excess_kurtosis: f64,
+}
+
+impl Summary {
+    /// Requires at least four values, the minimum for the sample kurtosis.
+    fn of(values: &[f64]) -> Summary {
+        let n = values.len() as f64;
+        let mean = values.mean();
+        let variance = values.variance();
+        // Central moments divided by n, as in the adjusted Fisher-Pearson estimators below.
+        let moment = |k: i32| values.iter().map(|v| (v - mean).powi(k)).sum::<f64>() / n;
+        let (m2, m3, m4) = (moment(2), moment(3), moment(4));
+        let (skewness, excess_kurtosis) = if m2 > 0.0 {
+            let g1 = m3 / m2.powf(1.5);
+            let g2 = m4 / (m2 * m2) - 3.0;
+            (
+                (n * (n - 1.0)).sqrt() / (n - 2.0) * g1,
+                (n - 1.0) / ((n - 2.0) * (n - 3.0)) * ((n + 1.0) * g2 + 6.0),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+
+        let mut data = Data::new(values.to_vec());
+        Summary {
+            mean,
+            median: Median::median(&data),
+            quantiles: [0.1, 0.25, 0.75, 0.9].map(|tau| (tau, data.quantile(tau))),
+            min: values.min(),
+            max: values.max(),
+            variance,
+            skewness,
+            excess_kurtosis,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "mean": self.mean,
+            "median": self.median,
+            "quantiles": self.quantiles.iter()
+                .map(|(tau, q)| serde_json::json!({ "q": tau, "value": q }))
+                .collect::<Vec<_>>(),
+            "min": self.min,
+            "max": self.max,
+            "sample_variance": self.variance,
+            "sample_std_dev": self.variance.sqrt(),
+            "skewness": self.skewness,
+            "excess_kurtosis": self.excess_kurtosis,
+        })
+    }
+}
+
+/// Mean, variance and excess kurtosis of the uniform distribution over `0..outcomes`.
+fn uniform_reference(outcomes: f64) -> serde_json::Value {
+    let squared = outcomes * outcomes;
+    serde_json::json!({
+        "mean": (outcomes - 1.0) / 2.0,
+        "variance": (squared - 1.0) / 12.0,
+        "skewness": 0.0,
+        "excess_kurtosis": -6.0 * (squared + 1.0) / (5.0 * (squared - 1.0)),
+    })
+}
+
+/// Probability of each digit sum 0..=9 * `num_len` when every digit is uniform and independent.
+fn digit_sum_probabilities(num_len: usize) -> Vec<f64> {
+    let mut probabilities = vec![1.0];
+    for _ in 0..num_len {
+        let mut next = vec![0.0; probabilities.len() + 9];
+        for (sum, p) in probabilities.iter().enumerate() {
+            for digit in 0..10 {
+                next[sum + digit] += p / 10.0;
+            }
+        }
+        probabilities = next;
+    }
+    probabilities
+}
+
+// --- Method 1: Descriptive Statistics ---
+/// Order statistics and sample moments of the numbers, of each digit position and of the digit sums,
+/// each next to what a uniform draw would give. The prediction is the mode.
+pub(super) fn run_comprehensive_analysis(numbers_str: &[String]) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 10 {
+        return Err(anyhow!("ข้อมูลไม่เพียงพอ ต้องการชุดตัวเลขอย่างน้อย 10 ชุด แต่พบเพียง {}", numbers_str.len()));
+    }
+    let rows = parse_digit_rows(numbers_str)?;
+    let num_len = rows[0].len();
+    let n = rows.len() as f64;
+
+    // The numbers are labels rather than magnitudes, so these only show whether draws spread evenly over the range.
+    let values: Vec<f64> = numbers_str.iter().filter_map(|s| s.parse::<f64>().ok()).collect();
+    let overall = Summary::of(&values);
+
+    let positions: Vec<serde_json::Value> = (0..num_len)
+        .map(|pos| {
+            let digits: Vec<f64> = rows.iter().map(|r| r[pos] as f64).collect();
+            let counts: Vec<usize> = (0..10u32).map(|d| rows.iter().filter(|r| r[pos] == d).count()).collect();
+            let summary = Summary::of(&digits);
+            serde_json::json!({
+                "position": pos + 1,
+                "mean": summary.mean,
+                "median": summary.median,
+                "sample_variance": summary.variance,
+                "digit_counts": counts,
+            })
+        })
+        .collect();
+
+    let sums: Vec<usize> = rows.iter().map(|r| r.iter().sum::<u32>() as usize).collect();
+    let sum_summary = Summary::of(&sums.iter().map(|&s| s as f64).collect::<Vec<_>>());
+    let sum_distribution: Vec<serde_json::Value> = digit_sum_probabilities(num_len).iter()
+        .enumerate()
+        .map(|(sum, p)| serde_json::json!({
+            "sum": sum,
+            "observed": sums.iter().filter(|&&s| s == sum).count(),
+            "expected": p * n,
+        }))
+        .collect();
+
+    let mut counts = HashMap::new();
+    for s in numbers_str {
+        *counts.entry(s.clone()).or_insert(0) += 1;
+    }
+    let prediction_distribution = probability::from_counts(counts.iter().map(|(n, &c)| (n, c)), 10u64.saturating_pow(num_len as u32));
+    let mut sorted_counts: Vec<_> = counts.into_iter().collect();
+    sorted_counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let top_prediction = sorted_counts[0].0.clone();
+    let top_count = sorted_counts[0].1;
+
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(top_prediction));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("ฐานนิยมทางสถิติ"));
+    let alternatives: Vec<String> = sorted_counts.iter().skip(1).take(4).map(|(val, _)| val.clone()).collect();
+    if !alternatives.is_empty() {
+        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
+    }
+
+    let digit_reference = uniform_reference(10.0);
+    let sum_reference = serde_json::json!({
+        "mean": 4.5 * num_len as f64,
+        "variance": 8.25 * num_len as f64,
+        "skewness": 0.0,
+        "excess_kurtosis": digit_reference["excess_kurtosis"].as_f64().unwrap_or(0.0) / num_len as f64,
+    });
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("ขนาดชุดข้อมูล".to_string(), numbers_str.len().to_string()),
+            ("จำนวนหลัก".to_string(), num_len.to_string()),
+            ("ค่าเฉลี่ย".to_string(), format!("{:.2}", overall.mean)),
+            ("มัธยฐาน".to_string(), format!("{:.2}", overall.median)),
+            ("ควอร์ไทล์ (Q1 - Q3)".to_string(), format!("{:.2} - {:.2}", overall.quantiles[1].1, overall.quantiles[2].1)),
+            ("ส่วนเบี่ยงเบนมาตรฐาน (ตัวอย่าง)".to_string(), format!("{:.2}", overall.variance.sqrt())),
+            ("ความเบ้".to_string(), format!("{:.3}", overall.skewness)),
+            ("ความโด่งส่วนเกิน".to_string(), format!("{:.3}", overall.excess_kurtosis)),
+            ("พิสัย".to_string(), format!("{} - {}", overall.min, overall.max)),
+            ("ฐานนิยม (พบบ่อยที่สุด)".to_string(), format!("{} ({} ครั้ง)", top_prediction, top_count)),
+            ("ค่าเฉลี่ยผลรวมของหลัก".to_string(), format!("{:.2} (ถ้าสุ่มจะเท่ากับ {:.1})", sum_summary.mean, 4.5 * num_len as f64)),
+        ]),
+        pattern_analysis: HashMap::from([
+            ("สถิติของตัวเลขทั้งชุด".to_string(), serde_json::json!({
+                "observed": overall.to_json(),
+                "uniform_reference": uniform_reference(10f64.powi(num_len as i32)),
+            })),
+            ("สถิติรายหลัก".to_string(), serde_json::json!({
+                "positions": positions,
+                "uniform_reference": digit_reference,
+            })),
+            ("ผลรวมของหลัก".to_string(), serde_json::json!({
+                "observed": sum_summary.to_json(),
+                "uniform_reference": sum_reference,
+                "distribution": sum_distribution,
+            })),
+        ]),
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), "คำนวณมัธยฐาน ควอร์ไทล์ ความแปรปรวนของตัวอย่าง (หารด้วย n - 1) ความเบ้ และความโด่ง ของตัวเลขทั้งชุด ของเลขแต่ละหลัก และของผลรวมของหลัก แล้วแสดงคู่กับค่าที่คาดไว้หากทุกหลักสุ่มอย่างสม่ำเสมอ (ค่าเฉลี่ย 4.5 และความแปรปรวน 8.25 ต่อหลัก)".to_string()),
+            ("การตีความ".to_string(), "เลขสลากเป็นเพียงป้ายชื่อ ไม่ใช่ปริมาณ ค่าเฉลี่ยของเลข 6 หลักจึงบอกได้เพียงว่าผลรางวัลกระจายทั่วช่วงหรือไม่ สถิติรายหลักและการแจกแจงของผลรวมของหลักมีความหมายมากกว่า เพราะเปรียบเทียบกับการสุ่มได้โดยตรง".to_string()),
+            ("ตรรกะการทำนาย".to_string(), format!("ตัวเลข '{}' ปรากฏบ่อยที่สุดในข้อมูลย้อนหลังที่ให้มา ({} ครั้ง)", top_prediction, top_count)),
+        ]),
+        evaluation: None,
+        prediction_distribution: Some(prediction_distribution),
+        target: None,
+    })
+}
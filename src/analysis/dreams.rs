@@ -0,0 +1,192 @@
+use super::gaps::{ending_gap_stats, DEFAULT_RECENT_WINDOW};
+use super::probability;
+use crate::models::{AnalysisParams, AnalysisResponse, DreamMatch};
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, HashMap};
+
+use statrs::distribution::{Binomial, DiscreteCDF};
+
+/// The dream book is read from this file on every request, so entries can be edited without rebuilding.
+const DREAM_BOOK_PATH: &str = "data/dream_book.json";
+
+/// Reads the dream book, a JSON object mapping each Thai keyword to its 2- and 3-digit numbers.
+pub(super) fn load_dream_book() -> Result<BTreeMap<String, Vec<String>>> {
+    let content = std::fs::read_to_string(DREAM_BOOK_PATH)
+        .map_err(|e| anyhow!("ไม่สามารถอ่านไฟล์ตำราทำนายฝัน {}: {}", DREAM_BOOK_PATH, e))?;
+    serde_json::from_str(&content).map_err(|e| anyhow!("รูปแบบไฟล์ตำราทำนายฝันไม่ถูกต้อง: {}", e))
+}
+
+/// Phrasings a dream is often told with, removed from the front of a term before it is looked up.
+const DREAM_PREFIXES: [&str; 4] = ["ฝันเห็น", "ฝันว่า", "ฝันถึง", "ฝัน"];
+
+/// A term reduced to the keyword it names: trimmed, without a leading "ฝันเห็น" or similar.
+fn keyword_of(term: &str) -> &str {
+    let term = term.trim();
+    DREAM_PREFIXES.iter()
+        .find_map(|prefix| term.strip_prefix(prefix))
+        .map_or(term, str::trim)
+}
+
+/// Every entry whose keyword is one of `terms`, so "ฝันเห็นงู" finds "งู". Thai is written without
+/// spaces, so a keyword inside a longer word is not a match: "พระจันทร์" does not find "พระ".
+/// Without terms, the whole dictionary is returned.
+pub(super) fn lookup(book: &BTreeMap<String, Vec<String>>, terms: &[String]) -> Vec<DreamMatch> {
+    book.iter()
+        .filter(|(keyword, _)| terms.is_empty() || terms.iter().any(|t| keyword_of(t) == keyword.as_str()))
+        .map(|(keyword, numbers)| DreamMatch { keyword: keyword.clone(), numbers: numbers.clone() })
+        .collect()
+}
+
+/// A dream-book number with its score against the history.
+struct ScoredNumber {
+    number: String,
+    frequency_ratio: f64,
+    current_gap: usize,
+    smoothed_share: f64,
+    details: serde_json::Value,
+}
+
+// --- Method 15: Dream Numbers (ทำนายฝัน) ---
+/// Looks up the numbers of the user's dream keywords and scores each against the history of the
+/// same-length endings: how often it was drawn compared with chance, and how long since it last appeared.
+pub(super) fn run_dream_number_analysis(numbers_str: &[String], params: &AnalysisParams) -> Result<AnalysisResponse> {
+    let terms: Vec<String> = params.dream_keywords.iter().flatten()
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .collect();
+    if terms.is_empty() {
+        return Err(anyhow!("กรุณาระบุคำทำนายฝันอย่างน้อย 1 คำ (dream_keywords)"));
+    }
+    if numbers_str.len() < 10 {
+        return Err(anyhow!("การวิเคราะห์เลขทำนายฝันต้องการข้อมูลอย่างน้อย 10 งวด แต่พบเพียง {}", numbers_str.len()));
+    }
+    if !numbers_str.iter().all(|n| n.len() >= 2 && n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("ตัวเลขทั้งหมดต้องประกอบด้วยตัวเลขอย่างน้อย 2 หลักสำหรับการวิเคราะห์เลขทำนายฝัน"));
+    }
+
+    let matches = lookup(&load_dream_book()?, &terms);
+    if matches.is_empty() {
+        return Err(anyhow!("ไม่พบคำว่า '{}' ในตำราทำนายฝัน", terms.join(", ")));
+    }
+    // Each number is scored once, remembering every keyword that led to it.
+    let mut candidates: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for m in &matches {
+        for number in &m.numbers {
+            candidates.entry(number.as_str()).or_default().push(m.keyword.as_str());
+        }
+    }
+
+    let n = numbers_str.len();
+    let min_len = numbers_str.iter().map(|s| s.len()).min().unwrap_or(2);
+    let mut stats_by_len = HashMap::new();
+    let mut scored = Vec::new();
+    let mut skipped = Vec::new();
+    for (number, keywords) in &candidates {
+        let digits = number.len();
+        if digits == 0 || digits > min_len || !number.chars().all(|c| c.is_ascii_digit()) {
+            skipped.push(number.to_string());
+            continue;
+        }
+        let stats = stats_by_len.entry(digits).or_insert_with(|| ending_gap_stats(numbers_str, digits, DEFAULT_RECENT_WINDOW.min(n)));
+        let Some(stat) = stats.iter().find(|s| s.ending == *number) else { continue };
+
+        let outcomes = 10usize.pow(digits as u32);
+        let uniform = 1.0 / outcomes as f64;
+        // Add-one smoothing keeps a single lucky hit in a short history from dominating the ranking.
+        let smoothed_share = (stat.total_count + 1) as f64 / (n + outcomes) as f64;
+        let p_value = match (stat.total_count, Binomial::new(uniform, n as u64)) {
+            (0, _) | (_, Err(_)) => 1.0,
+            (count, Ok(dist)) => dist.sf(count as u64 - 1),
+        };
+        let mut details = stat.to_json(outcomes);
+        details["keywords"] = serde_json::json!(keywords);
+        details["expected_count"] = serde_json::json!(n as f64 * uniform);
+        details["frequency_ratio"] = serde_json::json!(smoothed_share / uniform);
+        details["p_value"] = serde_json::json!(p_value);
+        scored.push(ScoredNumber {
+            number: number.to_string(),
+            frequency_ratio: smoothed_share / uniform,
+            current_gap: stat.current_gap,
+            smoothed_share,
+            details,
+        });
+    }
+    if scored.is_empty() {
+        return Err(anyhow!("เลขจากตำราทำนายฝันยาวกว่าตัวเลขย้อนหลังที่ให้มา จึงไม่สามารถเปรียบเทียบได้"));
+    }
+    scored.sort_by(|a, b| b.frequency_ratio.partial_cmp(&a.frequency_ratio).unwrap()
+        .then(a.current_gap.cmp(&b.current_gap))
+        .then(a.number.cmp(&b.number)));
+
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(scored[0].number));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("เลขทำนายฝัน (ตำราทำนายฝัน + สถิติ)"));
+    let alternatives: Vec<&String> = scored.iter().skip(1).take(4).map(|s| &s.number).collect();
+    if !alternatives.is_empty() {
+        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
+    }
+
+    let mut pattern_analysis = HashMap::from([
+        ("คำที่พบในตำรา".to_string(), serde_json::json!(matches)),
+        ("คะแนนของเลขทำนายฝัน".to_string(), serde_json::json!(scored.iter().map(|s| &s.details).collect::<Vec<_>>())),
+    ]);
+    if !skipped.is_empty() {
+        pattern_analysis.insert("เลขที่ไม่สามารถเปรียบเทียบได้".to_string(), serde_json::json!(skipped));
+    }
+
+    // The distribution covers the 2-digit numbers only, since mixing lengths would mix different supports.
+    let two_digit_shares: Vec<(String, f64)> = scored.iter()
+        .filter(|s| s.number.len() == 2)
+        .map(|s| (s.number.clone(), s.smoothed_share))
+        .collect();
+    let prediction_distribution = (!two_digit_shares.is_empty()).then(|| probability::from_probabilities(two_digit_shares, 100, n));
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("จำนวนงวด".to_string(), n.to_string()),
+            ("คำทำนายฝันที่ระบุ".to_string(), terms.join(", ")),
+            ("จำนวนคำที่พบในตำรา".to_string(), matches.len().to_string()),
+            ("จำนวนเลขที่ให้คะแนน".to_string(), scored.len().to_string()),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), format!("ค้นหาคำทำนายฝันในตำรา ({}) ซึ่งแก้ไขเพิ่มเติมได้ แล้วเปรียบเทียบเลขแต่ละตัวกับเลขท้ายที่มีความยาวเท่ากันในข้อมูลย้อนหลัง", DREAM_BOOK_PATH)),
+            ("การให้คะแนน".to_string(), "frequency_ratio คือความถี่ที่ออก (บวกหนึ่งเพื่อปรับให้เรียบ) เทียบกับอัตราการสุ่ม โดย 1.0 หมายถึงออกบ่อยเท่าที่คาดไว้ ค่า p ใช้การแจกแจงทวินามเพื่อบอกว่าการออกบ่อยขนาดนี้เกิดจากความบังเอิญได้ง่ายเพียงใด ส่วน current_gap และ overdue_ratio บอกว่าเลขนั้นไม่ออกมานานเท่าใด".to_string()),
+            ("ข้อควรระวัง".to_string(), "ตำราทำนายฝันเป็นความเชื่อทางวัฒนธรรม เช่นเดียวกับศาสตร์แห่งตัวเลข การให้คะแนนนี้เพียงแสดงประวัติของเลขที่เลือก ไม่ได้ทำให้เลขเหล่านั้นมีโอกาสออกมากขึ้น".to_string()),
+        ]),
+        evaluation: None,
+        prediction_distribution,
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book() -> BTreeMap<String, Vec<String>> {
+        ["งู", "พระ", "ปลา", "ไฟไหม้"].iter().map(|k| (k.to_string(), vec!["12".to_string()])).collect()
+    }
+
+    fn found(terms: &[&str]) -> Vec<String> {
+        let terms: Vec<String> = terms.iter().map(|t| t.to_string()).collect();
+        lookup(&book(), &terms).into_iter().map(|m| m.keyword).collect()
+    }
+
+    #[test]
+    fn keywords_match_whole_terms() {
+        assert_eq!(found(&[" งู ", "ไฟไหม้"]), ["งู", "ไฟไหม้"]);
+        assert_eq!(found(&["ฝันเห็นงู", "ฝันว่า ปลา"]), ["งู", "ปลา"]);
+    }
+
+    #[test]
+    fn keywords_inside_longer_words_do_not_match() {
+        assert!(found(&["พระจันทร์", "ปลาย", "ฝันเห็นงูใหญ่"]).is_empty());
+    }
+
+    #[test]
+    fn no_terms_returns_the_whole_book() {
+        assert_eq!(found(&[]).len(), 4);
+    }
+}
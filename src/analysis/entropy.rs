@@ -0,0 +1,331 @@
+use super::parse_digit_rows;
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+use statrs::distribution::{Binomial, ChiSquared, ContinuousCDF, Discrete};
+
+/// Significance level for the goodness-of-fit tests before the Bonferroni correction.
+const ALPHA: f64 = 0.05;
+/// Entropy below this fraction of what uniform draws of the same size would give is reported as a
+/// collapse. Real draws stay close to 1; a scraper that repeats a row or drops digits falls far below.
+const COLLAPSE_FRACTION: f64 = 0.8;
+/// Chi-square p-values are only reported when every category expects at least this many draws.
+const MIN_EXPECTED_COUNT: f64 = 5.0;
+/// Benford probabilities are summed exactly up to this significant digit, which is already uniform to
+/// within 1e-5. The exact sum for the k-th digit takes 9 * 10^(k-2) terms, so later digits use 0.1.
+const MAX_BENFORD_DIGIT: usize = 6;
+
+/// Plug-in Shannon entropy in bits of the observed counts.
+fn shannon_entropy(counts: &[usize]) -> f64 {
+    let n: usize = counts.iter().sum();
+    counts.iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / n as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Expected plug-in entropy of `n` uniform draws over `categories` outcomes. With few draws it is well
+/// below log2(categories), so comparing against this value does not mistake a short history for a collapse.
+fn expected_uniform_entropy(n: usize, categories: usize) -> Result<f64> {
+    let dist = Binomial::new(1.0 / categories as f64, n as u64).map_err(|e| anyhow!("ไม่สามารถสร้างการแจกแจงทวินาม: {}", e))?;
+    let per_category: f64 = (1..=n as u64)
+        .map(|c| {
+            let p = c as f64 / n as f64;
+            dist.pmf(c) * -p * p.log2()
+        })
+        .sum();
+    Ok(categories as f64 * per_category)
+}
+
+/// Pearson's chi-square goodness-of-fit test. Categories that expect nothing are left out.
+/// Returns (statistic, degrees of freedom, p-value), or `None` when fewer than two categories expect
+/// anything, e.g. Benford's law against numbers that are all zero, which leaves nothing to test.
+fn chi_square_fit(observed: &[usize], expected: &[f64]) -> Result<Option<(f64, f64, f64)>> {
+    let (statistic, categories) = observed.iter().zip(expected)
+        .filter(|(_, &e)| e > 0.0)
+        .fold((0.0, 0), |(sum, k), (&o, &e)| (sum + (o as f64 - e).powi(2) / e, k + 1));
+    if categories < 2 {
+        return Ok(None);
+    }
+    let dof = (categories - 1) as f64;
+    let dist = ChiSquared::new(dof).map_err(|e| anyhow!("ไม่สามารถสร้างการแจกแจงไคสแควร์: {}", e))?;
+    Ok(Some((statistic, dof, dist.sf(statistic))))
+}
+
+/// Entropy of one set of categories next to its maximum and its expectation under uniform draws.
+/// The G-test statistic follows from the entropy deficit: G = 2 n ln 2 (log2 K - H).
+struct EntropyCheck {
+    entropy: f64,
+    max_entropy: f64,
+    expected_entropy: f64,
+    p_value: Option<f64>,
+}
+
+impl EntropyCheck {
+    fn of(counts: &[usize]) -> Result<EntropyCheck> {
+        let n: usize = counts.iter().sum();
+        let categories = counts.len();
+        let entropy = shannon_entropy(counts);
+        let max_entropy = (categories as f64).log2();
+        let p_value = if n as f64 / categories as f64 >= MIN_EXPECTED_COUNT {
+            let g = 2.0 * n as f64 * std::f64::consts::LN_2 * (max_entropy - entropy);
+            let dist = ChiSquared::new((categories - 1) as f64).map_err(|e| anyhow!("ไม่สามารถสร้างการแจกแจงไคสแควร์: {}", e))?;
+            Some(dist.sf(g))
+        } else {
+            None
+        };
+        Ok(EntropyCheck { entropy, max_entropy, expected_entropy: expected_uniform_entropy(n, categories)?, p_value })
+    }
+
+    fn collapsed(&self) -> bool {
+        self.entropy < COLLAPSE_FRACTION * self.expected_entropy
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "entropy_bits": self.entropy,
+            "max_entropy_bits": self.max_entropy,
+            "normalized_entropy": self.entropy / self.max_entropy,
+            "expected_entropy_if_uniform": self.expected_entropy,
+            "ratio_to_expected": self.entropy / self.expected_entropy,
+            "g_test_p_value": self.p_value,
+            "collapsed": self.collapsed(),
+        })
+    }
+}
+
+/// Benford probability of digit `d` at the `k`-th significant digit (k = 1 is the leading digit).
+/// Later digits approach the uniform distribution quickly, so past `MAX_BENFORD_DIGIT` it is 0.1.
+fn benford_probability(k: usize, d: u32) -> f64 {
+    if k == 1 {
+        return if d == 0 { 0.0 } else { (1.0 + 1.0 / d as f64).log10() };
+    }
+    if k > MAX_BENFORD_DIGIT {
+        return 0.1;
+    }
+    let start = 10u64.pow(k as u32 - 2);
+    (start..start * 10).map(|j| (1.0 + 1.0 / (10 * j + d as u64) as f64).log10()).sum()
+}
+
+/// A digit compared with both distributions. The uniform expectation applies to the digit as printed,
+/// leading zeros included, while Benford's law applies to significant digits, so the two tests may
+/// count different digits of the same number.
+struct DigitComparison {
+    printed: [usize; 10],
+    uniform: [f64; 10],
+    significant: [usize; 10],
+    benford: [f64; 10],
+}
+
+impl DigitComparison {
+    fn new(n: usize) -> DigitComparison {
+        DigitComparison { printed: [0; 10], uniform: [n as f64 / 10.0; 10], significant: [0; 10], benford: [0.0; 10] }
+    }
+
+    fn to_json(&self) -> Result<serde_json::Value> {
+        let test = |observed: &[usize; 10], expected: &[f64; 10]| -> Result<serde_json::Value> {
+            let fit = chi_square_fit(observed, expected)?;
+            Ok(serde_json::json!({
+                "observed": observed,
+                "expected": expected,
+                "testable": fit.is_some(),
+                "statistic": fit.map(|(statistic, _, _)| statistic),
+                "degrees_of_freedom": fit.map(|(_, dof, _)| dof),
+                "p_value": fit.map(|(_, _, p_value)| p_value),
+            }))
+        };
+        Ok(serde_json::json!({
+            "uniform": test(&self.printed, &self.uniform)?,
+            "benford": test(&self.significant, &self.benford)?,
+        }))
+    }
+
+    fn uniform_p_value(&self) -> Result<Option<f64>> {
+        Ok(chi_square_fit(&self.printed, &self.uniform)?.map(|(_, _, p_value)| p_value))
+    }
+
+    /// "การสุ่มสม่ำเสมอ" or "กฎของเบนฟอร์ด", whichever gives the smaller chi-square per degree of freedom.
+    /// A distribution that cannot be tested never wins.
+    fn closer_fit(&self) -> Result<&'static str> {
+        let per_dof = |fit: Option<(f64, f64, f64)>| fit.map(|(statistic, dof, _)| statistic / dof);
+        let uniform = per_dof(chi_square_fit(&self.printed, &self.uniform)?);
+        let benford = per_dof(chi_square_fit(&self.significant, &self.benford)?);
+        Ok(match (uniform, benford) {
+            (Some(u), Some(b)) if u > b => "กฎของเบนฟอร์ด",
+            (None, Some(_)) => "กฎของเบนฟอร์ด",
+            (None, None) => "ทดสอบไม่ได้",
+            _ => "การสุ่มสม่ำเสมอ",
+        })
+    }
+}
+
+/// First and last digits of every number. For Benford's law the first digit is the first non-zero
+/// one, and the last digit follows the law of its significant position, e.g. the 4th digit of "001234".
+fn compare_first_and_last(rows: &[Vec<u32>]) -> (DigitComparison, DigitComparison) {
+    let mut first = DigitComparison::new(rows.len());
+    let mut last = DigitComparison::new(rows.len());
+    let mut benford_by_position: HashMap<usize, [f64; 10]> = HashMap::new();
+
+    for row in rows {
+        let last_digit = row[row.len() - 1] as usize;
+        first.printed[row[0] as usize] += 1;
+        last.printed[last_digit] += 1;
+        // An all-zero number has no significant digits, so it only counts towards the uniform tests.
+        let Some(leading) = row.iter().position(|&d| d != 0) else { continue };
+
+        first.significant[row[leading] as usize] += 1;
+        last.significant[last_digit] += 1;
+        let significant_len = row.len() - leading;
+        for k in [1, significant_len] {
+            benford_by_position.entry(k).or_insert_with(|| std::array::from_fn(|d| benford_probability(k, d as u32)));
+        }
+        for (expected, p) in first.benford.iter_mut().zip(benford_by_position[&1]) {
+            *expected += p;
+        }
+        for (expected, p) in last.benford.iter_mut().zip(benford_by_position[&significant_len]) {
+            *expected += p;
+        }
+    }
+    (first, last)
+}
+
+// --- Method 16: Entropy & Benford Diagnostics ---
+/// Shannon entropy of every digit position and of the ending distributions, and first/last digit
+/// comparisons with uniform and Benford expectations. Meant as an integrity check: a scraping bug
+/// usually shows up as an entropy collapse long before any test of randomness notices.
+pub(super) fn run_entropy_diagnostics(numbers_str: &[String]) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 20 {
+        return Err(anyhow!("การตรวจเอนโทรปีต้องการข้อมูลอย่างน้อย 20 ชุด แต่พบเพียง {}", numbers_str.len()));
+    }
+    let rows = parse_digit_rows(numbers_str)?;
+    let num_len = rows[0].len();
+
+    let mut pattern_analysis = HashMap::new();
+    let mut p_values = Vec::new();
+    let mut collapsed = Vec::new();
+
+    for pos in 0..num_len {
+        let mut counts = [0usize; 10];
+        for row in &rows {
+            counts[row[pos] as usize] += 1;
+        }
+        let check = EntropyCheck::of(&counts)?;
+        let part = format!("หลักที่ {}", pos + 1);
+        p_values.extend(check.p_value);
+        pattern_analysis.insert(format!("เอนโทรปี {}", part), check.to_json());
+        if check.collapsed() {
+            collapsed.push(part);
+        }
+    }
+
+    for digits in [2, 3].into_iter().filter(|&d| d <= num_len) {
+        let mut counts = vec![0usize; 10usize.pow(digits as u32)];
+        for row in &rows {
+            let ending = row[num_len - digits..].iter().fold(0usize, |acc, &d| acc * 10 + d as usize);
+            counts[ending] += 1;
+        }
+        let check = EntropyCheck::of(&counts)?;
+        let part = format!("เลขท้าย {} ตัว", digits);
+        p_values.extend(check.p_value);
+        pattern_analysis.insert(format!("เอนโทรปี {}", part), check.to_json());
+        if check.collapsed() {
+            collapsed.push(part);
+        }
+    }
+
+    let (first, last) = compare_first_and_last(&rows);
+    p_values.extend(first.uniform_p_value()?.into_iter().chain(last.uniform_p_value()?));
+    pattern_analysis.insert("หลักแรก: สม่ำเสมอ vs เบนฟอร์ด".to_string(), first.to_json()?);
+    pattern_analysis.insert("หลักสุดท้าย: สม่ำเสมอ vs เบนฟอร์ด".to_string(), last.to_json()?);
+
+    // Bonferroni correction over every uniformity test; the Benford fits are descriptive only.
+    let corrected_alpha = ALPHA / p_values.len() as f64;
+    let min_p_value = p_values.iter().cloned().fold(1.0, f64::min);
+    let verdict = if !collapsed.is_empty() {
+        format!("เอนโทรปียุบตัวใน {} ข้อมูลอาจผิดพลาดจากการดึงข้อมูล เช่น มีงวดซ้ำหรือหลักหายไป", collapsed.join(", "))
+    } else if min_p_value < corrected_alpha {
+        "เอนโทรปีไม่ยุบตัว แต่การแจกแจงของบางหลักแตกต่างจากการสุ่มอย่างมีนัยสำคัญ".to_string()
+    } else {
+        "ไม่พบความผิดปกติ เอนโทรปีใกล้เคียงกับการสุ่มทุกหลัก".to_string()
+    };
+
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(verdict));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("ตรวจสอบเอนโทรปีและกฎของเบนฟอร์ด"));
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("ขนาดชุดข้อมูล".to_string(), rows.len().to_string()),
+            ("จำนวนหลัก".to_string(), num_len.to_string()),
+            ("จำนวนส่วนที่เอนโทรปียุบตัว".to_string(), collapsed.len().to_string()),
+            ("ระดับนัยสำคัญ (หลังปรับ Bonferroni)".to_string(), format!("{:.4}", corrected_alpha)),
+            ("p-value ต่ำสุด".to_string(), format!("{:.4}", min_p_value)),
+            ("หลักแรกใกล้เคียงกับ".to_string(), first.closer_fit()?.to_string()),
+            ("หลักสุดท้ายใกล้เคียงกับ".to_string(), last.closer_fit()?.to_string()),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), "เอนโทรปีของแชนนอน (หน่วยบิต) วัดว่าตัวเลขกระจายตัวเพียงใด ค่าสูงสุดคือ log2 ของจำนวนค่าที่เป็นไปได้ (3.32 บิตต่อหลัก) แต่ข้อมูลที่มีจำนวนงวดจำกัดจะได้ค่าต่ำกว่านี้เสมอแม้จะสุ่มจริง จึงเปรียบเทียบกับค่าที่คาดไว้ของการสุ่มที่มีจำนวนงวดเท่ากันด้วย".to_string()),
+            ("การตรวจคุณภาพข้อมูล".to_string(), format!("หากเอนโทรปีต่ำกว่า {:.0}% ของค่าที่คาดไว้ จะถือว่ายุบตัว ซึ่งมักเกิดจากข้อผิดพลาดในการดึงข้อมูล เช่น อ่านงวดเดิมซ้ำ หรือหลักถูกตัดหาย ค่า p ของ G-test จะแสดงเฉพาะเมื่อแต่ละค่าคาดว่าจะพบอย่างน้อย {} ครั้ง", COLLAPSE_FRACTION * 100.0, MIN_EXPECTED_COUNT)),
+            ("กฎของเบนฟอร์ด".to_string(), "กฎของเบนฟอร์ดใช้กับตัวเลขที่เกิดขึ้นตามธรรมชาติ เช่น ยอดเงินหรือจำนวนประชากร ซึ่งหลักแรกมักเป็นเลข 1 ผลสลากที่สุ่มอย่างยุติธรรมควรใกล้เคียงกับการสุ่มสม่ำเสมอมากกว่า ส่วนหลักสุดท้ายตามกฎของเบนฟอร์ดแทบไม่ต่างจากการสุ่มสม่ำเสมอ".to_string()),
+        ]),
+        evaluation: None,
+        prediction_distribution: None,
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benford_probabilities_follow_the_law() {
+        assert_eq!(benford_probability(1, 0), 0.0);
+        assert!((benford_probability(1, 1) - 2f64.log10()).abs() < 1e-12);
+        assert!((benford_probability(1, 9) - (10.0f64 / 9.0).log10()).abs() < 1e-12);
+        // The second digit is 0 about 12% of the time and 9 about 8.5%.
+        assert!((benford_probability(2, 0) - 0.1197).abs() < 1e-4);
+        assert!((benford_probability(2, 9) - 0.0850).abs() < 1e-4);
+        for k in 1..=4 {
+            let total: f64 = (0..10).map(|d| benford_probability(k, d)).sum();
+            assert!((total - 1.0).abs() < 1e-9, "k = {}", k);
+        }
+        // By the fourth digit the law is all but uniform.
+        assert!((0..10).all(|d| (benford_probability(4, d) - 0.1).abs() < 1e-3));
+        assert!((0..10).all(|d| (benford_probability(MAX_BENFORD_DIGIT, d) - 0.1).abs() < 1e-5));
+    }
+
+    #[test]
+    fn long_numbers_use_the_uniform_limit() {
+        assert_eq!(benford_probability(MAX_BENFORD_DIGIT + 1, 3), 0.1);
+        assert_eq!(benford_probability(40, 9), 0.1);
+        let long: Vec<String> = (0..30).map(|i| format!("{:040}", i * 7_919 + 1)).collect();
+        assert!(run_entropy_diagnostics(&long).is_ok());
+    }
+
+    #[test]
+    fn fit_without_two_expected_categories_is_not_testable() {
+        assert!(chi_square_fit(&[0; 10], &[0.0; 10]).unwrap().is_none());
+        let mut expected = [0.0; 10];
+        expected[1] = 20.0;
+        assert!(chi_square_fit(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0], &expected).unwrap().is_none());
+    }
+
+    #[test]
+    fn all_zero_numbers_collapse_without_a_benford_test() {
+        let numbers = vec!["000000".to_string(); 30];
+        let response = run_entropy_diagnostics(&numbers).unwrap();
+        let first = &response.pattern_analysis["หลักแรก: สม่ำเสมอ vs เบนฟอร์ด"];
+        assert_eq!(first["benford"]["testable"], false);
+        assert!(first["benford"]["p_value"].is_null());
+        assert_eq!(first["uniform"]["testable"], true);
+        assert_eq!(response.statistical_summary["หลักแรกใกล้เคียงกับ"], "การสุ่มสม่ำเสมอ");
+        assert!(response.prediction_output["PREDICTION"].as_str().unwrap().starts_with("เอนโทรปียุบตัว"));
+    }
+}
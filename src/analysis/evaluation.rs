@@ -0,0 +1,122 @@
+use super::classifiers::{argmax, fit_classifier, ModelConfig, ModelKind, CHANCE_BASELINE, NUM_CLASSES};
+use crate::models::{ClassMetrics, CvStrategy, EvaluationReport, TargetAccuracy};
+use anyhow::{Result, anyhow};
+
+use ndarray::{Array1, Array2, Axis};
+use rand::rngs::StdRng;
+
+/// Number of folds when the request does not set `cv_folds`.
+pub(super) const DEFAULT_CV_FOLDS: usize = 5;
+/// Probabilities are clipped to this floor so a single confident miss cannot make the log-loss infinite.
+const LOG_LOSS_EPS: f64 = 1e-15;
+
+/// Train/test row indices for every fold. K-fold uses contiguous blocks (no shuffling, so neighbouring
+/// draws stay together); time-series CV trains on an expanding window and tests on the block after it.
+fn fold_splits(n_samples: usize, folds: usize, strategy: CvStrategy) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let blocks = match strategy {
+        CvStrategy::KFold => folds,
+        CvStrategy::TimeSeries => folds + 1,
+    };
+    let bounds: Vec<usize> = (0..=blocks).map(|b| b * n_samples / blocks).collect();
+    match strategy {
+        CvStrategy::KFold => (0..folds)
+            .map(|f| {
+                let test: Vec<usize> = (bounds[f]..bounds[f + 1]).collect();
+                let train = (0..n_samples).filter(|i| !(bounds[f]..bounds[f + 1]).contains(i)).collect();
+                (train, test)
+            })
+            .collect(),
+        CvStrategy::TimeSeries => (1..=folds)
+            .map(|f| ((0..bounds[f]).collect(), (bounds[f]..bounds[f + 1]).collect()))
+            .collect(),
+    }
+}
+
+/// Cross-validates `kind` on every target and pools the out-of-fold predictions into one report.
+/// Each target is a (label, class per row of `x`) pair, e.g. one per digit position.
+pub(super) fn cross_validate(
+    kind: ModelKind,
+    config: &ModelConfig,
+    x: &Array2<f64>,
+    targets: &[(String, Array1<usize>)],
+    strategy: CvStrategy,
+    folds: usize,
+    rng: &mut StdRng,
+) -> Result<EvaluationReport> {
+    let n_samples = x.nrows();
+    let folds = folds.max(2);
+    if n_samples < folds * 4 {
+        return Err(anyhow!("ข้อมูล {} แถวไม่เพียงพอสำหรับการตรวจสอบไขว้ {} fold", n_samples, folds));
+    }
+
+    let mut confusion = vec![vec![0usize; NUM_CLASSES]; NUM_CLASSES];
+    let (mut n_predictions, mut correct, mut baseline_correct) = (0usize, 0usize, 0usize);
+    let (mut log_loss, mut baseline_log_loss) = (0.0, 0.0);
+    let mut per_target = Vec::with_capacity(targets.len());
+
+    for (label, y) in targets {
+        let mut target_correct = 0;
+        let mut target_predictions = 0;
+        for (train, test) in fold_splits(n_samples, folds, strategy) {
+            let (train_x, test_x) = (x.select(Axis(0), &train), x.select(Axis(0), &test));
+            let (train_y, test_y) = (y.select(Axis(0), &train), y.select(Axis(0), &test));
+
+            // The majority-class baseline always predicts the most common training class, and its
+            // probabilities are the (add-one smoothed) training class frequencies.
+            let mut class_counts = [0.0; NUM_CLASSES];
+            for &c in train_y.iter() {
+                class_counts[c] += 1.0;
+            }
+            let majority = argmax(&class_counts);
+            let prior_total = train_y.len() as f64 + NUM_CLASSES as f64;
+
+            let model = fit_classifier(kind, &train_x, &train_y, config, rng)?;
+            for (probabilities, &actual) in model.predict_proba(&test_x).iter().zip(test_y.iter()) {
+                let predicted = argmax(probabilities);
+                confusion[actual][predicted] += 1;
+                target_correct += usize::from(predicted == actual);
+                baseline_correct += usize::from(majority == actual);
+                log_loss -= probabilities[actual].max(LOG_LOSS_EPS).ln();
+                baseline_log_loss -= ((class_counts[actual] + 1.0) / prior_total).ln();
+                target_predictions += 1;
+            }
+        }
+        per_target.push(TargetAccuracy {
+            target: label.clone(),
+            accuracy: target_correct as f64 / target_predictions as f64,
+        });
+        correct += target_correct;
+        n_predictions += target_predictions;
+    }
+
+    let per_class = (0..NUM_CLASSES)
+        .map(|c| {
+            let predicted_as_c: usize = confusion.iter().map(|row| row[c]).sum();
+            let support: usize = confusion[c].iter().sum();
+            ClassMetrics {
+                class: c,
+                precision: (predicted_as_c > 0).then(|| confusion[c][c] as f64 / predicted_as_c as f64),
+                recall: (support > 0).then(|| confusion[c][c] as f64 / support as f64),
+                support,
+            }
+        })
+        .collect();
+
+    let n = n_predictions as f64;
+    Ok(EvaluationReport {
+        model: kind.display_name().to_string(),
+        strategy,
+        folds,
+        predictions: n_predictions,
+        accuracy: correct as f64 / n,
+        log_loss: log_loss / n,
+        majority_baseline_accuracy: baseline_correct as f64 / n,
+        majority_baseline_log_loss: baseline_log_loss / n,
+        chance_accuracy: CHANCE_BASELINE,
+        uniform_log_loss: (NUM_CLASSES as f64).ln(),
+        confusion_matrix: confusion,
+        per_class,
+        per_target,
+        target: None,
+    })
+}
@@ -0,0 +1,123 @@
+use super::calculate_digital_root;
+use chrono::{Datelike, NaiveDate};
+use ndarray::{Array1, Array2};
+use serde::Serialize;
+
+/// Number of previous draws included as lagged features when the request does not set one.
+pub const DEFAULT_FEATURE_LAGS: usize = 3;
+
+/// A feature matrix in which row `i` describes the history up to and including draw `first_draw + i`.
+/// Row `i` is therefore used to predict draw `first_draw + i + 1`, and the last row predicts the next,
+/// not yet drawn, result.
+#[derive(Serialize)]
+pub struct FeatureMatrix {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+    pub first_draw: usize,
+}
+
+impl FeatureMatrix {
+    /// Training records: every row except the last, whose target is not known yet.
+    pub fn training_records(&self) -> Array2<f64> {
+        self.to_array(&self.rows[..self.rows.len() - 1])
+    }
+
+    /// The single row describing the latest draw, used for the final prediction.
+    pub fn latest_record(&self) -> Array2<f64> {
+        self.to_array(&self.rows[self.rows.len() - 1..])
+    }
+
+    /// Targets aligned with `training_records`, taken from the draw that follows each row.
+    pub fn training_targets(&self, targets: &[usize]) -> Array1<usize> {
+        Array1::from(targets[self.first_draw + 1..].to_vec())
+    }
+
+    fn to_array(&self, rows: &[Vec<f64>]) -> Array2<f64> {
+        Array2::from_shape_vec((rows.len(), self.columns.len()), rows.iter().flatten().copied().collect())
+            .expect("every feature row has one value per column")
+    }
+
+    /// Renders the matrix as CSV with a header row, for use in external notebooks.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("draw_index,{}\n", self.columns.join(","));
+        for (i, row) in self.rows.iter().enumerate() {
+            let values: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            csv.push_str(&format!("{},{}\n", self.first_draw + i, values.join(",")));
+        }
+        csv
+    }
+}
+
+/// Builds the feature matrix shared by every ML analyzer from ordered digit rows (oldest draw first).
+pub struct FeatureBuilder {
+    lags: usize,
+}
+
+impl FeatureBuilder {
+    pub fn new(lags: usize) -> Self {
+        FeatureBuilder { lags: lags.max(1) }
+    }
+
+    /// The fewest draws needed to produce at least one training row.
+    pub fn min_draws(&self) -> usize {
+        self.lags + 1
+    }
+
+    /// Builds one row per draw that has `lags` draws of history. Calendar features are added when
+    /// `dates` holds a date for every draw; they describe the draw being predicted, and the date of
+    /// the next draw is estimated from the most recent interval between draws.
+    pub fn build(&self, digit_rows: &[Vec<u32>], dates: Option<&[NaiveDate]>) -> FeatureMatrix {
+        let num_len = digit_rows.first().map_or(0, |r| r.len());
+        let dates = dates.filter(|d| d.len() == digit_rows.len() && d.len() >= 2);
+
+        let mut columns = Vec::new();
+        for lag in 1..=self.lags {
+            columns.extend((1..=num_len).map(|pos| format!("digit{}_lag{}", pos, lag)));
+            for name in ["digit_sum", "digital_root", "even_count", "high_count"] {
+                columns.push(format!("{}_lag{}", name, lag));
+            }
+        }
+        columns.extend((0..10).map(|d| format!("gap_since_digit{}", d)));
+        if dates.is_some() {
+            columns.extend(["target_month", "target_day", "target_weekday", "target_second_half"].map(String::from));
+        }
+
+        // last_seen[d] is the index of the latest draw (so far) that contained digit d anywhere.
+        let mut last_seen: [Option<usize>; 10] = [None; 10];
+        let mut rows = Vec::new();
+        let first_draw = self.lags - 1;
+
+        for (t, digits) in digit_rows.iter().enumerate() {
+            for &d in digits {
+                last_seen[d as usize] = Some(t);
+            }
+            if t < first_draw {
+                continue;
+            }
+
+            let mut row = Vec::with_capacity(columns.len());
+            for lag in 0..self.lags {
+                let lagged = &digit_rows[t - lag];
+                row.extend(lagged.iter().map(|&d| d as f64));
+                let number: String = lagged.iter().map(|d| d.to_string()).collect();
+                row.push(lagged.iter().sum::<u32>() as f64);
+                row.push(calculate_digital_root(&number) as f64);
+                row.push(lagged.iter().filter(|&&d| d % 2 == 0).count() as f64);
+                row.push(lagged.iter().filter(|&&d| d >= 5).count() as f64);
+            }
+            row.extend(last_seen.iter().map(|seen| seen.map_or(t + 1, |s| t - s) as f64));
+
+            if let Some(dates) = dates {
+                let target_date = dates.get(t + 1).copied()
+                    .unwrap_or_else(|| dates[t] + (dates[t] - dates[t - 1]));
+                row.push(target_date.month() as f64);
+                row.push(target_date.day() as f64);
+                row.push(target_date.weekday().num_days_from_monday() as f64);
+                row.push(if target_date.day() > 15 { 1.0 } else { 0.0 });
+            }
+            rows.push(row);
+        }
+
+        FeatureMatrix { columns, rows, first_draw }
+    }
+}
@@ -0,0 +1,133 @@
+use super::probability;
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+/// Number of most recent draws used for the "hot" frequency when the request does not set one.
+pub(super) const DEFAULT_RECENT_WINDOW: usize = 20;
+/// How many endings are listed in each hot and cold ranking.
+const RANKING_SIZE: usize = 10;
+
+/// Appearance history of one ending (e.g. "07" or "913") across the ordered draws.
+pub(super) struct EndingStats {
+    pub(super) ending: String,
+    pub(super) total_count: usize,
+    pub(super) recent_count: usize,
+    /// Draws since the ending last appeared. Equals the history length if it never appeared.
+    pub(super) current_gap: usize,
+    pub(super) average_gap: Option<f64>,
+    pub(super) longest_gap: usize,
+}
+
+impl EndingStats {
+    pub(super) fn to_json(&self, expected_gap: usize) -> serde_json::Value {
+        serde_json::json!({
+            "ending": self.ending,
+            "total_count": self.total_count,
+            "recent_count": self.recent_count,
+            "current_gap": self.current_gap,
+            "average_gap": self.average_gap.map(|g| (g * 100.0).round() / 100.0),
+            "longest_gap": self.longest_gap,
+            "overdue_ratio": (self.current_gap as f64 / expected_gap as f64 * 100.0).round() / 100.0,
+        })
+    }
+}
+
+/// Computes gap statistics for every possible `digits`-long ending, oldest draw first.
+pub(super) fn ending_gap_stats(numbers_str: &[String], digits: usize, recent_window: usize) -> Vec<EndingStats> {
+    let n = numbers_str.len();
+    let mut appearances: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, num) in numbers_str.iter().enumerate() {
+        appearances.entry(&num[num.len() - digits..]).or_default().push(idx);
+    }
+
+    let recent_start = n.saturating_sub(recent_window);
+    (0..10usize.pow(digits as u32))
+        .map(|value| {
+            let ending = format!("{:0width$}", value, width = digits);
+            let seen = appearances.get(ending.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+            let current_gap = seen.last().map_or(n, |&last| n - 1 - last);
+            let gaps: Vec<usize> = seen.windows(2).map(|w| w[1] - w[0]).collect();
+            let average_gap = (!gaps.is_empty()).then(|| gaps.iter().sum::<usize>() as f64 / gaps.len() as f64);
+            let longest_gap = gaps.iter().copied().chain(std::iter::once(current_gap)).max().unwrap_or(current_gap);
+            EndingStats {
+                ending,
+                total_count: seen.len(),
+                recent_count: seen.iter().filter(|&&idx| idx >= recent_start).count(),
+                current_gap,
+                average_gap,
+                longest_gap,
+            }
+        })
+        .collect()
+}
+
+/// Ranks 2- and 3-digit endings by recent frequency (hot) and by draws since last seen (cold/overdue).
+/// The input must be ordered from the oldest draw to the newest.
+pub(super) fn run_hot_cold_analysis(numbers_str: &[String], recent_window: Option<usize>) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 10 {
+        return Err(anyhow!("การวิเคราะห์เลขร้อน/เลขเย็นต้องการข้อมูลอย่างน้อย 10 งวด แต่พบเพียง {}", numbers_str.len()));
+    }
+    if !numbers_str.iter().all(|n| n.len() >= 2 && n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("ตัวเลขทั้งหมดต้องประกอบด้วยตัวเลขอย่างน้อย 2 หลักสำหรับการวิเคราะห์เลขร้อน/เลขเย็น"));
+    }
+    let recent_window = recent_window.unwrap_or(DEFAULT_RECENT_WINDOW).clamp(1, numbers_str.len());
+    let min_len = numbers_str.iter().map(|n| n.len()).min().unwrap_or(2);
+    let ending_lengths: Vec<usize> = [2, 3].into_iter().filter(|&d| d <= min_len).collect();
+
+    let mut pattern_analysis = HashMap::new();
+    let mut top_hot = Vec::new();
+    let mut prediction_distribution = None;
+
+    for &digits in &ending_lengths {
+        let expected_gap = 10usize.pow(digits as u32);
+        let mut stats = ending_gap_stats(numbers_str, digits, recent_window);
+
+        stats.sort_by(|a, b| b.recent_count.cmp(&a.recent_count)
+            .then(b.total_count.cmp(&a.total_count))
+            .then(a.current_gap.cmp(&b.current_gap)));
+        let hot: Vec<_> = stats.iter().filter(|s| s.total_count > 0).take(RANKING_SIZE).collect();
+        top_hot = hot.iter().map(|s| s.ending.clone()).collect();
+        // The prediction comes from the longest ending, so its distribution is the recent-window share of each ending.
+        let recent_shares = stats.iter().map(|s| (s.ending.clone(), s.recent_count as f64 / recent_window as f64)).collect();
+        prediction_distribution = Some(probability::from_probabilities(recent_shares, expected_gap as u64, recent_window));
+        pattern_analysis.insert(
+            format!("เลขร้อน {} ตัว", digits),
+            serde_json::json!(hot.iter().map(|s| s.to_json(expected_gap)).collect::<Vec<_>>()),
+        );
+
+        stats.sort_by(|a, b| b.current_gap.cmp(&a.current_gap).then(a.ending.cmp(&b.ending)));
+        pattern_analysis.insert(
+            format!("เลขเย็น (ค้างนาน) {} ตัว", digits),
+            serde_json::json!(stats.iter().take(RANKING_SIZE).map(|s| s.to_json(expected_gap)).collect::<Vec<_>>()),
+        );
+    }
+
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert(
+        "PREDICTION".to_string(),
+        serde_json::json!(top_hot.first().cloned().unwrap_or_else(|| "N/A".to_string())),
+    );
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("เลขร้อน/เลขเย็น (Gap Analysis)"));
+    if top_hot.len() > 1 {
+        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(top_hot[1..5.min(top_hot.len())]));
+    }
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("จำนวนงวด".to_string(), numbers_str.len().to_string()),
+            ("ช่วงงวดล่าสุดที่ใช้วัดเลขร้อน".to_string(), recent_window.to_string()),
+            ("ความยาวเลขท้ายที่วิเคราะห์".to_string(), ending_lengths.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), "สำหรับเลขท้ายทุกตัวที่เป็นไปได้ การวิเคราะห์นี้นับจำนวนงวดนับจากครั้งล่าสุดที่ออก (current_gap) ระยะห่างเฉลี่ยและระยะห่างที่ยาวที่สุดระหว่างการออกแต่ละครั้ง และจำนวนครั้งที่ออกในช่วงงวดล่าสุด (recent_count)".to_string()),
+            ("การจัดอันดับ".to_string(), "เลขร้อนเรียงตามจำนวนครั้งที่ออกในช่วงล่าสุด เลขเย็นเรียงตามจำนวนงวดที่ยังไม่ออก ค่า overdue_ratio เปรียบเทียบกับระยะห่างที่คาดหวังจากการสุ่ม (100 งวดสำหรับเลข 2 ตัว และ 1,000 งวดสำหรับเลข 3 ตัว)".to_string()),
+            ("ข้อควรระวัง".to_string(), "ในการสุ่มที่แท้จริง เลขที่ไม่ออกมานานไม่ได้มีโอกาสออกมากขึ้น ข้อมูลนี้แสดงประวัติ ไม่ใช่ความน่าจะเป็นในอนาคต".to_string()),
+        ]),
+        evaluation: None,
+        prediction_distribution,
+        target: None,
+    })
+}
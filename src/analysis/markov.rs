@@ -0,0 +1,186 @@
+use super::probability;
+use crate::models::{AnalysisParams, AnalysisResponse, MarkovStateSpace, Smoothing};
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeSet, HashMap};
+
+/// Pseudo-count used by Dirichlet smoothing when the request does not set `smoothing_alpha`.
+const DEFAULT_DIRICHLET_ALPHA: f64 = 0.5;
+
+/// One symbol sequence the chain is fitted on, together with every symbol it may emit next.
+struct ChainInput {
+    label: String,
+    sequence: Vec<String>,
+    alphabet: Vec<String>,
+}
+
+/// Counts of next symbols observed after each length-`order` context. Contexts are joined with ','.
+fn fit_transitions(sequence: &[String], order: usize) -> HashMap<String, HashMap<String, u32>> {
+    let mut transitions: HashMap<String, HashMap<String, u32>> = HashMap::new();
+    for window in sequence.windows(order + 1) {
+        let context = window[..order].join(",");
+        *transitions.entry(context).or_default().entry(window[order].clone()).or_default() += 1;
+    }
+    transitions
+}
+
+/// Smoothed next-state distribution `(count + alpha) / (total + alpha * |alphabet|)`, most likely first.
+/// Returns an empty list when the context was never seen and no smoothing is applied.
+fn next_state_distribution(counts: Option<&HashMap<String, u32>>, alphabet: &[String], alpha: f64) -> Vec<(String, f64)> {
+    let total = counts.map_or(0, |c| c.values().sum::<u32>()) as f64;
+    let denom = total + alpha * alphabet.len() as f64;
+    if denom == 0.0 {
+        return Vec::new();
+    }
+    let mut distribution: Vec<(String, f64)> = alphabet.iter()
+        .map(|s| {
+            let count = counts.and_then(|c| c.get(s)).copied().unwrap_or(0) as f64;
+            (s.clone(), (count + alpha) / denom)
+        })
+        .filter(|(_, p)| *p > 0.0)
+        .collect();
+    distribution.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    distribution
+}
+
+fn distribution_json(distribution: &[(String, f64)]) -> serde_json::Value {
+    serde_json::json!(distribution.iter()
+        .map(|(state, p)| serde_json::json!({ "state": state, "probability": p }))
+        .collect::<Vec<_>>())
+}
+
+/// Splits the numbers into the symbol sequences required by the chosen state space.
+fn build_chain_inputs(numbers_str: &[String], state_space: MarkovStateSpace) -> Result<Vec<ChainInput>> {
+    let all_digits = numbers_str.iter().all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    let endings = |digits: usize| -> Result<ChainInput> {
+        if !all_digits || numbers_str.iter().any(|n| n.len() < digits) {
+            return Err(anyhow!("ตัวเลขทั้งหมดต้องเป็นตัวเลขอย่างน้อย {} หลักสำหรับมาร์คอฟเชนแบบเลขท้าย", digits));
+        }
+        Ok(ChainInput {
+            label: format!("เลขท้าย {} ตัว", digits),
+            sequence: numbers_str.iter().map(|n| n[n.len() - digits..].to_string()).collect(),
+            alphabet: (0..10usize.pow(digits as u32)).map(|v| format!("{:0width$}", v, width = digits)).collect(),
+        })
+    };
+
+    match state_space {
+        MarkovStateSpace::FullNumber => Ok(vec![ChainInput {
+            label: "ตัวเลขทั้งชุด".to_string(),
+            sequence: numbers_str.to_vec(),
+            // The full alphabet of 6-digit numbers is too large to enumerate, so only observed numbers are candidates.
+            alphabet: numbers_str.iter().cloned().collect::<BTreeSet<_>>().into_iter().collect(),
+        }]),
+        MarkovStateSpace::LastTwo => Ok(vec![endings(2)?]),
+        MarkovStateSpace::LastThree => Ok(vec![endings(3)?]),
+        MarkovStateSpace::PerDigit => {
+            let rows = super::parse_digit_rows(numbers_str)?;
+            let digit_alphabet: Vec<String> = (0..10).map(|d| d.to_string()).collect();
+            Ok((0..rows[0].len())
+                .map(|pos| ChainInput {
+                    label: format!("หลักที่ {}", pos + 1),
+                    sequence: rows.iter().map(|r| r[pos].to_string()).collect(),
+                    alphabet: digit_alphabet.clone(),
+                })
+                .collect())
+        }
+    }
+}
+
+// --- Method 4: Markov Chain ---
+/// Order-k Markov chain over whole numbers, endings or individual digit positions, with optional smoothing.
+/// The input must be ordered from the oldest draw to the newest.
+pub(super) fn run_markov_chain_analysis(numbers_str: &[String], params: &AnalysisParams) -> Result<AnalysisResponse> {
+    let order = params.markov_order.unwrap_or(1).max(1);
+    if numbers_str.len() < order + 1 {
+        return Err(anyhow!("การวิเคราะห์แบบมาร์คอฟเชนอันดับ {} ต้องการข้อมูลอย่างน้อย {} จุดข้อมูล", order, order + 1));
+    }
+    let state_space = params.markov_state_space.unwrap_or_default();
+    let smoothing = params.smoothing.unwrap_or_default();
+    let alpha = match smoothing {
+        Smoothing::None => 0.0,
+        Smoothing::Laplace => 1.0,
+        Smoothing::Dirichlet => params.smoothing_alpha.unwrap_or(DEFAULT_DIRICHLET_ALPHA).max(0.0),
+    };
+
+    let inputs = build_chain_inputs(numbers_str, state_space)?;
+    let mut pattern_analysis = HashMap::new();
+    let mut distributions = Vec::with_capacity(inputs.len());
+    // Transitions observed out of each chain's latest context, i.e. the evidence behind its distribution.
+    let mut context_totals = Vec::with_capacity(inputs.len());
+    let mut state_count = 0;
+
+    for input in &inputs {
+        let transitions = fit_transitions(&input.sequence, order);
+        state_count += transitions.len();
+        let context = input.sequence[input.sequence.len() - order..].join(",");
+        let distribution = next_state_distribution(transitions.get(&context), &input.alphabet, alpha);
+        context_totals.push(transitions.get(&context).map_or(0, |c| c.values().sum::<u32>()) as usize);
+        pattern_analysis.insert(
+            format!("การแจกแจงสถานะถัดไป ({})", input.label),
+            serde_json::json!({ "context": context, "distribution": distribution_json(&distribution) }),
+        );
+        distributions.push(distribution);
+    }
+
+    let prediction_distribution = if distributions.iter().any(|d| d.is_empty()) {
+        None
+    } else if state_space == MarkovStateSpace::PerDigit {
+        let positions: Vec<[f64; 10]> = distributions.iter()
+            .map(|d| {
+                let mut probabilities = [0.0; 10];
+                for (digit, p) in d {
+                    probabilities[digit.parse::<usize>().unwrap()] = *p;
+                }
+                probabilities
+            })
+            .collect();
+        Some(probability::from_digit_positions(&positions, context_totals.iter().copied().min().unwrap_or(0)))
+    } else {
+        // Whole numbers can be any number of their length, not only the ones observed so far.
+        let support_size = match state_space {
+            MarkovStateSpace::FullNumber => 10u64.saturating_pow(numbers_str.iter().map(|n| n.len()).max().unwrap_or(0) as u32),
+            _ => inputs[0].alphabet.len() as u64,
+        };
+        Some(probability::from_probabilities(distributions[0].clone(), support_size, context_totals[0]))
+    };
+
+    let mut prediction_output = HashMap::new();
+    if distributions.iter().any(|d| d.is_empty()) {
+        prediction_output.insert("PREDICTION".to_string(), serde_json::json!("ไม่พบการเปลี่ยนแปลงในอดีตสำหรับเลขตัวล่าสุด"));
+    } else if state_space == MarkovStateSpace::PerDigit {
+        // Each position is predicted independently; the number is the most likely digit at every position.
+        let prediction: String = distributions.iter().map(|d| d[0].0.as_str()).collect();
+        prediction_output.insert("PREDICTION".to_string(), serde_json::json!(prediction));
+    } else {
+        let distribution = &distributions[0];
+        prediction_output.insert("PREDICTION".to_string(), serde_json::json!(distribution[0].0));
+        let alternatives: Vec<&String> = distribution.iter().skip(1).take(4).map(|(s, _)| s).collect();
+        if !alternatives.is_empty() {
+            prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
+        }
+    }
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("การวิเคราะห์แบบมาร์คอฟเชน"));
+
+    let smoothing_label = match smoothing {
+        Smoothing::None => "ไม่มี".to_string(),
+        Smoothing::Laplace => "Laplace (α = 1)".to_string(),
+        Smoothing::Dirichlet => format!("Dirichlet (α = {})", alpha),
+    };
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("อันดับของเชน (order)".to_string(), order.to_string()),
+            ("ปริภูมิสถานะ".to_string(), inputs.iter().map(|i| i.label.as_str()).collect::<Vec<_>>().join(", ")),
+            ("จำนวนสถานะที่พบ (บริบทที่ไม่ซ้ำกัน)".to_string(), state_count.to_string()),
+            ("การปรับให้เรียบ (smoothing)".to_string(), smoothing_label),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), format!("การวิเคราะห์นี้จะคำนวณความน่าจะเป็นในอดีตของการเปลี่ยนจาก {} สถานะล่าสุดไปยังสถานะถัดไป การทำนายคือสถานะที่ตามหลังบริบทล่าสุดบ่อยที่สุดในอดีต และแสดงการแจกแจงความน่าจะเป็นของทุกสถานะถัดไป", order)),
+            ("การปรับให้เรียบ".to_string(), "การปรับแบบ Laplace หรือ Dirichlet จะเพิ่มค่านับเทียม (α) ให้ทุกสถานะ ทำให้บริบทที่ไม่เคยพบหรือสถานะที่ไม่เคยเกิดขึ้นยังคงมีความน่าจะเป็นมากกว่าศูนย์ เหมาะกับการวิเคราะห์รายหลักหรือเลขท้ายที่มีจำนวนสถานะจำกัด".to_string()),
+        ]),
+        evaluation: None,
+        prediction_distribution,
+        target: None,
+    })
+}
@@ -0,0 +1,258 @@
+use super::classifiers::{argmax, fit_classifier, ModelConfig, ModelKind, CHANCE_BASELINE};
+use super::evaluation::{cross_validate, DEFAULT_CV_FOLDS};
+use super::features::{FeatureBuilder, FeatureMatrix, DEFAULT_FEATURE_LAGS};
+use super::{parse_digit_rows, probability};
+use crate::models::{AnalysisParams, AnalysisResponse, EvaluationReport, PredictionDistribution};
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+// Machine Learning & Stats
+use ndarray::{s, Array1};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use statrs::distribution::{Binomial, DiscreteCDF};
+
+/// Training data shared by the ML analyzers and the standalone evaluation.
+struct MlProblem {
+    matrix: FeatureMatrix,
+    /// One (label, class of the following draw for every training row) pair per predicted digit.
+    targets: Vec<(String, Array1<usize>)>,
+}
+
+/// Builds the feature matrix and targets for `kind`. The decision tree predicts only the last digit;
+/// the ensembles predict every digit position.
+fn prepare_problem(numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams, kind: ModelKind) -> Result<MlProblem> {
+    let builder = FeatureBuilder::new(params.feature_lags.unwrap_or(DEFAULT_FEATURE_LAGS));
+    match kind {
+        ModelKind::DecisionTree if numbers_str.len() < 10 => {
+            return Err(anyhow!("แมชชีนเลิร์นนิงต้องการข้อมูลอย่างน้อย 10 จุดข้อมูลในการฝึกฝน"));
+        }
+        ModelKind::RandomForest | ModelKind::GradientBoosting if numbers_str.len() < builder.min_draws() + 20 => {
+            return Err(anyhow!("โมเดล {} ต้องการข้อมูลอย่างน้อย {} งวดเพื่อแบ่งชุดฝึกฝนและชุดทดสอบ", kind.display_name(), builder.min_draws() + 20));
+        }
+        _ => {}
+    }
+    let num_len = numbers_str.first().map_or(0, |n| n.len());
+    if num_len == 0 || !numbers_str.iter().all(|n| n.len() == num_len && n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("ตัวเลขทั้งหมดต้องมีความยาวเท่ากันและประกอบด้วยตัวเลขเท่านั้นสำหรับการวิเคราะห์ ML"));
+    }
+    let rows = parse_digit_rows(numbers_str)?;
+    if rows.len() <= builder.min_draws() {
+        return Err(anyhow!("ไม่สามารถสร้างคู่ฝึกฝนจากข้อมูลได้"));
+    }
+    let matrix = builder.build(&rows, dates);
+
+    let positions: Vec<usize> = match kind {
+        ModelKind::DecisionTree => vec![num_len - 1],
+        ModelKind::RandomForest | ModelKind::GradientBoosting => (0..num_len).collect(),
+    };
+    let targets = positions.into_iter()
+        .map(|pos| {
+            let labels: Vec<usize> = rows.iter().map(|r| r[pos] as usize).collect();
+            (format!("หลักที่ {}", pos + 1), matrix.training_targets(&labels))
+        })
+        .collect();
+    Ok(MlProblem { matrix, targets })
+}
+
+fn evaluate_problem(problem: &MlProblem, kind: ModelKind, config: &ModelConfig, params: &AnalysisParams, rng: &mut StdRng) -> Result<EvaluationReport> {
+    cross_validate(
+        kind,
+        config,
+        &problem.matrix.training_records(),
+        &problem.targets,
+        params.cv_strategy.unwrap_or_default(),
+        params.cv_folds.unwrap_or(DEFAULT_CV_FOLDS),
+        rng,
+    )
+}
+
+/// Produces the cross-validated evaluation report of an ML method without running the prediction.
+pub(super) fn evaluate_ml_model(numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams, kind: ModelKind) -> Result<EvaluationReport> {
+    let problem = prepare_problem(numbers_str, dates, params, kind)?;
+    let config = ModelConfig::from_params(kind, params);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    evaluate_problem(&problem, kind, &config, params, &mut rng)
+}
+
+/// The cross-validation report for /analyze, only computed when the caller opts in because it refits
+/// the model once per fold. Short histories leave too few rows to cross-validate; the prediction is
+/// still returned without a report.
+fn optional_evaluation(problem: &MlProblem, kind: ModelKind, config: &ModelConfig, params: &AnalysisParams, rng: &mut StdRng) -> Option<EvaluationReport> {
+    if !params.cross_validate.unwrap_or(false) {
+        return None;
+    }
+    evaluate_problem(problem, kind, config, params, rng).ok()
+}
+
+/// The distribution of the next draw from models fitted on all draws, without the held-out split or
+/// the cross-validation of the full analysis. Used where only the vote matters, e.g. the consensus.
+pub(super) fn predict_distribution(numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams, kind: ModelKind) -> Result<PredictionDistribution> {
+    let problem = prepare_problem(numbers_str, dates, params, kind)?;
+    let config = ModelConfig::from_params(kind, params);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let records = problem.matrix.training_records();
+    let last_features = problem.matrix.latest_record();
+    let digit_probabilities = problem.targets.iter()
+        .map(|(_, targets)| Ok(fit_classifier(kind, &records, targets, &config, &mut rng)?.predict_proba(&last_features)[0]))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(probability::from_digit_positions(&digit_probabilities, records.nrows()))
+}
+
+/// P(X >= correct) for X ~ Binomial(n, 0.1): how likely random guessing is to do at least this well.
+fn chance_p_value(correct: usize, n: usize) -> f64 {
+    match (correct, Binomial::new(CHANCE_BASELINE, n as u64)) {
+        (0, _) => 1.0,
+        (_, Ok(dist)) => dist.sf(correct as u64 - 1),
+        (_, Err(_)) => 1.0,
+    }
+}
+
+// --- Method 3: Machine Learning (Decision Tree) ---
+pub(super) fn run_decision_tree_analysis(numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams) -> Result<AnalysisResponse> {
+    let kind = ModelKind::DecisionTree;
+    let problem = prepare_problem(numbers_str, dates, params, kind)?;
+    let config = ModelConfig::from_params(kind, params);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let records = problem.matrix.training_records();
+    let model = fit_classifier(kind, &records, &problem.targets[0].1, &config, &mut rng)?;
+    // Class shares of the leaf the latest record falls into; the prediction is the most common class.
+    let leaf_probabilities = model.predict_proba(&problem.matrix.latest_record());
+    let predicted_last_digit = argmax(&leaf_probabilities[0]);
+
+    let evaluation = optional_evaluation(&problem, kind, &config, params, &mut rng);
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("โมเดล".to_string(), "Decision Tree Classifier".to_string()),
+            ("จำนวนฟีเจอร์".to_string(), problem.matrix.columns.len().to_string()),
+        ]),
+        pattern_analysis: HashMap::from([("เป้าหมายการทำนาย".to_string(), serde_json::json!("ตัวเลขสุดท้ายของหมายเลขถัดไป"))]),
+        prediction_output: HashMap::from([
+            ("PREDICTION".to_string(), serde_json::json!(predicted_last_digit.to_string())),
+            ("METHOD".to_string(), serde_json::json!("แมชชีนเลิร์นนิง (Decision Tree)")),
+            ("ความหมายของคำทำนาย".to_string(), serde_json::json!(format!("ตัวเลขใดๆ ที่ลงท้ายด้วย '{}'", predicted_last_digit))),
+        ]),
+        detailed_explanation: HashMap::from([("หลักการ".to_string(), "โมเดล Decision Tree ได้รับการฝึกฝนเพื่อทำนาย 'เลขท้าย' ของหมายเลขถัดไปโดยพิจารณาจากฟีเจอร์ของหมายเลขก่อนหน้าหลายงวด เช่น เลขแต่ละหลัก ผลรวม digital root และจำนวนงวดที่เลขแต่ละตัวไม่ปรากฏ เนื่องจากลักษณะของโมเดลนี้จะให้ผลลัพธ์ที่ชัดเจนตามกฎที่เรียนรู้มา จึงไม่มีการแสดง 'ทางเลือกอื่น' เหมือนโมเดลเชิงสถิติ".to_string())]),
+        evaluation,
+        prediction_distribution: Some(probability::from_digit_positions(&leaf_probabilities, records.nrows())),
+        target: None,
+    })
+}
+
+/// Trains one ensemble per digit position to predict each digit of the next number from the engineered features,
+/// evaluates it on a chronological held-out split, then refits on all data for the final prediction.
+/// The input must be ordered from the oldest draw to the newest.
+pub(super) fn run_ensemble_analysis(numbers_str: &[String], dates: Option<&[NaiveDate]>, params: &AnalysisParams, kind: ModelKind) -> Result<AnalysisResponse> {
+    let problem = prepare_problem(numbers_str, dates, params, kind)?;
+    let config = ModelConfig::from_params(kind, params);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let records = problem.matrix.training_records();
+    let last_features = problem.matrix.latest_record();
+    let n_samples = records.nrows();
+
+    let n_test = ((n_samples as f64 * config.test_fraction).round() as usize).clamp(1, n_samples - 10);
+    let n_train = n_samples - n_test;
+    let (train_x, test_x) = (records.slice(s![..n_train, ..]).to_owned(), records.slice(s![n_train.., ..]).to_owned());
+
+    let mut pattern_analysis = HashMap::new();
+    let mut predicted_digits = Vec::with_capacity(problem.targets.len());
+    let mut digit_probabilities = Vec::with_capacity(problem.targets.len());
+    let mut total_correct = 0;
+
+    for (label, targets) in &problem.targets {
+        let (train_y, test_y) = (targets.slice(s![..n_train]).to_owned(), targets.slice(s![n_train..]).to_owned());
+
+        let holdout_model = fit_classifier(kind, &train_x, &train_y, &config, &mut rng)?;
+        let correct = holdout_model.predict(&test_x).iter().zip(test_y.iter()).filter(|(p, t)| p == t).count();
+        total_correct += correct;
+        let accuracy = correct as f64 / n_test as f64;
+
+        let full_model = fit_classifier(kind, &records, targets, &config, &mut rng)?;
+        let probabilities = full_model.predict_proba(&last_features)[0];
+        let digit = argmax(&probabilities);
+        predicted_digits.push(digit);
+        digit_probabilities.push(probabilities);
+
+        pattern_analysis.insert(
+            label.clone(),
+            serde_json::json!({
+                "predicted_digit": digit,
+                "test_accuracy": accuracy,
+                "chance_baseline": CHANCE_BASELINE,
+                "p_value_vs_chance": chance_p_value(correct, n_test),
+            }),
+        );
+    }
+
+    let evaluation = optional_evaluation(&problem, kind, &config, params, &mut rng);
+    let mean_accuracy = total_correct as f64 / (n_test * problem.targets.len()) as f64;
+    let prediction: String = predicted_digits.iter().map(|d| d.to_string()).collect();
+
+    let mut hyperparameters = vec![
+        format!("n_trees = {}", config.n_trees),
+        format!("max_depth = {}", config.max_depth.map_or("ไม่จำกัด".to_string(), |d| d.to_string())),
+        format!("seed = {}", config.seed),
+    ];
+    match kind {
+        ModelKind::RandomForest => hyperparameters.push(format!("feature_fraction = {}", config.feature_fraction)),
+        ModelKind::GradientBoosting => hyperparameters.push(format!("learning_rate = {}", config.learning_rate)),
+        ModelKind::DecisionTree => {}
+    }
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("โมเดล".to_string(), format!("{} Classifier (แยกตามหลัก)", kind.display_name())),
+            ("ไฮเปอร์พารามิเตอร์".to_string(), hyperparameters.join(", ")),
+            ("ขนาดชุดฝึกฝน / ชุดทดสอบ".to_string(), format!("{} / {}", n_train, n_test)),
+            ("จำนวนฟีเจอร์".to_string(), problem.matrix.columns.len().to_string()),
+            ("ความแม่นยำเฉลี่ยบนชุดทดสอบ".to_string(), format!("{:.1}%", mean_accuracy * 100.0)),
+            ("ความแม่นยำจากการเดาสุ่ม".to_string(), format!("{:.1}%", CHANCE_BASELINE * 100.0)),
+        ]),
+        pattern_analysis,
+        prediction_output: HashMap::from([
+            ("PREDICTION".to_string(), serde_json::json!(prediction)),
+            ("METHOD".to_string(), serde_json::json!(format!("แมชชีนเลิร์นนิง ({})", kind.display_name()))),
+        ]),
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), format!("ฝึกโมเดล {} แยกสำหรับแต่ละหลัก เพื่อทำนายเลขในหลักนั้นของงวดถัดไปจากฟีเจอร์ของงวดก่อนหน้าหลายงวด (เลขแต่ละหลัก ผลรวม digital root จำนวนเลขคู่และเลขสูง และจำนวนงวดที่เลขแต่ละตัวไม่ปรากฏ) แล้วนำเลขที่ทำนายได้มาเรียงต่อกัน", kind.display_name())),
+            ("การประเมินผล".to_string(), format!("งวดล่าสุด {} งวดถูกกันไว้เป็นชุดทดสอบตามลำดับเวลา โมเดลที่ไม่ได้เรียนรู้อะไรเลยจะมีความแม่นยำประมาณ 10% ต่อหลัก ค่า p_value_vs_chance คือโอกาสที่การเดาสุ่มจะได้ผลดีเท่านี้หรือดีกว่า", n_test)),
+        ]),
+        evaluation,
+        prediction_distribution: Some(probability::from_digit_positions(&digit_probabilities, n_samples)),
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::permutations::predicted_numbers;
+
+    fn numbers(draws: usize) -> Vec<String> {
+        (0..draws).map(|i| format!("{:06}", (i * 7_919 + 12_345) % 1_000_000)).collect()
+    }
+
+    #[test]
+    fn analyze_only_cross_validates_on_request() {
+        let numbers = numbers(60);
+        let params = AnalysisParams { n_trees: Some(5), ..Default::default() };
+        assert!(run_ensemble_analysis(&numbers, None, &params, ModelKind::RandomForest).unwrap().evaluation.is_none());
+        assert!(run_decision_tree_analysis(&numbers, None, &params).unwrap().evaluation.is_none());
+
+        let params = AnalysisParams { cross_validate: Some(true), ..params };
+        assert!(run_ensemble_analysis(&numbers, None, &params, ModelKind::RandomForest).unwrap().evaluation.is_some());
+        assert!(run_decision_tree_analysis(&numbers, None, &params).unwrap().evaluation.is_some());
+    }
+
+    #[test]
+    fn decision_tree_prediction_is_a_plain_digit() {
+        let response = run_decision_tree_analysis(&numbers(60), None, &AnalysisParams::default()).unwrap();
+        let predicted = predicted_numbers(&response);
+        assert_eq!(predicted.len(), 1);
+        assert_eq!(predicted[0].len(), 1);
+    }
+}
@@ -0,0 +1,277 @@
+use super::{calculate_digital_root, probability};
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeSet, HashMap};
+
+use statrs::distribution::{Binomial, DiscreteCDF};
+
+/// Family-wise significance level, divided by the number of rules tested (Bonferroni).
+const ALPHA: f64 = 0.05;
+/// Length of the ending the prediction is made for.
+const PREDICTION_DIGITS: usize = 2;
+
+/// A Thai numerology rule that derives the numbers expected in the next draw from the latest ending.
+#[derive(Clone, Copy, PartialEq)]
+enum Rule {
+    /// The ending read backwards (เลขกลับ), e.g. 47 -> 74.
+    Reversal,
+    /// Every other arrangement of a 3-digit ending (เลขกลับ 6 ประตู), e.g. 123 -> 132, 213, ...
+    Permutations,
+    /// Numbers made of the ending's digits in which a digit repeats (เลขเบิ้ล), e.g. 47 -> 44, 77.
+    Doubles,
+    /// Every digit replaced by its mirror (เลขเงา: 1↔6, 2↔7, 3↔8, 4↔9, 5↔0), e.g. 47 -> 92.
+    Mirror,
+    /// Position-wise sums of the latest two endings, keeping the last digit, e.g. 47 and 38 -> 75.
+    PositionSum,
+    /// Every number whose digit sum ends in the same digit as the ending's (เลขผลรวม).
+    DigitSum,
+}
+
+impl Rule {
+    const ALL: [Rule; 6] = [Rule::Reversal, Rule::Permutations, Rule::Doubles, Rule::Mirror, Rule::PositionSum, Rule::DigitSum];
+
+    fn label(self) -> &'static str {
+        match self {
+            Rule::Reversal => "เลขกลับ",
+            Rule::Permutations => "เลขกลับ (ทุกรูปแบบ)",
+            Rule::Doubles => "เลขเบิ้ล",
+            Rule::Mirror => "เลขเงา",
+            Rule::PositionSum => "ผลบวกรายหลัก",
+            Rule::DigitSum => "เลขผลรวม",
+        }
+    }
+
+    /// The numbers this rule derives from `ending`, which has `previous` before it.
+    /// The ending itself is left out, since a straight repeat is not a numerology pattern.
+    fn derive(self, ending: &str, previous: Option<&str>) -> BTreeSet<String> {
+        let digits: Vec<u32> = ending.chars().filter_map(|c| c.to_digit(10)).collect();
+        let to_string = |ds: &[u32]| ds.iter().map(|d| d.to_string()).collect::<String>();
+        let mut derived: BTreeSet<String> = match self {
+            Rule::Reversal => BTreeSet::from([ending.chars().rev().collect()]),
+            Rule::Permutations => permutations(&digits).iter().map(|p| to_string(p)).collect(),
+            Rule::Doubles => {
+                let pool: BTreeSet<u32> = digits.iter().copied().collect();
+                sequences(&pool, digits.len()).into_iter()
+                    .filter(|s| s.iter().collect::<BTreeSet<_>>().len() < s.len())
+                    .map(|s| to_string(&s))
+                    .collect()
+            }
+            Rule::Mirror => BTreeSet::from([to_string(&digits.iter().map(|d| (d + 5) % 10).collect::<Vec<_>>())]),
+            Rule::PositionSum => match previous {
+                Some(previous) => {
+                    let sums: Vec<u32> = digits.iter().zip(previous.chars().filter_map(|c| c.to_digit(10)))
+                        .map(|(a, b)| (a + b) % 10)
+                        .collect();
+                    BTreeSet::from([to_string(&sums)])
+                }
+                None => BTreeSet::new(),
+            },
+            Rule::DigitSum => {
+                let target = digits.iter().sum::<u32>() % 10;
+                (0..10usize.pow(digits.len() as u32))
+                    .map(|v| format!("{:0width$}", v, width = digits.len()))
+                    .filter(|s| s.chars().filter_map(|c| c.to_digit(10)).sum::<u32>() % 10 == target)
+                    .collect()
+            }
+        };
+        if self != Rule::PositionSum {
+            derived.remove(ending);
+        }
+        derived
+    }
+}
+
+/// Every distinct ordering of `digits`.
+pub(super) fn permutations(digits: &[u32]) -> BTreeSet<Vec<u32>> {
+    if digits.len() <= 1 {
+        return BTreeSet::from([digits.to_vec()]);
+    }
+    let mut result = BTreeSet::new();
+    for i in 0..digits.len() {
+        let mut rest = digits.to_vec();
+        let first = rest.remove(i);
+        for mut tail in permutations(&rest) {
+            tail.insert(0, first);
+            result.insert(tail);
+        }
+    }
+    result
+}
+
+/// Every `len`-long sequence drawn, with repetition, from `pool`.
+fn sequences(pool: &BTreeSet<u32>, len: usize) -> Vec<Vec<u32>> {
+    (0..len).fold(vec![Vec::new()], |acc, _| {
+        acc.into_iter()
+            .flat_map(|prefix| pool.iter().map(move |&d| {
+                let mut next = prefix.clone();
+                next.push(d);
+                next
+            }))
+            .collect()
+    })
+}
+
+/// How often a rule applied to one draw contained the ending of the following draw.
+struct RuleRecord {
+    rule: Rule,
+    digits: usize,
+    trials: usize,
+    hits: usize,
+    /// Average share of all possible endings the rule covered, i.e. the hit rate of random guessing.
+    chance_rate: f64,
+    p_value: f64,
+}
+
+impl RuleRecord {
+    fn hit_rate(&self) -> f64 {
+        if self.trials == 0 { 0.0 } else { self.hits as f64 / self.trials as f64 }
+    }
+
+    fn lift(&self) -> f64 {
+        if self.chance_rate > 0.0 { self.hit_rate() / self.chance_rate } else { 0.0 }
+    }
+
+    fn to_json(&self, corrected_alpha: f64) -> serde_json::Value {
+        serde_json::json!({
+            "rule": self.rule.label(),
+            "trials": self.trials,
+            "hits": self.hits,
+            "hit_rate": self.hit_rate(),
+            "chance_rate": self.chance_rate,
+            "lift": self.lift(),
+            "p_value": self.p_value,
+            "significant": self.p_value < corrected_alpha,
+        })
+    }
+}
+
+/// Replays every draw: applies `rule` to its `digits`-long ending and checks the next draw's ending.
+fn backtest_rule(endings: &[&str], rule: Rule, digits: usize) -> RuleRecord {
+    let outcomes = 10usize.pow(digits as u32) as f64;
+    let (mut trials, mut hits, mut coverage) = (0, 0, 0.0);
+    for t in 0..endings.len() - 1 {
+        let derived = rule.derive(endings[t], t.checked_sub(1).map(|p| endings[p]));
+        if derived.is_empty() {
+            continue;
+        }
+        trials += 1;
+        coverage += derived.len() as f64 / outcomes;
+        if derived.contains(endings[t + 1]) {
+            hits += 1;
+        }
+    }
+    let chance_rate = if trials == 0 { 0.0 } else { coverage / trials as f64 };
+    // The sets differ slightly in size between draws; the binomial uses their average coverage.
+    let p_value = match (hits, Binomial::new(chance_rate.clamp(0.0, 1.0), trials as u64)) {
+        (0, _) | (_, Err(_)) => 1.0,
+        (h, Ok(dist)) => dist.sf(h as u64 - 1),
+    };
+    RuleRecord { rule, digits, trials, hits, chance_rate, p_value }
+}
+
+// --- Method 2: Numerology (Digital Root, เลขกลับ, เลขเบิ้ล, เลขเงา) ---
+/// Digital-root frequencies plus the Thai numerology rules applied to the 2- and 3-digit endings,
+/// each backtested on how often it contained the next draw's ending.
+pub(super) fn run_numerology_analysis(numbers_str: &[String]) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 10 {
+        return Err(anyhow!("การวิเคราะห์ศาสตร์แห่งตัวเลขต้องการข้อมูลอย่างน้อย 10 งวด แต่พบเพียง {}", numbers_str.len()));
+    }
+    if !numbers_str.iter().all(|n| n.len() >= PREDICTION_DIGITS && n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("ตัวเลขทั้งหมดต้องประกอบด้วยตัวเลขอย่างน้อย 2 หลักสำหรับการวิเคราะห์ศาสตร์แห่งตัวเลข"));
+    }
+
+    let mut root_counts: HashMap<u32, usize> = HashMap::new();
+    for num_str in numbers_str {
+        *root_counts.entry(calculate_digital_root(num_str)).or_insert(0) += 1;
+    }
+    let most_common_root = root_counts.iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map_or(0, |(&root, _)| root);
+
+    let min_len = numbers_str.iter().map(|n| n.len()).min().unwrap_or(PREDICTION_DIGITS);
+    let mut records = Vec::new();
+    let mut latest_derived = serde_json::Map::new();
+    for digits in [2, 3].into_iter().filter(|&d| d <= min_len) {
+        let endings: Vec<&str> = numbers_str.iter().map(|n| &n[n.len() - digits..]).collect();
+        let (latest, previous) = (endings[endings.len() - 1], endings[endings.len() - 2]);
+        let mut derived_now = serde_json::Map::new();
+        for rule in Rule::ALL {
+            // For two digits every rearrangement is simply the reversal.
+            if rule == Rule::Permutations && digits < 3 {
+                continue;
+            }
+            records.push(backtest_rule(&endings, rule, digits));
+            let derived = rule.derive(latest, Some(previous));
+            derived_now.insert(rule.label().to_string(), serde_json::json!(derived));
+        }
+        latest_derived.insert(format!("เลขท้าย {} ตัว ({})", digits, latest), serde_json::Value::Object(derived_now));
+    }
+    let corrected_alpha = ALPHA / records.len().max(1) as f64;
+
+    // The prediction applies the rule that matched the next draw best, relative to chance, on the
+    // 2-digit endings. Choosing the best of several rules on the same history favours luck, so its lift
+    // is an optimistic estimate.
+    let best = records.iter()
+        .filter(|r| r.digits == PREDICTION_DIGITS && r.trials > 0)
+        .max_by(|a, b| a.lift().partial_cmp(&b.lift()).unwrap().then(b.chance_rate.partial_cmp(&a.chance_rate).unwrap()))
+        .ok_or_else(|| anyhow!("ไม่สามารถทดสอบกฎของศาสตร์แห่งตัวเลขกับข้อมูลนี้ได้"))?;
+    let endings: Vec<&str> = numbers_str.iter().map(|n| &n[n.len() - PREDICTION_DIGITS..]).collect();
+    let (latest, previous) = (endings[endings.len() - 1], endings[endings.len() - 2]);
+    let predicted: Vec<String> = best.rule.derive(latest, Some(previous)).into_iter().collect();
+    // Other rules' numbers fill the alternatives when the best rule gives a single number.
+    let mut alternatives: Vec<String> = predicted.iter().skip(1).cloned().collect();
+    for r in records.iter().filter(|r| r.digits == PREDICTION_DIGITS && r.rule != best.rule) {
+        for n in r.rule.derive(latest, Some(previous)) {
+            if alternatives.len() < 4 && !alternatives.contains(&n) && !predicted.contains(&n) {
+                alternatives.push(n);
+            }
+        }
+    }
+    alternatives.truncate(4);
+
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(predicted.first().cloned().unwrap_or_else(|| "N/A".to_string())));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!(format!("ศาสตร์แห่งตัวเลข ({})", best.rule.label())));
+    if !alternatives.is_empty() {
+        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
+    }
+
+    // The best rule's historical hit rate is spread evenly over the numbers it derives now.
+    let share = best.hit_rate() / predicted.len().max(1) as f64;
+    let prediction_distribution = probability::from_probabilities(
+        predicted.iter().map(|n| (n.clone(), share)).collect(),
+        10u64.pow(PREDICTION_DIGITS as u32),
+        best.trials,
+    );
+
+    let significant_count = records.iter().filter(|r| r.p_value < corrected_alpha).count();
+    let mut pattern_analysis = HashMap::from([
+        ("ความถี่ของ Digital Root".to_string(), serde_json::json!(root_counts)),
+        ("เลขที่ได้จากงวดล่าสุด".to_string(), serde_json::Value::Object(latest_derived)),
+    ]);
+    for digits in [2, 3] {
+        let table: Vec<_> = records.iter().filter(|r| r.digits == digits).map(|r| r.to_json(corrected_alpha)).collect();
+        if !table.is_empty() {
+            pattern_analysis.insert(format!("อัตราการตรงกับงวดถัดไป (เลขท้าย {} ตัว)", digits), serde_json::json!(table));
+        }
+    }
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("ประเภทการวิเคราะห์".to_string(), "ศาสตร์แห่งตัวเลข (Digital Root, เลขกลับ, เลขเบิ้ล, เลขเงา, ผลรวม)".to_string()),
+            ("รากที่พบบ่อยที่สุด".to_string(), most_common_root.to_string()),
+            ("กฎที่ตรงกับงวดถัดไปดีที่สุด".to_string(), format!("{} ({:.1}% เทียบกับการสุ่ม {:.1}%)", best.rule.label(), best.hit_rate() * 100.0, best.chance_rate * 100.0)),
+            ("จำนวนกฎที่มีนัยสำคัญหลังปรับ Bonferroni".to_string(), format!("{} จาก {}", significant_count, records.len())),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), "คำนวณ 'digital root' (ผลรวมเลขหลักเดียว) ของแต่ละตัวเลข และสร้างเลขจากเลขท้าย 2 และ 3 ตัวของงวดล่าสุดตามหลักศาสตร์แห่งตัวเลขของไทย ได้แก่ เลขกลับ เลขเบิ้ล เลขเงา (1↔6, 2↔7, 3↔8, 4↔9, 5↔0) ผลบวกรายหลักของสองงวดล่าสุด และเลขที่มีผลรวมลงท้ายเหมือนกัน".to_string()),
+            ("อัตราการตรงกับงวดถัดไป".to_string(), "แต่ละกฎถูกนำไปใช้กับทุกงวดในอดีต แล้วตรวจว่าเลขท้ายของงวดถัดไปอยู่ในชุดเลขที่ได้หรือไม่ chance_rate คือโอกาสที่การสุ่มเลือกเลขจำนวนเท่ากันจะตรง และ lift คืออัตราส่วนระหว่างอัตราการตรงจริงกับ chance_rate ค่า p ใช้การแจกแจงทวินามและปรับด้วยวิธี Bonferroni".to_string()),
+            ("ตรรกะการทำนาย".to_string(), format!("ใช้กฎ '{}' ซึ่งมี lift สูงสุดกับเลขท้าย 2 ตัวในอดีต กับงวดล่าสุด เนื่องจากเลือกกฎที่ดีที่สุดจากข้อมูลชุดเดียวกัน lift นี้จึงมักสูงกว่าที่จะเกิดขึ้นจริงในอนาคต", best.rule.label())),
+        ]),
+        evaluation: None,
+        prediction_distribution: Some(prediction_distribution),
+        target: None,
+    })
+}
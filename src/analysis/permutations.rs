@@ -0,0 +1,72 @@
+use super::numerology::permutations;
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
+
+/// Longest ending that can be expanded; six digits already give up to 720 permutations.
+const MAX_PERMUTATION_DIGITS: usize = 6;
+
+/// The numbers an analyzer put forward: its prediction first, then its alternatives.
+/// Labels may carry a note after the number, e.g. "123456 (ราก 3)"; anything that is not a number is skipped.
+pub(super) fn predicted_numbers(response: &AnalysisResponse) -> Vec<String> {
+    let prediction = response.prediction_output.get("PREDICTION").into_iter();
+    let alternatives = response.prediction_output.get("ทางเลือกอื่นๆ")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten();
+    prediction.chain(alternatives)
+        .filter_map(|v| v.as_str())
+        .filter_map(|s| s.split_whitespace().next())
+        .filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        .map(String::from)
+        .collect()
+}
+
+/// Post-processing step any analyzer can opt into with `permutation_digits` (กลับเลข): the last
+/// `digits` digits of the prediction and of every alternative are expanded into their unique
+/// permutations, ranked by how often each appeared as an ending in the history. A permutation already
+/// produced by an earlier candidate is not repeated. The result is added to the prediction output as
+/// "PERMUTATIONS".
+pub(super) fn expand_permutations(response: &mut AnalysisResponse, numbers_str: &[String], digits: usize) -> Result<()> {
+    if !(2..=MAX_PERMUTATION_DIGITS).contains(&digits) {
+        return Err(anyhow!("จำนวนหลักที่จะกลับเลข (permutation_digits) ต้องอยู่ระหว่าง 2 ถึง {}", MAX_PERMUTATION_DIGITS));
+    }
+
+    let mut seen = HashSet::new();
+    let mut history_counts: HashMap<usize, HashMap<&str, usize>> = HashMap::new();
+    let mut expanded = Vec::new();
+    for source in predicted_numbers(response) {
+        let ending = &source[source.len().saturating_sub(digits)..];
+        let counts = history_counts.entry(ending.len()).or_insert_with(|| {
+            let mut counts = HashMap::new();
+            for n in numbers_str.iter().filter(|n| n.len() >= ending.len()) {
+                *counts.entry(&n[n.len() - ending.len()..]).or_default() += 1;
+            }
+            counts
+        });
+
+        let ending_digits: Vec<u32> = ending.chars().filter_map(|c| c.to_digit(10)).collect();
+        let mut ranked: Vec<(String, usize)> = permutations(&ending_digits).into_iter()
+            .map(|p| p.iter().map(|d| d.to_string()).collect::<String>())
+            .filter(|p| seen.insert(p.clone()))
+            .map(|p| {
+                let count = counts.get(p.as_str()).copied().unwrap_or(0);
+                (p, count)
+            })
+            .collect();
+        if ranked.is_empty() {
+            continue;
+        }
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        expanded.push(serde_json::json!({
+            "source": source,
+            "ending": ending,
+            "permutations": ranked.iter()
+                .map(|(number, count)| serde_json::json!({ "number": number, "historical_count": count }))
+                .collect::<Vec<_>>(),
+        }));
+    }
+
+    response.prediction_output.insert("PERMUTATIONS".to_string(), serde_json::json!(expanded));
+    Ok(())
+}
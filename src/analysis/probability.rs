@@ -0,0 +1,92 @@
+use crate::models::{CandidateProbability, PredictionDistribution};
+
+/// Number of ranked candidates returned in a prediction distribution.
+const MAX_CANDIDATES: usize = 10;
+
+/// Shrinks a raw probability towards the uniform rate, as if one extra observation had been seen
+/// for every possible outcome: `(evidence * p + 1) / (evidence + support)`. With little evidence or
+/// a huge outcome space the confidence collapses towards `1 / support`, which is the honest answer.
+fn calibrate(probability: f64, evidence: usize, support_size: u64) -> f64 {
+    (evidence as f64 * probability + 1.0) / (evidence as f64 + support_size as f64)
+}
+
+fn entropy_term(p: f64) -> f64 {
+    if p > 0.0 { -p * p.log2() } else { 0.0 }
+}
+
+/// Entropy (bits) of the calibrated distribution over the whole outcome space: the listed outcomes
+/// plus every unlisted outcome, each of which keeps the calibrated floor `1 / (evidence + support)`.
+fn calibrated_entropy(raw: &[(String, f64)], evidence: usize, support_size: u64) -> f64 {
+    let listed: f64 = raw.iter().map(|(_, p)| entropy_term(calibrate(*p, evidence, support_size))).sum();
+    let unlisted = support_size.saturating_sub(raw.len() as u64) as f64;
+    listed + unlisted * entropy_term(calibrate(0.0, evidence, support_size))
+}
+
+fn finish(candidates: Vec<CandidateProbability>, support_size: u64, entropy_bits: f64) -> PredictionDistribution {
+    let max_entropy_bits = (support_size as f64).log2();
+    PredictionDistribution {
+        candidates,
+        support_size,
+        entropy_bits,
+        max_entropy_bits,
+        normalized_entropy: if max_entropy_bits > 0.0 { entropy_bits / max_entropy_bits } else { 0.0 },
+        digit_probabilities: None,
+        outcomes: Vec::new(),
+    }
+}
+
+/// Builds a ranked distribution from raw probabilities of the observed candidates.
+/// `support_size` is the number of possible outcomes and `evidence` the number of observations
+/// the probabilities were estimated from.
+pub(super) fn from_probabilities(mut raw: Vec<(String, f64)>, support_size: u64, evidence: usize) -> PredictionDistribution {
+    raw.retain(|(_, p)| *p > 0.0);
+    raw.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    let entropy_bits = calibrated_entropy(&raw, evidence, support_size);
+    let candidates = raw.iter()
+        .take(MAX_CANDIDATES)
+        .map(|(candidate, p)| CandidateProbability {
+            candidate: candidate.clone(),
+            probability: *p,
+            calibrated_confidence: calibrate(*p, evidence, support_size),
+        })
+        .collect();
+    PredictionDistribution { outcomes: raw, ..finish(candidates, support_size, entropy_bits) }
+}
+
+/// Builds a ranked distribution from occurrence counts, using each candidate's share of the total.
+pub(super) fn from_counts<'a>(counts: impl IntoIterator<Item = (&'a String, usize)>, support_size: u64) -> PredictionDistribution {
+    let counts: Vec<(&String, usize)> = counts.into_iter().collect();
+    let total: usize = counts.iter().map(|(_, c)| c).sum();
+    let raw = counts.into_iter()
+        .map(|(candidate, c)| (candidate.clone(), c as f64 / total.max(1) as f64))
+        .collect();
+    from_probabilities(raw, support_size, total)
+}
+
+/// Builds a distribution over whole numbers from independent per-position digit probabilities.
+/// The numbers with the highest joint probability are found with a beam search, and the entropy
+/// of the joint distribution is the sum of the per-position entropies.
+pub(super) fn from_digit_positions(positions: &[[f64; 10]], evidence: usize) -> PredictionDistribution {
+    let support_size = 10u64.saturating_pow(positions.len() as u32);
+    let digits: Vec<(String, f64)> = (0..10).map(|d| (d.to_string(), 0.0)).collect();
+
+    let mut entropy_bits = 0.0;
+    let mut beam: Vec<(String, f64, f64)> = vec![(String::new(), 1.0, 1.0)];
+    for probabilities in positions {
+        let raw: Vec<(String, f64)> = digits.iter().map(|(d, _)| (d.clone(), probabilities[d.parse::<usize>().unwrap()])).collect();
+        entropy_bits += calibrated_entropy(&raw, evidence, 10);
+
+        let mut next: Vec<(String, f64, f64)> = beam.iter()
+            .flat_map(|(prefix, p, c)| raw.iter().map(move |(d, pd)| (format!("{}{}", prefix, d), p * pd, c * calibrate(*pd, evidence, 10))))
+            .collect();
+        next.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        next.truncate(MAX_CANDIDATES);
+        beam = next;
+    }
+
+    let candidates = beam.into_iter()
+        .filter(|(_, p, _)| *p > 0.0)
+        .map(|(candidate, probability, calibrated_confidence)| CandidateProbability { candidate, probability, calibrated_confidence })
+        .collect();
+    PredictionDistribution { digit_probabilities: Some(positions.to_vec()), ..finish(candidates, support_size, entropy_bits) }
+}
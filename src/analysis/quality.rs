@@ -0,0 +1,186 @@
+use super::parse_draw_date;
+use crate::models::{DataQualityIssue, DataQualityIssueKind, DataQualityReport, LottoResult, LottoType};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// An interval longer than this multiple of the typical one is reported as missing draws. Thai draws
+/// moved by a holiday (e.g. 17 January or 2 May) stay well below it.
+const GAP_FACTOR: f64 = 1.5;
+
+/// Rows that share a date, keyed by the parsed date when it can be read and by the raw text otherwise,
+/// so that "2024-01-16" and "16/01/2567" count as the same day.
+fn rows_by_date(results: &[LottoResult]) -> BTreeMap<String, Vec<&LottoResult>> {
+    let mut by_date: BTreeMap<String, Vec<&LottoResult>> = BTreeMap::new();
+    for r in results {
+        let key = parse_draw_date(&r.draw_date).map_or_else(|| r.draw_date.trim().to_string(), |d| d.to_string());
+        by_date.entry(key).or_default().push(r);
+    }
+    by_date
+}
+
+fn duplicate_issues(results: &[LottoResult]) -> Vec<DataQualityIssue> {
+    let mut issues = Vec::new();
+    for (date, rows) in rows_by_date(results).into_iter().filter(|(_, rows)| rows.len() > 1) {
+        let mut distinct: Vec<(&str, &str)> = rows.iter().map(|r| (r.prize1.as_str(), r.prize2.as_str())).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+        let issue = if distinct.len() == 1 {
+            DataQualityIssue {
+                kind: DataQualityIssueKind::DuplicateRow,
+                dates: vec![date],
+                detail: format!("แถว {} / {} ถูกบันทึกซ้ำ {} ครั้ง", distinct[0].0, distinct[0].1, rows.len()),
+            }
+        } else {
+            DataQualityIssue {
+                kind: DataQualityIssueKind::DuplicateDate,
+                dates: vec![date],
+                detail: format!(
+                    "มี {} แถวในวันเดียวกันแต่รางวัลต่างกัน: {}",
+                    rows.len(),
+                    distinct.iter().map(|(p1, p2)| format!("{} / {}", p1, p2)).collect::<Vec<_>>().join(", ")
+                ),
+            }
+        };
+        issues.push(issue);
+    }
+    issues
+}
+
+fn digit_count_issues(lotto_type: &LottoType, results: &[LottoResult]) -> Vec<DataQualityIssue> {
+    let names = lotto_type.prize_names();
+    let digits = lotto_type.prize_digits();
+    results.iter()
+        .flat_map(|r| {
+            [&r.prize1, &r.prize2].into_iter().enumerate()
+                .filter(move |(i, prize)| prize.len() != digits[*i] || !prize.chars().all(|c| c.is_ascii_digit()))
+                .map(move |(i, prize)| DataQualityIssue {
+                    kind: DataQualityIssueKind::WrongDigitCount,
+                    dates: vec![r.draw_date.clone()],
+                    detail: format!("{} '{}' ไม่ใช่ตัวเลข {} หลัก", names[i], prize, digits[i]),
+                })
+        })
+        .collect()
+}
+
+/// Checks the calendar and the sequence of prizes. Both need the rows in date order, so rows whose
+/// date cannot be read are reported and left out.
+fn calendar_issues(results: &[LottoResult]) -> (Vec<DataQualityIssue>, Option<i64>) {
+    let mut issues = Vec::new();
+    let mut dated: Vec<(NaiveDate, &LottoResult)> = Vec::new();
+    for r in results {
+        match parse_draw_date(&r.draw_date) {
+            Some(date) => dated.push((date, r)),
+            None => issues.push(DataQualityIssue {
+                kind: DataQualityIssueKind::UnparsableDate,
+                dates: vec![r.draw_date.clone()],
+                detail: format!("อ่านวันที่ '{}' ไม่ได้", r.draw_date),
+            }),
+        }
+    }
+    dated.sort_by_key(|(date, _)| *date);
+    dated.dedup_by_key(|(date, _)| *date);
+
+    for pair in dated.windows(2) {
+        let ((d1, r1), (d2, r2)) = (pair[0], pair[1]);
+        if r1.prize1 == r2.prize1 && r1.prize2 == r2.prize2 {
+            issues.push(DataQualityIssue {
+                kind: DataQualityIssueKind::RepeatedPrizes,
+                dates: vec![d1.to_string(), d2.to_string()],
+                detail: format!("สองงวดติดกันมีรางวัลเหมือนกันทุกตัว ({} / {}) ซึ่งแทบเป็นไปไม่ได้หากไม่ได้คัดลอกแถวผิด", r1.prize1, r1.prize2),
+            });
+        }
+    }
+
+    let gaps: Vec<(NaiveDate, NaiveDate, i64)> = dated.windows(2).map(|w| (w[0].0, w[1].0, (w[1].0 - w[0].0).num_days())).collect();
+    if gaps.len() < 2 {
+        return (issues, None);
+    }
+    let mut intervals: Vec<i64> = gaps.iter().map(|(_, _, days)| *days).collect();
+    intervals.sort_unstable();
+    let typical = intervals[intervals.len() / 2];
+    for (from, to, days) in gaps.into_iter().filter(|(_, _, days)| *days as f64 > GAP_FACTOR * typical as f64) {
+        let missing = ((days as f64 / typical as f64).round() as i64 - 1).max(1);
+        issues.push(DataQualityIssue {
+            kind: DataQualityIssueKind::MissingDraws,
+            dates: vec![from.to_string(), to.to_string()],
+            detail: format!("ห่างกัน {} วัน (ปกติ {} วัน) คาดว่าขาดไปประมาณ {} งวด", days, typical, missing),
+        });
+    }
+    (issues, Some(typical))
+}
+
+/// Scans the stored draws of one lottery type for the problems a scraper bug usually leaves behind.
+pub(super) fn check_results(lotto_type: &LottoType, results: &[LottoResult]) -> DataQualityReport {
+    let dates: Vec<NaiveDate> = results.iter().filter_map(|r| parse_draw_date(&r.draw_date)).collect();
+    let (calendar, typical_interval_days) = calendar_issues(results);
+    let mut issues = duplicate_issues(results);
+    issues.extend(digit_count_issues(lotto_type, results));
+    issues.extend(calendar);
+    DataQualityReport {
+        lotto_type: lotto_type.clone(),
+        draws: results.len(),
+        first_date: dates.iter().min().map(|d| d.to_string()),
+        last_date: dates.iter().max().map(|d| d.to_string()),
+        typical_interval_days,
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn row(date: &str, prize1: &str, prize2: &str) -> LottoResult {
+        LottoResult { draw_date: date.to_string(), prize1: prize1.to_string(), prize2: prize2.to_string(), other_prizes: BTreeMap::new() }
+    }
+
+    fn kinds(report: &DataQualityReport) -> Vec<DataQualityIssueKind> {
+        report.issues.iter().map(|i| i.kind).collect()
+    }
+
+    #[test]
+    fn twice_monthly_draws_have_no_gaps() {
+        let results = [
+            row("2024-01-01", "111111", "11"),
+            row("2024-01-17", "222222", "22"),
+            row("2024-02-01", "333333", "33"),
+            row("16/02/2567", "444444", "44"),
+        ];
+        let report = check_results(&LottoType::Thai, &results);
+        assert!(report.issues.is_empty(), "{:?}", kinds(&report));
+        assert_eq!(report.typical_interval_days, Some(15));
+    }
+
+    #[test]
+    fn a_long_interval_is_reported_with_the_missing_draws() {
+        let results = [
+            row("2024-01-01", "111111", "11"),
+            row("2024-01-16", "222222", "22"),
+            row("2024-02-01", "333333", "33"),
+            row("2024-03-16", "444444", "44"),
+            row("2024-04-01", "555555", "55"),
+        ];
+        let report = check_results(&LottoType::Thai, &results);
+        assert_eq!(kinds(&report), [DataQualityIssueKind::MissingDraws]);
+        assert_eq!(report.issues[0].dates, ["2024-02-01", "2024-03-16"]);
+        assert!(report.issues[0].detail.contains("ประมาณ 2 งวด"));
+    }
+
+    #[test]
+    fn duplicates_and_bad_rows_are_told_apart() {
+        let results = [
+            row("2024-01-01", "111111", "11"),
+            row("01/01/2567", "111111", "11"),
+            row("2024-01-16", "222222", "22"),
+            row("2024-01-16", "999999", "99"),
+            row("2024-02-01", "12345", "33"),
+            row("not a date", "444444", "44"),
+        ];
+        let report = check_results(&LottoType::Thai, &results);
+        let kinds = kinds(&report);
+        for kind in [DataQualityIssueKind::DuplicateRow, DataQualityIssueKind::DuplicateDate, DataQualityIssueKind::WrongDigitCount, DataQualityIssueKind::UnparsableDate] {
+            assert_eq!(kinds.iter().filter(|&&k| k == kind).count(), 1, "{:?}", kind);
+        }
+    }
+}
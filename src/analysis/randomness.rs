@@ -0,0 +1,177 @@
+use super::parse_digit_rows;
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+use statrs::distribution::{ChiSquared, ContinuousCDF};
+
+/// Significance level for the randomness tests before the Bonferroni correction.
+const ALPHA: f64 = 0.05;
+/// Longest number the KS test reads as a whole value; 18 digits always fit in a `u64`.
+const MAX_KS_DIGITS: usize = 18;
+
+/// Pearson's chi-square goodness-of-fit test of a digit position against a uniform 0-9 distribution.
+/// Returns (statistic, degrees of freedom, p-value).
+fn chi_square_uniform(counts: &[u32; 10]) -> Result<(f64, f64, f64)> {
+    let total: u32 = counts.iter().sum();
+    let expected = total as f64 / 10.0;
+    let statistic = counts.iter().map(|&obs| (obs as f64 - expected).powi(2) / expected).sum::<f64>();
+    let dof = 9.0;
+    let dist = ChiSquared::new(dof).map_err(|e| anyhow!("ไม่สามารถสร้างการแจกแจงไคสแควร์: {}", e))?;
+    Ok((statistic, dof, dist.sf(statistic)))
+}
+
+/// Asymptotic survival function of the Kolmogorov distribution, using Stephens' small-sample correction.
+fn kolmogorov_p_value(d: f64, n: usize) -> f64 {
+    let sqrt_n = (n as f64).sqrt();
+    let lambda = (sqrt_n + 0.12 + 0.11 / sqrt_n) * d;
+    if lambda < 1e-3 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    for k in 1..=100 {
+        let k = k as f64;
+        let term = 2.0 * (-1.0f64).powf(k - 1.0) * (-2.0 * k * k * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 {
+            break;
+        }
+    }
+    sum.clamp(0.0, 1.0)
+}
+
+/// One-sample Kolmogorov-Smirnov test of integer values against the discrete uniform distribution on
+/// `0..outcomes`, whose CDF at k is (k + 1) / outcomes. Both CDFs are step functions, so the largest gap
+/// lies at an observed value or just before one. The Kolmogorov p-value is conservative for a discrete
+/// distribution, so it never overstates the evidence. Returns (D statistic, p-value).
+fn ks_discrete_uniform(mut values: Vec<u64>, outcomes: u64) -> (f64, f64) {
+    values.sort_unstable();
+    let n = values.len() as f64;
+    let outcomes = outcomes as f64;
+    let mut d: f64 = 0.0;
+    let mut below = 0;
+    while below < values.len() {
+        let value = values[below];
+        let at_or_below = below + values[below..].iter().take_while(|&&v| v == value).count();
+        d = d
+            .max((value as f64 / outcomes - below as f64 / n).abs())
+            .max((at_or_below as f64 / n - (value as f64 + 1.0) / outcomes).abs());
+        below = at_or_below;
+    }
+    (d, kolmogorov_p_value(d, values.len()))
+}
+
+/// Runs chi-square tests on every digit position and a KS test on the whole values.
+pub(super) fn run_randomness_analysis(numbers_str: &[String]) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 20 {
+        return Err(anyhow!("การทดสอบความสุ่มต้องการข้อมูลอย่างน้อย 20 ชุด แต่พบเพียง {}", numbers_str.len()));
+    }
+    let rows = parse_digit_rows(numbers_str)?;
+    let num_len = rows[0].len();
+    let outcomes = 10u64.checked_pow(num_len as u32)
+        .filter(|_| num_len <= MAX_KS_DIGITS)
+        .ok_or_else(|| anyhow!("การทดสอบความสุ่มรองรับตัวเลขไม่เกิน {} หลัก แต่พบ {} หลัก กรุณาเลือกส่วนของตัวเลข (target) ที่สั้นลง", MAX_KS_DIGITS, num_len))?;
+
+    let mut pattern_analysis = HashMap::new();
+    let mut p_values = Vec::with_capacity(num_len + 1);
+
+    for pos in 0..num_len {
+        let mut counts = [0u32; 10];
+        for row in &rows {
+            counts[row[pos] as usize] += 1;
+        }
+        let (statistic, dof, p_value) = chi_square_uniform(&counts)?;
+        p_values.push(p_value);
+        pattern_analysis.insert(
+            format!("ไคสแควร์ หลักที่ {}", pos + 1),
+            serde_json::json!({
+                "statistic": statistic,
+                "degrees_of_freedom": dof,
+                "p_value": p_value,
+                "counts": counts,
+            }),
+        );
+    }
+
+    let values: Vec<u64> = numbers_str.iter()
+        .map(|n| n.parse().map_err(|_| anyhow!("'{}' ไม่ใช่ตัวเลขที่ถูกต้องสำหรับการทดสอบ Kolmogorov-Smirnov", n)))
+        .collect::<Result<_>>()?;
+    let (ks_statistic, ks_p_value) = ks_discrete_uniform(values, outcomes);
+    p_values.push(ks_p_value);
+    pattern_analysis.insert(
+        "Kolmogorov-Smirnov".to_string(),
+        serde_json::json!({
+            "statistic": ks_statistic,
+            "sample_size": rows.len(),
+            "p_value": ks_p_value,
+        }),
+    );
+
+    // Bonferroni correction: every position plus the KS test is a separate hypothesis.
+    let corrected_alpha = ALPHA / p_values.len() as f64;
+    let min_p_value = p_values.iter().cloned().fold(1.0, f64::min);
+    let biased = min_p_value < corrected_alpha;
+    let verdict = if biased {
+        "พบหลักฐานของความไม่สุ่ม (มีความเอนเอียง)"
+    } else {
+        "ไม่พบหลักฐานของความเอนเอียง ข้อมูลสอดคล้องกับการสุ่ม"
+    };
+
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(verdict));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("การทดสอบความสุ่ม (Chi-square & KS)"));
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("ขนาดชุดข้อมูล".to_string(), rows.len().to_string()),
+            ("จำนวนหลักที่ทดสอบ".to_string(), num_len.to_string()),
+            ("ระดับนัยสำคัญ (หลังปรับ Bonferroni)".to_string(), format!("{:.4}", corrected_alpha)),
+            ("p-value ต่ำสุด".to_string(), format!("{:.4}", min_p_value)),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), "การทดสอบไคสแควร์ (Chi-square) ตรวจสอบว่าเลข 0-9 ในแต่ละหลักปรากฏบ่อยเท่ากันหรือไม่ (องศาอิสระ 9) ส่วนการทดสอบ Kolmogorov-Smirnov ตรวจสอบว่าค่าของตัวเลขทั้งชุดกระจายตัวสม่ำเสมอตลอดช่วงที่เป็นไปได้หรือไม่".to_string()),
+            ("การตีความ".to_string(), format!("p-value ที่ต่ำกว่า {:.4} ถือว่ามีนัยสำคัญ ค่านี้ปรับด้วยวิธี Bonferroni เพราะมีการทดสอบหลายครั้งพร้อมกัน หากไม่มีการทดสอบใดมีนัยสำคัญ แสดงว่าไม่มีรูปแบบที่ใช้ทำนายได้", corrected_alpha)),
+        ]),
+        evaluation: None,
+        prediction_distribution: None,
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_too_long_for_the_ks_test_are_rejected() {
+        let long: Vec<String> = (0..20).map(|i| format!("{:020}", i)).collect();
+        assert!(run_randomness_analysis(&long).is_err());
+        let longest: Vec<String> = (0..20).map(|i| format!("{:018}", i * 7_919)).collect();
+        assert!(run_randomness_analysis(&longest).is_ok());
+    }
+
+    #[test]
+    fn ks_accepts_evenly_spread_single_digits() {
+        let values: Vec<u64> = (0..1000).map(|i| i % 10).collect();
+        let (d, p_value) = ks_discrete_uniform(values, 10);
+        assert!(d < 1e-12);
+        assert!(p_value > 0.99);
+    }
+
+    #[test]
+    fn ks_rejects_digits_piled_on_one_end() {
+        let values: Vec<u64> = (0..200).map(|i| i % 3).collect();
+        let (d, p_value) = ks_discrete_uniform(values, 10);
+        assert!((d - 0.7).abs() < 1e-12);
+        assert!(p_value < 1e-6);
+    }
+
+    #[test]
+    fn uniform_single_digit_target_is_not_rejected() {
+        let numbers: Vec<String> = (0..500).map(|i| (i % 10).to_string()).collect();
+        let response = run_randomness_analysis(&numbers).unwrap();
+        assert_eq!(response.prediction_output["PREDICTION"], "ไม่พบหลักฐานของความเอนเอียง ข้อมูลสอดคล้องกับการสุ่ม");
+    }
+}
@@ -0,0 +1,194 @@
+use super::permutations::predicted_numbers;
+use super::seasonal::estimate_next_draw;
+use super::{apply_target, parse_draw_date};
+use crate::models::{
+    AnalysisMethod, AnalysisResponse, AnalyzeRequest, HistoryPrize, LeaderboardPeriod, LeaderboardRow, LedgerEntry, LedgerOutcome,
+    LottoResult, LottoType, TargetSpec,
+};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+
+/// Builds the ledger entry of a finished analysis; its id is assigned when it is added to the ledger.
+/// Methods that report a verdict rather than a number, such as the randomness tests, have nothing to
+/// score and are not saved. Analyses of posted numbers are saved without a lottery type and prize,
+/// since only a history reference tells which draw to compare them with, so they are never scored.
+pub(super) fn record(req: &AnalyzeRequest, response: &AnalysisResponse) -> Option<LedgerEntry> {
+    let mut predicted = predicted_numbers(response).into_iter();
+    let prediction = predicted.next()?;
+
+    let mut dates: Vec<NaiveDate> = req.dates.iter().flatten().filter_map(|d| parse_draw_date(d)).collect();
+    dates.sort_unstable();
+    let target_draw_date = match dates.as_slice() {
+        [.., previous, latest] => Some(estimate_next_draw(*latest, *previous).to_string()),
+        _ => None,
+    };
+    let mut params = serde_json::to_value(&req.params).unwrap_or_default();
+    if let Some(set) = params.as_object_mut() {
+        set.retain(|_, value| !value.is_null());
+    }
+
+    Some(LedgerEntry {
+        id: 0,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        method: req.method,
+        params,
+        target: req.target.unwrap_or(TargetSpec::Whole),
+        lotto_type: req.history.as_ref().map(|h| h.lotto_type.clone()),
+        prize: req.history.as_ref().map(|h| h.prize),
+        input_draws: req.numbers.len(),
+        input_first_date: dates.first().map(|d| d.to_string()),
+        input_last_date: dates.last().map(|d| d.to_string()),
+        target_draw_date,
+        prediction,
+        alternatives: predicted.collect(),
+        outcome: None,
+    })
+}
+
+/// Scores every open entry of `lotto_type` whose draw is among `results`: the first draw after the
+/// entry's input window. The drawn prize is reduced to the entry's target, and a prediction hits
+/// when it matches the end of it, so a 2-digit prediction is checked against the last two digits.
+pub(super) fn score(entries: &mut [LedgerEntry], lotto_type: &LottoType, results: &[LottoResult]) -> usize {
+    let mut draws: Vec<(NaiveDate, &LottoResult)> = results.iter()
+        .filter_map(|r| parse_draw_date(&r.draw_date).map(|date| (date, r)))
+        .collect();
+    draws.sort_by_key(|(date, _)| *date);
+
+    let scored_at = chrono::Utc::now().to_rfc3339();
+    let mut scored = 0;
+    for entry in entries.iter_mut().filter(|e| e.outcome.is_none() && e.lotto_type.as_ref() == Some(lotto_type)) {
+        let (Some(prize), Some(last_input)) = (entry.prize, entry.input_last_date.as_deref().and_then(parse_draw_date)) else { continue };
+        let Some((draw_date, draw)) = draws.iter().find(|(date, _)| *date > last_input) else { continue };
+        let drawn: String = match prize {
+            HistoryPrize::Prize1 => &draw.prize1,
+            HistoryPrize::Prize2 => &draw.prize2,
+        }.chars().filter(|c| c.is_ascii_digit()).collect();
+        let Some(actual) = apply_target(&[drawn], entry.target).ok().and_then(|v| v.into_iter().next()) else { continue };
+
+        let matches = |p: &String| !p.is_empty() && actual.ends_with(p.as_str());
+        let hit = matches(&entry.prediction);
+        let hit_any = hit || entry.alternatives.iter().any(matches);
+        entry.outcome = Some(LedgerOutcome { draw_date: draw_date.to_string(), actual, hit, hit_any, scored_at: scored_at.clone() });
+        scored += 1;
+    }
+    scored
+}
+
+/// Hit rate of every method over its scored predictions, next to the rate of a random guess of the
+/// same length, best first.
+pub(super) fn leaderboard(entries: &[LedgerEntry]) -> Vec<LeaderboardRow> {
+    let mut by_method: HashMap<AnalysisMethod, Vec<&LedgerEntry>> = HashMap::new();
+    for entry in entries {
+        by_method.entry(entry.method).or_default().push(entry);
+    }
+
+    let mut rows: Vec<LeaderboardRow> = by_method.into_iter()
+        .map(|(method, entries)| {
+            let outcomes: Vec<(&LedgerEntry, &LedgerOutcome)> = entries.iter()
+                .filter_map(|e| e.outcome.as_ref().map(|o| (*e, o)))
+                .collect();
+            let scored = outcomes.len();
+            let hits = outcomes.iter().filter(|(_, o)| o.hit).count();
+            let chance: f64 = outcomes.iter().map(|(e, _)| 10f64.powi(-(e.prediction.len() as i32))).sum();
+
+            let mut months: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+            for (_, outcome) in &outcomes {
+                let month = months.entry(outcome.draw_date.get(..7).unwrap_or(&outcome.draw_date)).or_default();
+                month.0 += 1;
+                month.1 += usize::from(outcome.hit);
+            }
+            LeaderboardRow {
+                method,
+                predictions: entries.len(),
+                scored,
+                hits,
+                hit_rate: (scored > 0).then(|| hits as f64 / scored as f64),
+                chance_hit_rate: (scored > 0).then(|| chance / scored as f64),
+                hits_any: outcomes.iter().filter(|(_, o)| o.hit_any).count(),
+                by_month: months.into_iter()
+                    .map(|(month, (scored, hits))| LeaderboardPeriod { month: month.to_string(), scored, hits, hit_rate: hits as f64 / scored as f64 })
+                    .collect(),
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.hit_rate.unwrap_or(-1.0).partial_cmp(&a.hit_rate.unwrap_or(-1.0)).unwrap()
+            .then(b.scored.cmp(&a.scored))
+    });
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn request(history: bool) -> AnalyzeRequest {
+        let mut req = serde_json::json!({
+            "numbers": ["123456", "654321"],
+            "dates": ["2025-05-01", "2025-05-16"],
+            "method": "ml_decision_tree",
+        });
+        if history {
+            req["history"] = serde_json::json!({"lotto_type": "thai", "prize": "prize1"});
+        }
+        serde_json::from_value(req).unwrap()
+    }
+
+    fn response(prediction: &str) -> AnalysisResponse {
+        AnalysisResponse {
+            statistical_summary: HashMap::new(),
+            pattern_analysis: HashMap::new(),
+            prediction_output: HashMap::from([("PREDICTION".to_string(), serde_json::json!(prediction))]),
+            detailed_explanation: HashMap::new(),
+            evaluation: None,
+            prediction_distribution: None,
+            target: None,
+        }
+    }
+
+    #[test]
+    fn predictions_with_a_number_are_recorded() {
+        assert!(record(&request(true), &response("ไม่พบความผิดปกติ")).is_none());
+
+        let posted = record(&request(false), &response("7")).unwrap();
+        assert_eq!(posted.lotto_type, None);
+        assert!(posted.prize.is_none());
+
+        let entry = record(&request(true), &response("7")).unwrap();
+        assert_eq!(entry.lotto_type, Some(LottoType::Thai));
+        assert_eq!(entry.prediction, "7");
+        assert_eq!(entry.target_draw_date.as_deref(), Some("2025-06-01"));
+    }
+
+    #[test]
+    fn a_last_digit_prediction_is_scored_against_the_next_draw() {
+        let mut entries = vec![record(&request(true), &response("7")).unwrap()];
+        let draw = |date: &str, prize1: &str| LottoResult {
+            draw_date: date.to_string(),
+            prize1: prize1.to_string(),
+            prize2: "00".to_string(),
+            other_prizes: BTreeMap::new(),
+        };
+        let results = [draw("2025-05-16", "654321"), draw("2025-06-01", "100007"), draw("2025-06-16", "999999")];
+
+        assert_eq!(score(&mut entries, &LottoType::Thai, &results), 1);
+        let outcome = entries[0].outcome.as_ref().unwrap();
+        assert_eq!(outcome.draw_date, "2025-06-01");
+        assert!(outcome.hit);
+        assert_eq!(score(&mut entries, &LottoType::Thai, &results), 0);
+    }
+
+    #[test]
+    fn posted_predictions_are_never_scored() {
+        let mut entries = vec![record(&request(false), &response("7")).unwrap()];
+        let results = [LottoResult {
+            draw_date: "2025-06-01".to_string(),
+            prize1: "100007".to_string(),
+            prize2: "07".to_string(),
+            other_prizes: BTreeMap::new(),
+        }];
+        assert_eq!(score(&mut entries, &LottoType::Thai, &results), 0);
+        assert!(entries[0].outcome.is_none());
+    }
+}
@@ -0,0 +1,349 @@
+use super::{parse_digit_rows, probability};
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use statrs::distribution::{ChiSquared, ContinuousCDF};
+
+/// Family-wise significance level, divided by the number of breakdowns tested (Bonferroni).
+const ALPHA: f64 = 0.05;
+/// A draw belongs to a holiday period when it is held on the holiday or up to this many days after it.
+const HOLIDAY_WINDOW_DAYS: i64 = 14;
+/// Label of the draws that are not in any holiday period.
+const NO_HOLIDAY: &str = "ช่วงปกติ";
+
+const MONTH_NAMES: [&str; 12] = [
+    "มกราคม", "กุมภาพันธ์", "มีนาคม", "เมษายน", "พฤษภาคม", "มิถุนายน",
+    "กรกฎาคม", "สิงหาคม", "กันยายน", "ตุลาคม", "พฤศจิกายน", "ธันวาคม",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["วันจันทร์", "วันอังคาร", "วันพุธ", "วันพฤหัสบดี", "วันศุกร์", "วันเสาร์", "วันอาทิตย์"];
+
+/// Thai Buddhist holidays follow the lunar calendar, so their dates are listed per year in this file,
+/// read on every request so later years can be added without rebuilding.
+const HOLIDAYS_PATH: &str = "data/buddhist_holidays.json";
+
+/// One holiday and its date in every year the table lists. Khao Phansa falls on the day after
+/// Asanha Bucha and shares its period.
+#[derive(Deserialize)]
+struct HolidayEntry {
+    name: String,
+    dates: Vec<String>,
+}
+
+/// The holiday table with its dates parsed. Only years in which every holiday has a date are covered.
+struct HolidayCalendar {
+    holidays: Vec<(String, Vec<NaiveDate>)>,
+    years: BTreeSet<i32>,
+}
+
+impl HolidayCalendar {
+    fn load() -> Result<HolidayCalendar> {
+        let content = std::fs::read_to_string(HOLIDAYS_PATH)
+            .map_err(|e| anyhow!("ไม่สามารถอ่านไฟล์วันสำคัญทางพุทธศาสนา {}: {}", HOLIDAYS_PATH, e))?;
+        let entries: Vec<HolidayEntry> = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("รูปแบบไฟล์วันสำคัญทางพุทธศาสนาไม่ถูกต้อง: {}", e))?;
+        HolidayCalendar::from_entries(entries)
+    }
+
+    fn from_entries(entries: Vec<HolidayEntry>) -> Result<HolidayCalendar> {
+        let holidays = entries.into_iter()
+            .map(|entry| {
+                let dates = entry.dates.iter()
+                    .map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                        .map_err(|_| anyhow!("วันที่ของ{}ในไฟล์วันสำคัญทางพุทธศาสนาไม่ถูกต้อง: '{}'", entry.name, d)))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((entry.name, dates))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let years = holidays.iter()
+            .map(|(_, dates)| dates.iter().map(|d| d.year()).collect::<BTreeSet<i32>>())
+            .reduce(|covered, years| &covered & &years)
+            .unwrap_or_default();
+        Ok(HolidayCalendar { holidays, years })
+    }
+
+    /// The holiday whose period contains `date`, or `NO_HOLIDAY`. `None` for years the table does not cover.
+    fn period(&self, date: NaiveDate) -> Option<(usize, String)> {
+        if !self.years.contains(&date.year()) {
+            return None;
+        }
+        for (i, (name, dates)) in self.holidays.iter().enumerate() {
+            if dates.iter().any(|&holiday| (0..=HOLIDAY_WINDOW_DAYS).contains(&(date - holiday).num_days())) {
+                return Some((i, name.clone()));
+            }
+        }
+        Some((self.holidays.len(), NO_HOLIDAY.to_string()))
+    }
+
+    /// The covered years as "2015 ถึง 2026", or a note that none are covered.
+    fn describe_years(&self) -> String {
+        match (self.years.first(), self.years.last()) {
+            (Some(first), Some(last)) if self.years.len() == (last - first + 1) as usize => format!("ปี {} ถึง {}", first, last),
+            (Some(_), Some(_)) => format!("ปี {}", self.years.iter().map(|y| y.to_string()).collect::<Vec<_>>().join(", ")),
+            _ => "(ไม่มีปีใดที่ระบุครบทุกวัน)".to_string(),
+        }
+    }
+}
+
+/// A way of splitting the draws into calendar groups.
+#[derive(Clone, Copy)]
+enum Breakdown {
+    Month,
+    HalfMonth,
+    Weekday,
+    Holiday,
+}
+
+impl Breakdown {
+    const ALL: [Breakdown; 4] = [Breakdown::Month, Breakdown::HalfMonth, Breakdown::Weekday, Breakdown::Holiday];
+
+    fn label(self) -> &'static str {
+        match self {
+            Breakdown::Month => "ตามเดือน",
+            Breakdown::HalfMonth => "งวดวันที่ 1 และงวดวันที่ 16",
+            Breakdown::Weekday => "ตามวันในสัปดาห์",
+            Breakdown::Holiday => "ช่วงวันสำคัญทางพุทธศาสนา",
+        }
+    }
+
+    /// The group a draw date belongs to, as (sort order, name), or `None` when it cannot be placed.
+    fn group_of(self, date: NaiveDate, calendar: &HolidayCalendar) -> Option<(usize, String)> {
+        match self {
+            Breakdown::Month => Some((date.month0() as usize, MONTH_NAMES[date.month0() as usize].to_string())),
+            Breakdown::HalfMonth if date.day() <= 15 => Some((0, "งวดวันที่ 1".to_string())),
+            Breakdown::HalfMonth => Some((1, "งวดวันที่ 16".to_string())),
+            Breakdown::Weekday => {
+                let day = date.weekday().num_days_from_monday() as usize;
+                Some((day, WEEKDAY_NAMES[day].to_string()))
+            }
+            Breakdown::Holiday => calendar.period(date),
+        }
+    }
+}
+
+/// Digit counts of one calendar group, pooled over every digit position.
+struct Group {
+    name: String,
+    draws: usize,
+    digit_counts: [u32; 10],
+}
+
+/// Pearson's chi-square test of independence between the groups and the drawn digits.
+/// Digits never drawn are left out. Returns (statistic, degrees of freedom, p-value, smallest expected count).
+fn chi_square_independence(groups: &[Group]) -> Result<(f64, f64, f64, f64)> {
+    let digit_totals: [u32; 10] = std::array::from_fn(|d| groups.iter().map(|g| g.digit_counts[d]).sum());
+    let digits: Vec<usize> = (0..10).filter(|&d| digit_totals[d] > 0).collect();
+    let total: u32 = digit_totals.iter().sum();
+
+    let mut statistic = 0.0;
+    let mut min_expected = f64::INFINITY;
+    for group in groups {
+        let group_total: u32 = group.digit_counts.iter().sum();
+        for &d in &digits {
+            let expected = group_total as f64 * digit_totals[d] as f64 / total as f64;
+            min_expected = min_expected.min(expected);
+            statistic += (group.digit_counts[d] as f64 - expected).powi(2) / expected;
+        }
+    }
+    let dof = ((groups.len() - 1) * (digits.len().max(2) - 1)) as f64;
+    let dist = ChiSquared::new(dof).map_err(|e| anyhow!("ไม่สามารถสร้างการแจกแจงไคสแควร์: {}", e))?;
+    Ok((statistic, dof, dist.sf(statistic), min_expected))
+}
+
+/// Estimates the date of the draw after `latest`. Twice-monthly draws (about 15 days apart) are held
+/// on the 1st and the 16th; any other schedule is assumed to repeat the latest interval.
+pub(super) fn estimate_next_draw(latest: NaiveDate, previous: NaiveDate) -> NaiveDate {
+    let interval = latest - previous;
+    if !(13..=17).contains(&interval.num_days()) {
+        return latest + interval;
+    }
+    let next = if latest.day() <= 15 {
+        latest.with_day(16)
+    } else if latest.month() == 12 {
+        NaiveDate::from_ymd_opt(latest.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(latest.year(), latest.month() + 1, 1)
+    };
+    next.unwrap_or(latest + interval)
+}
+
+// --- Method 14: Seasonal & Calendar Analysis ---
+/// Digit frequencies broken down by month, by 1st versus 16th draws, by weekday and by Buddhist
+/// holiday periods, each with a chi-square test of whether the digits depend on the group.
+pub(super) fn run_seasonal_analysis(numbers_str: &[String], dates: Option<&[NaiveDate]>) -> Result<AnalysisResponse> {
+    let dates = dates.ok_or_else(|| anyhow!("การวิเคราะห์ตามฤดูกาลต้องการวันที่ของแต่ละงวด (dates) ในรูปแบบ YYYY-MM-DD"))?;
+    if numbers_str.len() < 24 {
+        return Err(anyhow!("การวิเคราะห์ตามฤดูกาลต้องการข้อมูลอย่างน้อย 24 งวด แต่พบเพียง {}", numbers_str.len()));
+    }
+    let rows = parse_digit_rows(numbers_str)?;
+    let calendar = HolidayCalendar::load()?;
+
+    let mut pattern_analysis = HashMap::new();
+    let mut tested = Vec::new();
+    let mut uncovered_draws = 0;
+    for breakdown in Breakdown::ALL {
+        let mut groups: BTreeMap<usize, Group> = BTreeMap::new();
+        let mut ungrouped = 0;
+        for (row, &date) in rows.iter().zip(dates) {
+            let Some((order, name)) = breakdown.group_of(date, &calendar) else {
+                ungrouped += 1;
+                continue;
+            };
+            let group = groups.entry(order).or_insert_with(|| Group { name, draws: 0, digit_counts: [0; 10] });
+            group.draws += 1;
+            for &d in row {
+                group.digit_counts[d as usize] += 1;
+            }
+        }
+        let groups: Vec<Group> = groups.into_values().collect();
+        if matches!(breakdown, Breakdown::Holiday) {
+            uncovered_draws = ungrouped;
+        }
+        let groups_json: Vec<_> = groups.iter()
+            .map(|g| serde_json::json!({
+                "group": g.name,
+                "draws": g.draws,
+                "digit_counts": g.digit_counts,
+                "most_frequent_digit": (0..10).max_by(|&a, &b| g.digit_counts[a].cmp(&g.digit_counts[b]).then(b.cmp(&a))),
+            }))
+            .collect();
+
+        let mut entry = if groups.len() < 2 {
+            serde_json::json!({
+                "groups": groups_json,
+                "note": "ข้อมูลครอบคลุมน้อยกว่า 2 กลุ่ม จึงไม่สามารถทดสอบได้",
+            })
+        } else {
+            let (statistic, dof, p_value, min_expected) = chi_square_independence(&groups)?;
+            tested.push((breakdown, p_value));
+            serde_json::json!({
+                "groups": groups_json,
+                "chi_square": statistic,
+                "degrees_of_freedom": dof,
+                "p_value": p_value,
+                "min_expected_count": min_expected,
+            })
+        };
+        if ungrouped > 0 {
+            entry["ungrouped_draws"] = serde_json::json!(ungrouped);
+        }
+        pattern_analysis.insert(breakdown.label().to_string(), entry);
+    }
+
+    let corrected_alpha = ALPHA / tested.len().max(1) as f64;
+    let significant: Vec<&str> = tested.iter()
+        .filter(|(_, p)| *p < corrected_alpha)
+        .map(|(b, _)| b.label())
+        .collect();
+    for (breakdown, p_value) in &tested {
+        if let Some(entry) = pattern_analysis.get_mut(breakdown.label()) {
+            entry["significant"] = serde_json::json!(*p_value < corrected_alpha);
+        }
+    }
+
+    let mut sorted_dates = dates.to_vec();
+    sorted_dates.sort_unstable();
+    let latest = sorted_dates[sorted_dates.len() - 1];
+    let next_date = estimate_next_draw(latest, sorted_dates[sorted_dates.len() - 2]);
+
+    // Per-position digit shares among past draws in the same month, with add-one smoothing.
+    let same_month: Vec<&Vec<u32>> = rows.iter().zip(dates)
+        .filter(|(_, d)| d.month() == next_date.month())
+        .map(|(r, _)| r)
+        .collect();
+    let positions: Vec<[f64; 10]> = (0..rows[0].len())
+        .map(|pos| {
+            let counts: [usize; 10] = std::array::from_fn(|d| same_month.iter().filter(|r| r[pos] as usize == d).count());
+            std::array::from_fn(|d| (counts[d] + 1) as f64 / (same_month.len() + 10) as f64)
+        })
+        .collect();
+    let distribution = probability::from_digit_positions(&positions, same_month.len());
+
+    let mut prediction_output = HashMap::new();
+    let prediction = distribution.candidates.first().map_or_else(|| "N/A".to_string(), |c| c.candidate.clone());
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(prediction));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("การวิเคราะห์ตามฤดูกาลและปฏิทิน"));
+    let alternatives: Vec<&String> = distribution.candidates.iter().skip(1).take(4).map(|c| &c.candidate).collect();
+    if !alternatives.is_empty() {
+        prediction_output.insert("ทางเลือกอื่นๆ".to_string(), serde_json::json!(alternatives));
+    }
+
+    let verdict = if significant.is_empty() {
+        "ไม่พบการแบ่งกลุ่มใดที่ความถี่ของตัวเลขแตกต่างกันอย่างมีนัยสำคัญ จึงไม่มีหลักฐานว่าตัวเลขที่ออกขึ้นกับปฏิทิน".to_string()
+    } else {
+        format!("ความถี่ของตัวเลขแตกต่างกันอย่างมีนัยสำคัญเมื่อแบ่ง{}", significant.join(", "))
+    };
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("ขนาดชุดข้อมูล".to_string(), numbers_str.len().to_string()),
+            ("ช่วงวันที่".to_string(), format!("{} ถึง {}", sorted_dates[0], latest)),
+            ("วันที่ของงวดถัดไป (ประมาณ)".to_string(), next_date.to_string()),
+            ("งวดในเดือนเดียวกันที่ใช้ทำนาย".to_string(), same_month.len().to_string()),
+            ("ระดับนัยสำคัญหลังปรับ Bonferroni".to_string(), format!("{:.4}", corrected_alpha)),
+            ("งวดที่อยู่นอกช่วงปีของตารางวันสำคัญ".to_string(), format!("{} จาก {}", uncovered_draws, rows.len())),
+            ("ผลการทดสอบ".to_string(), verdict),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), format!("แบ่งงวดตามเดือน ตามงวดวันที่ 1 หรือ 16 ตามวันในสัปดาห์ และตามช่วง {} วันหลังวันสำคัญทางพุทธศาสนา แล้วนับเลขโดดที่ออกในแต่ละกลุ่ม การทดสอบไคสแควร์ของความเป็นอิสระตรวจสอบว่าสัดส่วนของเลขแตกต่างกันระหว่างกลุ่มมากกว่าที่ความบังเอิญจะอธิบายได้หรือไม่ และปรับระดับนัยสำคัญด้วยวิธี Bonferroni", HOLIDAY_WINDOW_DAYS)),
+            ("วันสำคัญทางพุทธศาสนา".to_string(), format!("ใช้ตารางวันมาฆบูชา วิสาขบูชา อาสาฬหบูชา และออกพรรษาจากไฟล์ {} ซึ่งครอบคลุม{} งวดที่อยู่นอกช่วงปีดังกล่าว ({} งวด) จะไม่ถูกนับในการแบ่งกลุ่มนี้ เพิ่มวันที่ของปีอื่นในไฟล์ได้โดยไม่ต้องคอมไพล์ใหม่", HOLIDAYS_PATH, calendar.describe_years(), uncovered_draws)),
+            ("ข้อควรระวัง".to_string(), "กลุ่มที่มีจำนวนงวดน้อยจะให้ค่าคาดหวังต่ำ (min_expected_count ต่ำกว่า 5) ซึ่งทำให้การประมาณค่า p ของการทดสอบไคสแควร์คลาดเคลื่อนได้".to_string()),
+            ("ตรรกะการทำนาย".to_string(), format!("ใช้ความถี่ของเลขแต่ละหลักจากงวดในอดีตที่ออกในเดือน{}เช่นเดียวกับงวดถัดไป ({} งวด) โดยบวกหนึ่งให้ทุกเลขเพื่อไม่ให้เลขที่ไม่เคยออกมีความน่าจะเป็นเป็นศูนย์", MONTH_NAMES[next_date.month0() as usize], same_month.len())),
+        ]),
+        evaluation: None,
+        prediction_distribution: Some(distribution),
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, dates: &[&str]) -> HolidayEntry {
+        HolidayEntry { name: name.to_string(), dates: dates.iter().map(|d| d.to_string()).collect() }
+    }
+
+    #[test]
+    fn only_years_listed_for_every_holiday_are_covered() {
+        let calendar = HolidayCalendar::from_entries(vec![
+            entry("A", &["2024-02-24", "2025-02-12"]),
+            entry("B", &["2025-05-11", "2026-05-31"]),
+        ]).unwrap();
+        assert_eq!(calendar.years, BTreeSet::from([2025]));
+
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(calendar.period(date("2025-02-16")), Some((0, "A".to_string())));
+        assert_eq!(calendar.period(date("2025-05-20")), Some((1, "B".to_string())));
+        assert_eq!(calendar.period(date("2025-06-01")), Some((2, NO_HOLIDAY.to_string())));
+        assert_eq!(calendar.period(date("2024-02-24")), None);
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        assert!(HolidayCalendar::from_entries(vec![entry("A", &["16/01/2567"])]).is_err());
+    }
+
+    #[test]
+    fn bundled_table_loads() {
+        let calendar = HolidayCalendar::load().unwrap();
+        assert_eq!(calendar.holidays.len(), 4);
+        assert!((2015..=2026).all(|y| calendar.years.contains(&y)));
+    }
+
+    #[test]
+    fn reports_draws_outside_the_covered_years() {
+        let start = NaiveDate::from_ymd_opt(2013, 1, 1).unwrap();
+        let dates: Vec<NaiveDate> = (0..72).map(|i| start + chrono::Duration::days(15 * i)).collect();
+        let numbers: Vec<String> = (0..72).map(|i| format!("{:06}", (i * 7_919 + 12_345) % 1_000_000)).collect();
+        let outside = dates.iter().filter(|d| d.year() < 2015).count();
+
+        let response = run_seasonal_analysis(&numbers, Some(&dates)).unwrap();
+        assert_eq!(response.statistical_summary["งวดที่อยู่นอกช่วงปีของตารางวันสำคัญ"], format!("{} จาก 72", outside));
+        assert_eq!(response.pattern_analysis["ช่วงวันสำคัญทางพุทธศาสนา"]["ungrouped_draws"], outside);
+    }
+}
@@ -0,0 +1,138 @@
+use super::parse_digit_rows;
+use crate::models::AnalysisResponse;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+use statrs::distribution::{ContinuousCDF, Normal};
+
+/// Largest autocorrelation lag computed when the request does not set one.
+const DEFAULT_MAX_LAG: usize = 10;
+/// Significance level before the Bonferroni correction.
+const ALPHA: f64 = 0.05;
+
+/// Median of a sequence, averaging the two middle values for even lengths.
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    let mid = sorted.len() / 2;
+    match sorted.len() % 2 {
+        0 => (sorted[mid - 1] + sorted[mid]) / 2.0,
+        _ => sorted[mid],
+    }
+}
+
+/// Wald-Wolfowitz runs test on the above/below-median sequence. Values equal to the median are dropped.
+/// Returns (runs, expected runs, z-score, two-sided p-value), or `None` if one side is empty.
+fn runs_test(values: &[f64], normal: &Normal) -> Option<(usize, f64, f64, f64)> {
+    let med = median(values);
+    let signs: Vec<bool> = values.iter().filter(|&&v| v != med).map(|&v| v > med).collect();
+    let n1 = signs.iter().filter(|&&above| above).count() as f64;
+    let n2 = signs.len() as f64 - n1;
+    if n1 == 0.0 || n2 == 0.0 {
+        return None;
+    }
+    let runs = 1 + signs.windows(2).filter(|w| w[0] != w[1]).count();
+    let n = n1 + n2;
+    let expected = 2.0 * n1 * n2 / n + 1.0;
+    let variance = 2.0 * n1 * n2 * (2.0 * n1 * n2 - n) / (n * n * (n - 1.0));
+    if variance <= 0.0 {
+        return None;
+    }
+    let z = (runs as f64 - expected) / variance.sqrt();
+    Some((runs, expected, z, 2.0 * normal.sf(z.abs())))
+}
+
+/// Sample autocorrelation of the series at lags 1..=max_lag.
+fn autocorrelation(series: &[f64], max_lag: usize) -> Vec<f64> {
+    let n = series.len();
+    let mean = series.iter().sum::<f64>() / n as f64;
+    let denom: f64 = series.iter().map(|x| (x - mean).powi(2)).sum();
+    (1..=max_lag)
+        .map(|lag| {
+            if denom == 0.0 {
+                return 0.0;
+            }
+            let num: f64 = (0..n - lag).map(|t| (series[t] - mean) * (series[t + lag] - mean)).sum();
+            num / denom
+        })
+        .collect()
+}
+
+/// Checks whether consecutive draws depend on each other, using runs tests and per-position autocorrelation.
+/// The input must be ordered from the oldest draw to the newest.
+pub(super) fn run_serial_dependence_analysis(numbers_str: &[String], max_lag: Option<usize>) -> Result<AnalysisResponse> {
+    if numbers_str.len() < 20 {
+        return Err(anyhow!("การวิเคราะห์ความสัมพันธ์ระหว่างงวดต้องการข้อมูลอย่างน้อย 20 งวด แต่พบเพียง {}", numbers_str.len()));
+    }
+    let rows = parse_digit_rows(numbers_str)?;
+    let n = rows.len();
+    let num_len = rows[0].len();
+    let max_lag = max_lag.unwrap_or(DEFAULT_MAX_LAG).clamp(1, n / 2);
+    let band = 1.96 / (n as f64).sqrt();
+    let normal = Normal::new(0.0, 1.0).map_err(|e| anyhow!("ไม่สามารถสร้างการแจกแจงปกติ: {}", e))?;
+
+    let mut pattern_analysis = HashMap::new();
+    let mut runs_p_values = Vec::new();
+
+    let values: Vec<f64> = numbers_str.iter().map(|s| s.parse::<f64>().unwrap_or(0.0)).collect();
+    let mut series_list = vec![("ค่าตัวเลขทั้งชุด".to_string(), values)];
+    for pos in 0..num_len {
+        series_list.push((format!("หลักที่ {}", pos + 1), rows.iter().map(|r| r[pos] as f64).collect()));
+    }
+
+    for (label, series) in &series_list {
+        if let Some((runs, expected, z, p_value)) = runs_test(series, &normal) {
+            runs_p_values.push(p_value);
+            pattern_analysis.insert(
+                format!("Runs test {}", label),
+                serde_json::json!({ "runs": runs, "expected_runs": expected, "z": z, "p_value": p_value }),
+            );
+        }
+    }
+
+    let mut significant_lags = 0;
+    let total_lags = num_len * max_lag;
+    for (label, series) in series_list.iter().skip(1) {
+        let acf = autocorrelation(series, max_lag);
+        let flagged: Vec<usize> = acf.iter().enumerate().filter(|(_, r)| r.abs() > band).map(|(i, _)| i + 1).collect();
+        significant_lags += flagged.len();
+        pattern_analysis.insert(
+            format!("Autocorrelation {}", label),
+            serde_json::json!({ "acf": acf, "significant_lags": flagged }),
+        );
+    }
+
+    // Bonferroni correction across the runs tests; for the ACF, about 5% of lags cross the band by chance alone.
+    let corrected_alpha = ALPHA / runs_p_values.len().max(1) as f64;
+    let runs_flagged = runs_p_values.iter().filter(|&&p| p < corrected_alpha).count();
+    let significant_share = significant_lags as f64 / total_lags as f64;
+    let dependent = runs_flagged > 0 || significant_share > 2.0 * ALPHA;
+    let verdict = if dependent {
+        "พบหลักฐานว่าผลแต่ละงวดอาจสัมพันธ์กับงวดก่อนหน้า"
+    } else {
+        "ไม่พบหลักฐานความสัมพันธ์ระหว่างงวด โมเดลเชิงลำดับไม่มีพื้นฐานทางสถิติรองรับ"
+    };
+
+    let mut prediction_output = HashMap::new();
+    prediction_output.insert("PREDICTION".to_string(), serde_json::json!(verdict));
+    prediction_output.insert("METHOD".to_string(), serde_json::json!("Runs Test และ Autocorrelation"));
+
+    Ok(AnalysisResponse {
+        statistical_summary: HashMap::from([
+            ("จำนวนงวด".to_string(), n.to_string()),
+            ("Lag สูงสุด".to_string(), max_lag.to_string()),
+            ("ขอบเขตนัยสำคัญของ ACF (±)".to_string(), format!("{:.4}", band)),
+            ("Runs test ที่มีนัยสำคัญ".to_string(), format!("{} จาก {}", runs_flagged, runs_p_values.len())),
+            ("Lag ที่เกินขอบเขต".to_string(), format!("{} จาก {} ({:.1}%)", significant_lags, total_lags, significant_share * 100.0)),
+        ]),
+        pattern_analysis,
+        prediction_output,
+        detailed_explanation: HashMap::from([
+            ("หลักการ".to_string(), "Runs test ของ Wald-Wolfowitz แปลงลำดับผลรางวัลเป็น 'สูงกว่า/ต่ำกว่ามัธยฐาน' แล้วนับจำนวนช่วงที่ต่อเนื่องกัน (runs) หากมีน้อยหรือมากเกินไปแสดงว่าแต่ละงวดไม่อิสระต่อกัน ส่วน Autocorrelation วัดความสัมพันธ์ของเลขในแต่ละหลักกับงวดก่อนหน้าที่ระยะ 1 ถึง N งวด".to_string()),
+            ("การตีความ".to_string(), format!("ค่า ACF ที่เกิน ±{:.4} ถือว่ามีนัยสำคัญที่ระดับ 95% แต่แม้ข้อมูลสุ่มจริงก็จะมีประมาณ 5% ของ lag ที่เกินขอบเขต สำหรับ Runs test ใช้ระดับนัยสำคัญ {:.4} หลังปรับ Bonferroni", band, corrected_alpha)),
+        ]),
+        evaluation: None,
+        prediction_distribution: None,
+        target: None,
+    })
+}
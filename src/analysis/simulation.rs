@@ -0,0 +1,320 @@
+use super::dispatch;
+use crate::models::{
+    AnalysisMethod, AnalysisResponse, LottoType, NoiseMetric, NoiseSimulationReport, NoiseSimulationRequest, PrizeTierHits,
+    StrategySimulationReport, StrategySimulationRequest,
+};
+use crate::payouts::{self, PrizeMatch, THAI_PRIZES, THAI_TICKET_PRICE};
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Seed used when the request does not set one.
+const DEFAULT_SEED: u64 = 42;
+/// Random histories analysed when the request does not set `simulations`.
+const DEFAULT_NOISE_SIMULATIONS: usize = 100;
+/// Every random history runs a full analysis, so the count is capped to keep requests bounded.
+const MAX_NOISE_SIMULATIONS: usize = 1_000;
+/// Runs simulated when the strategy request does not set `simulations`.
+const DEFAULT_STRATEGY_SIMULATIONS: usize = 10_000;
+const MAX_STRATEGY_SIMULATIONS: usize = 100_000;
+/// Draws per run when the strategy request does not set `draws`: one year of Thai draws.
+const DEFAULT_STRATEGY_DRAWS: usize = 24;
+/// Fifty years of Thai draws.
+const MAX_STRATEGY_DRAWS: usize = 1_200;
+/// Draws played over all runs; longer runs get fewer of them so a request stays bounded.
+const MAX_STRATEGY_PLAYED_DRAWS: usize = MAX_STRATEGY_SIMULATIONS * DEFAULT_STRATEGY_DRAWS;
+const MAX_STRATEGY_TICKETS: usize = 100;
+/// p-values below this are counted as a "significant" finding.
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// A uniformly random number of `digits` digits, zero-padded.
+fn random_number(rng: &mut StdRng, digits: usize) -> String {
+    (0..digits).map(|_| char::from(b'0' + rng.gen_range(0..10u8))).collect()
+}
+
+/// Counts every `p_value*` entry below the significance level anywhere in the pattern analysis.
+fn count_significant(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Object(map) => map.iter()
+            .map(|(key, v)| match v.as_f64() {
+                Some(p) if key.starts_with("p_value") => usize::from(p < SIGNIFICANCE_LEVEL),
+                _ => count_significant(v),
+            })
+            .sum(),
+        serde_json::Value::Array(items) => items.iter().map(count_significant).sum(),
+        _ => 0,
+    }
+}
+
+/// Pattern measures taken from any analyzer's response, each with whether a larger value looks
+/// more like a pattern.
+fn pattern_metrics(response: &AnalysisResponse) -> Vec<(&'static str, bool, f64)> {
+    let mut metrics = Vec::new();
+    if let Some(distribution) = &response.prediction_distribution {
+        if let Some(top) = distribution.candidates.first() {
+            metrics.push(("top_candidate_probability", true, top.probability));
+        }
+        metrics.push(("normalized_entropy", false, distribution.normalized_entropy));
+    }
+    let significant: usize = response.pattern_analysis.values().map(count_significant).sum();
+    metrics.push(("significant_findings", true, significant as f64));
+    metrics
+}
+
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    sorted[((sorted.len() - 1) as f64 * q).round() as usize]
+}
+
+/// Runs the requested analyzer on many uniformly random histories shaped like the real one and
+/// reports how often its pattern measures are at least as strong as on the real draws.
+pub(super) fn run_noise_simulation(req: &NoiseSimulationRequest) -> Result<NoiseSimulationReport> {
+    // Each random history runs a full analysis; the ML methods and the consensus, which trains them,
+    // take seconds per run and would tie up the server for hours.
+    if matches!(req.method, AnalysisMethod::MlDecisionTree | AnalysisMethod::MlRandomForest | AnalysisMethod::MlGradientBoosting | AnalysisMethod::Consensus) {
+        return Err(anyhow!("การจำลองข้อมูลสุ่มใช้กับโมเดลแมชชีนเลิร์นนิงและการจัดอันดับฉันทามติไม่ได้ เพราะใช้เวลาประมวลผลนานเกินไป"));
+    }
+    let digits = req.numbers.first().map_or(0, |n| n.len());
+    if digits == 0 || !req.numbers.iter().all(|n| n.len() == digits && n.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("ตัวเลขทั้งหมดต้องมีความยาวเท่ากันและประกอบด้วยตัวเลขเท่านั้นสำหรับการจำลอง"));
+    }
+    let simulations = req.simulations.unwrap_or(DEFAULT_NOISE_SIMULATIONS).clamp(1, MAX_NOISE_SIMULATIONS);
+    let seed = req.seed.unwrap_or(DEFAULT_SEED);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let observed: HashMap<&str, f64> = match dispatch(req.method, &req.numbers, None, &req.params, &[]) {
+        Ok(response) => pattern_metrics(&response).into_iter().map(|(name, _, v)| (name, v)).collect(),
+        Err(_) => HashMap::new(),
+    };
+
+    let mut noise: HashMap<&str, (bool, Vec<f64>)> = HashMap::new();
+    let mut failed_simulations = 0;
+    for _ in 0..simulations {
+        let history: Vec<String> = (0..req.numbers.len()).map(|_| random_number(&mut rng, digits)).collect();
+        match dispatch(req.method, &history, None, &req.params, &[]) {
+            Ok(response) => {
+                for (name, larger_is_pattern, value) in pattern_metrics(&response) {
+                    noise.entry(name).or_insert((larger_is_pattern, Vec::new())).1.push(value);
+                }
+            }
+            Err(_) => failed_simulations += 1,
+        }
+    }
+    if noise.is_empty() {
+        return Err(anyhow!("วิธีการวิเคราะห์นี้ล้มเหลวกับข้อมูลสุ่มทุกชุด"));
+    }
+
+    let mut metrics: Vec<NoiseMetric> = noise.into_iter()
+        .map(|(name, (larger_is_pattern, mut values))| {
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let observed = observed.get(name).copied();
+            let share_as_extreme = observed.map(|o| {
+                let as_extreme = values.iter().filter(|&&v| if larger_is_pattern { v >= o } else { v <= o }).count();
+                as_extreme as f64 / values.len() as f64
+            });
+            NoiseMetric {
+                name: name.to_string(),
+                observed,
+                noise_mean: values.iter().sum::<f64>() / values.len() as f64,
+                noise_p05: percentile(&values, 0.05),
+                noise_p95: percentile(&values, 0.95),
+                share_as_extreme,
+            }
+        })
+        .collect();
+    metrics.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(NoiseSimulationReport {
+        method: req.method,
+        simulations,
+        failed_simulations,
+        draws_per_history: req.numbers.len(),
+        digits,
+        seed,
+        metrics,
+    })
+}
+
+/// Draws one Thai result and returns the winnings of every tier for the given tickets.
+fn simulate_thai_draw(rng: &mut StdRng, tickets: &[u32], tier_hits: &mut [usize]) -> f64 {
+    let first_prize = rng.gen_range(0..1_000_000u32);
+    let mut winnings = 0.0;
+    for (tier_idx, tier) in THAI_PRIZES.iter().enumerate() {
+        let prizes: Vec<u32> = match tier.matches {
+            PrizeMatch::AdjacentToFirst => vec![first_prize],
+            PrizeMatch::Whole if tier_idx == 0 => vec![first_prize],
+            PrizeMatch::Whole => (0..tier.count).map(|_| rng.gen_range(0..1_000_000)).collect(),
+            PrizeMatch::First(n) | PrizeMatch::Last(n) => (0..tier.count).map(|_| rng.gen_range(0..10u32.pow(n as u32))).collect(),
+        };
+        for &ticket in tickets {
+            let hits = prizes.iter().filter(|&&prize| tier.matches.wins(ticket, prize)).count();
+            tier_hits[tier_idx] += hits;
+            winnings += hits as f64 * tier.amount;
+        }
+    }
+    winnings
+}
+
+/// Plays one draw, adds the prizes won to the per-tier hit counts and returns the winnings.
+type DrawSimulator = Box<dyn FnMut(&mut StdRng, &mut [usize]) -> f64>;
+
+/// Winnings divided by cost under a uniformly random draw, computed from the payout table.
+fn exact_thai_return() -> f64 {
+    THAI_PRIZES.iter()
+        .map(|tier| {
+            let chance = match tier.matches {
+                PrizeMatch::Whole | PrizeMatch::AdjacentToFirst => 1e-6,
+                PrizeMatch::First(n) | PrizeMatch::Last(n) => 1.0 / 10f64.powi(n as i32),
+            };
+            tier.count as f64 * chance * tier.amount
+        })
+        .sum::<f64>() / THAI_TICKET_PRICE
+}
+
+/// Simulates buying the same tickets on every draw for many runs and summarises the net return
+/// under the Thai prize table or the Laos payout multipliers.
+pub(super) fn run_strategy_simulation(req: &StrategySimulationRequest) -> Result<StrategySimulationReport> {
+    if req.tickets.is_empty() {
+        return Err(anyhow!("ต้องระบุสลากอย่างน้อย 1 ใบ"));
+    }
+    if !req.tickets.iter().all(|t| !t.is_empty() && t.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("หมายเลขสลากต้องประกอบด้วยตัวเลขเท่านั้น"));
+    }
+    if req.tickets.len() > MAX_STRATEGY_TICKETS {
+        return Err(anyhow!("จำลองได้ครั้งละไม่เกิน {} ใบ", MAX_STRATEGY_TICKETS));
+    }
+    let draws = req.draws.unwrap_or(DEFAULT_STRATEGY_DRAWS).clamp(1, MAX_STRATEGY_DRAWS);
+    let simulations = req.simulations.unwrap_or(DEFAULT_STRATEGY_SIMULATIONS).clamp(1, MAX_STRATEGY_SIMULATIONS.min(MAX_STRATEGY_PLAYED_DRAWS / draws));
+    let seed = req.seed.unwrap_or(DEFAULT_SEED);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // The cost of one draw's tickets, the (tier name, amount) pairs, the exact return and the per-draw simulation.
+    let (cost_per_draw, tier_info, exact_return_to_player, mut play_draw): (f64, Vec<(String, f64)>, f64, DrawSimulator) = match req.lotto_type {
+        LottoType::Thai => {
+            if req.tickets.iter().any(|t| t.len() != 6) {
+                return Err(anyhow!("สลากไทยต้องเป็นตัวเลข 6 หลัก"));
+            }
+            let tickets: Vec<u32> = req.tickets.iter().map(|t| t.parse().unwrap_or(0)).collect();
+            (
+                THAI_TICKET_PRICE * tickets.len() as f64,
+                THAI_PRIZES.iter().map(|t| (t.name.to_string(), t.amount)).collect(),
+                exact_thai_return(),
+                Box::new(move |rng, tier_hits| simulate_thai_draw(rng, &tickets, tier_hits)),
+            )
+        }
+        LottoType::Laos => {
+            let stake = req.stake.unwrap_or(payouts::DEFAULT_LAOS_STAKE);
+            if stake <= 0.0 {
+                return Err(anyhow!("เงินเดิมพันต่อใบต้องมากกว่า 0"));
+            }
+            let bets: Vec<(usize, u32, f64)> = req.tickets.iter()
+                .map(|t| payouts::laos_multiplier(t.len())
+                    .map(|m| (t.len(), t.parse().unwrap_or(0), m))
+                    .ok_or_else(|| anyhow!("สลากลาวต้องเป็นเลขท้าย 2 หรือ 3 หลัก: '{}'", t)))
+                .collect::<Result<_>>()?;
+            let cost_per_draw = stake * bets.len() as f64;
+            let tier_info = payouts::LAOS_PAYOUT_MULTIPLIERS.iter()
+                .map(|(digits, m)| (format!("เลขท้าย {} ตัว", digits), stake * m))
+                .collect();
+            let exact_return = bets.iter().map(|(digits, _, m)| m / 10f64.powi(*digits as i32)).sum::<f64>() / bets.len() as f64;
+            let play_draw: DrawSimulator = Box::new(move |rng, tier_hits| {
+                let drawn = rng.gen_range(0..1_000u32);
+                let mut winnings = 0.0;
+                for &(digits, ending, multiplier) in &bets {
+                    if drawn % 10u32.pow(digits as u32) == ending {
+                        let tier_idx = payouts::LAOS_PAYOUT_MULTIPLIERS.iter().position(|(d, _)| *d == digits).unwrap_or(0);
+                        tier_hits[tier_idx] += 1;
+                        winnings += stake * multiplier;
+                    }
+                }
+                winnings
+            });
+            (cost_per_draw, tier_info, exact_return, play_draw)
+        }
+    };
+
+    let cost_per_run = cost_per_draw * draws as f64;
+    let mut tier_hits = vec![0usize; tier_info.len()];
+    let mut net_returns = Vec::with_capacity(simulations);
+    let (mut total_winnings, mut runs_with_win, mut runs_with_profit) = (0.0, 0usize, 0usize);
+    for _ in 0..simulations {
+        let winnings: f64 = (0..draws).map(|_| play_draw(&mut rng, &mut tier_hits)).sum();
+        total_winnings += winnings;
+        runs_with_win += usize::from(winnings > 0.0);
+        runs_with_profit += usize::from(winnings > cost_per_run);
+        net_returns.push(winnings - cost_per_run);
+    }
+
+    let n = simulations as f64;
+    let mean_net_return = net_returns.iter().sum::<f64>() / n;
+    let variance_net_return = match simulations {
+        1 => 0.0,
+        _ => net_returns.iter().map(|r| (r - mean_net_return).powi(2)).sum::<f64>() / (n - 1.0),
+    };
+    let total_draws = n * draws as f64;
+
+    Ok(StrategySimulationReport {
+        lotto_type: req.lotto_type.clone(),
+        tickets: req.tickets.len(),
+        draws_per_run: draws,
+        simulations,
+        seed,
+        cost_per_run,
+        mean_winnings: total_winnings / n,
+        mean_net_return,
+        variance_net_return,
+        std_dev_net_return: variance_net_return.sqrt(),
+        return_to_player: total_winnings / (cost_per_run * n),
+        exact_return_to_player,
+        probability_any_win: runs_with_win as f64 / n,
+        probability_profit: runs_with_profit as f64 / n,
+        tiers: tier_info.into_iter().zip(tier_hits)
+            .map(|((tier, amount), hits)| PrizeTierHits { tier, amount, hits_per_draw: hits as f64 / total_draws })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strategy(draws: usize, simulations: usize) -> StrategySimulationRequest {
+        StrategySimulationRequest {
+            lotto_type: LottoType::Laos,
+            tickets: vec!["12".to_string()],
+            stake: None,
+            draws: Some(draws),
+            simulations: Some(simulations),
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn strategy_work_is_bounded() {
+        let report = run_strategy_simulation(&strategy(1_000_000, MAX_STRATEGY_SIMULATIONS)).unwrap();
+        assert_eq!(report.draws_per_run, MAX_STRATEGY_DRAWS);
+        assert_eq!(report.simulations, MAX_STRATEGY_PLAYED_DRAWS / MAX_STRATEGY_DRAWS);
+
+        let report = run_strategy_simulation(&strategy(1, 1_000_000)).unwrap();
+        assert_eq!(report.simulations, MAX_STRATEGY_SIMULATIONS);
+    }
+
+    #[test]
+    fn exact_laos_return_follows_the_multiplier() {
+        let report = run_strategy_simulation(&strategy(24, 10)).unwrap();
+        assert!((report.exact_return_to_player - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn noise_simulation_rejects_the_slow_methods() {
+        let req = NoiseSimulationRequest {
+            numbers: vec!["12".to_string(); 50],
+            method: AnalysisMethod::MlGradientBoosting,
+            params: Default::default(),
+            simulations: Some(1),
+            seed: None,
+        };
+        assert!(run_noise_simulation(&req).is_err());
+    }
+}
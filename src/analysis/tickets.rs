@@ -0,0 +1,214 @@
+use super::parse_draw_date;
+use crate::models::{LottoResult, LottoType, TicketCheckReport, TicketCheckRequest, TicketPrize, TicketResult, TierNumbers};
+use crate::payouts::{self, PrizeMatch, PrizeTier, THAI_PRIZES};
+use anyhow::{Result, anyhow};
+
+/// Tickets checked in one request; enough for a whole book of tickets pasted at once.
+const MAX_TICKETS: usize = 1_000;
+
+/// The winning numbers of every checked tier, the tiers that could not be checked and the result of every ticket.
+type CheckedTickets = (Vec<TierNumbers>, Vec<String>, Vec<TicketResult>);
+
+/// The stored draw on `draw_date`, or the latest stored draw when no date is given.
+fn find_draw<'a>(lotto_type: &LottoType, results: &'a [LottoResult], draw_date: Option<&str>) -> Result<&'a LottoResult> {
+    let mut dated = results.iter().filter_map(|r| parse_draw_date(&r.draw_date).map(|date| (date, r)));
+    match draw_date {
+        Some(requested) => {
+            let date = parse_draw_date(requested).ok_or_else(|| anyhow!("รูปแบบวันที่ไม่ถูกต้อง: '{}'", requested))?;
+            dated.find(|(d, _)| *d == date)
+                .map(|(_, r)| r)
+                .ok_or_else(|| anyhow!("ไม่พบผลสลาก{}งวดวันที่ {} ในประวัติที่บันทึกไว้", lotto_type, date))
+        }
+        // The archive lists the newest draw first, which is the fallback when no date can be read.
+        None => dated.max_by_key(|(d, _)| *d).map(|(_, r)| r).or(results.first())
+            .ok_or_else(|| anyhow!("ยังไม่มีประวัติสลาก{}บนเซิร์ฟเวอร์ กรุณาดึงข้อมูลก่อน", lotto_type)),
+    }
+}
+
+/// The stored prize of `draw` with `digits` digits, reduced to its digits.
+fn stored_prize(lotto_type: &LottoType, draw: &LottoResult, digits: usize) -> Option<String> {
+    let prize = match lotto_type.prize_digits().iter().position(|&d| d == digits)? {
+        0 => &draw.prize1,
+        _ => &draw.prize2,
+    };
+    let prize: String = prize.chars().filter(|c| c.is_ascii_digit()).collect();
+    (prize.len() == digits).then_some(prize)
+}
+
+/// The stored numbers a Thai tier is checked against, as passed to `PrizeMatch::wins`. The first prize,
+/// its neighbours and the last two digits come from the two main prizes; the other tiers are only
+/// known when the scraper found them, and numbers of the wrong length are left out.
+fn stored_thai_prizes(tier_idx: usize, tier: &PrizeTier, draw: &LottoResult) -> Option<Vec<String>> {
+    let numbers = match tier.matches {
+        PrizeMatch::Whole if tier_idx == 0 => vec![stored_prize(&LottoType::Thai, draw, 6)?],
+        PrizeMatch::AdjacentToFirst => vec![stored_prize(&LottoType::Thai, draw, 6)?],
+        PrizeMatch::Last(2) => vec![stored_prize(&LottoType::Thai, draw, 2)?],
+        _ => draw.other_prizes.get(tier.name)?.iter()
+            .filter(|n| n.len() == tier.matches.digits() && n.chars().all(|c| c.is_ascii_digit()))
+            .cloned()
+            .collect(),
+    };
+    (!numbers.is_empty()).then_some(numbers)
+}
+
+/// Checks every ticket against each tier of the official Thai prize table that the stored draw holds.
+fn check_thai(draw: &LottoResult, tickets: &[String]) -> Result<CheckedTickets> {
+    if let Some(t) = tickets.iter().find(|t| t.len() != 6) {
+        return Err(anyhow!("สลากไทยต้องเป็นตัวเลข 6 หลัก: '{}'", t));
+    }
+    let mut winning_numbers = Vec::new();
+    let mut unchecked_tiers = Vec::new();
+    let mut checked = Vec::new();
+    for (tier_idx, tier) in THAI_PRIZES.iter().enumerate() {
+        let Some(prizes) = stored_thai_prizes(tier_idx, tier, draw) else {
+            unchecked_tiers.push(tier.name.to_string());
+            continue;
+        };
+        let values: Vec<(u32, String)> = prizes.into_iter().map(|p| (p.parse().unwrap_or(0), p)).collect();
+        let shown = match tier.matches {
+            PrizeMatch::AdjacentToFirst => vec![format!("{:06}", (values[0].0 + 999_999) % 1_000_000), format!("{:06}", (values[0].0 + 1) % 1_000_000)],
+            _ => values.iter().map(|(_, p)| p.clone()).collect(),
+        };
+        winning_numbers.push(TierNumbers { tier: tier.name.to_string(), numbers: shown });
+        checked.push((tier, values));
+    }
+
+    let results = tickets.iter()
+        .map(|ticket| {
+            let number: u32 = ticket.parse().unwrap_or(0);
+            let prizes: Vec<TicketPrize> = checked.iter()
+                .flat_map(|(tier, values)| values.iter()
+                    .filter(move |(value, _)| tier.matches.wins(number, *value))
+                    .map(move |(_, prize)| TicketPrize {
+                        tier: tier.name.to_string(),
+                        // The adjacent tier is won by a neighbour of the first prize, which is the ticket itself.
+                        winning_number: if matches!(tier.matches, PrizeMatch::AdjacentToFirst) { ticket.clone() } else { prize.clone() },
+                        amount: tier.amount,
+                    }))
+                .collect();
+            TicketResult { ticket: ticket.clone(), total: prizes.iter().fold(0.0, |sum, p| sum + p.amount), prizes }
+        })
+        .collect();
+    Ok((winning_numbers, unchecked_tiers, results))
+}
+
+/// Checks every Laos ending against the stored prize of the same length, paying the stake times its multiplier.
+fn check_laos(draw: &LottoResult, tickets: &[String], stake: f64) -> Result<CheckedTickets> {
+    if stake <= 0.0 {
+        return Err(anyhow!("เงินเดิมพันต่อใบต้องมากกว่า 0"));
+    }
+    if let Some(t) = tickets.iter().find(|t| payouts::laos_multiplier(t.len()).is_none()) {
+        return Err(anyhow!("สลากลาวต้องเป็นเลขท้าย 2 หรือ 3 หลัก: '{}'", t));
+    }
+    let mut winning_numbers = Vec::new();
+    let mut unchecked_tiers = Vec::new();
+    let mut checked = Vec::new();
+    for &(digits, multiplier) in &payouts::LAOS_PAYOUT_MULTIPLIERS {
+        let name = format!("เลขท้าย {} ตัว", digits);
+        match stored_prize(&LottoType::Laos, draw, digits) {
+            Some(prize) => {
+                winning_numbers.push(TierNumbers { tier: name.clone(), numbers: vec![prize.clone()] });
+                checked.push((name, prize, stake * multiplier));
+            }
+            None => unchecked_tiers.push(name),
+        }
+    }
+
+    let results = tickets.iter()
+        .map(|ticket| {
+            let prizes: Vec<TicketPrize> = checked.iter()
+                .filter(|(_, prize, _)| prize == ticket)
+                .map(|(name, prize, amount)| TicketPrize { tier: name.clone(), winning_number: prize.clone(), amount: *amount })
+                .collect();
+            TicketResult { ticket: ticket.clone(), total: prizes.iter().fold(0.0, |sum, p| sum + p.amount), prizes }
+        })
+        .collect();
+    Ok((winning_numbers, unchecked_tiers, results))
+}
+
+/// Checks tickets against one stored draw and returns every tier each ticket won with its official amount.
+pub(super) fn check(req: &TicketCheckRequest, results: &[LottoResult]) -> Result<TicketCheckReport> {
+    let tickets: Vec<String> = req.tickets.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+    if tickets.is_empty() {
+        return Err(anyhow!("ต้องระบุสลากอย่างน้อย 1 ใบ"));
+    }
+    if tickets.len() > MAX_TICKETS {
+        return Err(anyhow!("ตรวจสลากได้ครั้งละไม่เกิน {} ใบ", MAX_TICKETS));
+    }
+    if let Some(t) = tickets.iter().find(|t| !t.chars().all(|c| c.is_ascii_digit())) {
+        return Err(anyhow!("หมายเลขสลากต้องประกอบด้วยตัวเลขเท่านั้น: '{}'", t));
+    }
+
+    let draw = find_draw(&req.lotto_type, results, req.draw_date.as_deref())?;
+    let (winning_numbers, unchecked_tiers, tickets) = match req.lotto_type {
+        LottoType::Thai => check_thai(draw, &tickets)?,
+        LottoType::Laos => check_laos(draw, &tickets, req.stake.unwrap_or(payouts::DEFAULT_LAOS_STAKE))?,
+    };
+    if winning_numbers.is_empty() {
+        return Err(anyhow!("ผลรางวัลงวดวันที่ {} ที่บันทึกไว้ไม่สมบูรณ์ จึงตรวจสลากไม่ได้", draw.draw_date));
+    }
+
+    Ok(TicketCheckReport {
+        lotto_type: req.lotto_type.clone(),
+        draw_date: parse_draw_date(&draw.draw_date).map_or_else(|| draw.draw_date.clone(), |d| d.to_string()),
+        winning_numbers,
+        unchecked_tiers,
+        winning_tickets: tickets.iter().filter(|t| !t.prizes.is_empty()).count(),
+        total_winnings: tickets.iter().fold(0.0, |sum, t| sum + t.total),
+        tickets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn draw() -> LottoResult {
+        LottoResult {
+            draw_date: "2025-05-16".to_string(),
+            prize1: "807952".to_string(),
+            prize2: "52".to_string(),
+            other_prizes: BTreeMap::from([
+                ("รางวัลที่ 2".to_string(), vec!["111111".to_string(), "222222".to_string()]),
+                ("รางวัลเลขหน้า 3 ตัว".to_string(), vec!["123".to_string(), "456".to_string()]),
+                ("รางวัลเลขท้าย 3 ตัว".to_string(), vec!["789".to_string(), "052".to_string()]),
+            ]),
+        }
+    }
+
+    fn won(results: &[TicketResult], ticket: &str) -> Vec<String> {
+        results.iter().find(|r| r.ticket == ticket).unwrap().prizes.iter().map(|p| p.tier.clone()).collect()
+    }
+
+    #[test]
+    fn checks_every_stored_tier() {
+        let tickets: Vec<String> = ["807952", "807953", "111111", "123000", "000789", "999999"].map(String::from).to_vec();
+        let (winning_numbers, unchecked, results) = check_thai(&draw(), &tickets).unwrap();
+
+        assert_eq!(won(&results, "807952"), ["รางวัลที่ 1", "รางวัลเลขท้าย 2 ตัว"]);
+        assert_eq!(won(&results, "807953"), ["รางวัลข้างเคียงรางวัลที่ 1"]);
+        assert_eq!(won(&results, "111111"), ["รางวัลที่ 2"]);
+        assert_eq!(won(&results, "123000"), ["รางวัลเลขหน้า 3 ตัว"]);
+        assert_eq!(won(&results, "000789"), ["รางวัลเลขท้าย 3 ตัว"]);
+        assert!(won(&results, "999999").is_empty());
+        assert_eq!(results[0].total, 6_002_000.0);
+        assert_eq!(results[3].total, 4_000.0);
+
+        assert_eq!(unchecked, ["รางวัลที่ 3", "รางวัลที่ 4", "รางวัลที่ 5"]);
+        let adjacent = winning_numbers.iter().find(|w| w.tier == "รางวัลข้างเคียงรางวัลที่ 1").unwrap();
+        assert_eq!(adjacent.numbers, ["807951", "807953"]);
+    }
+
+    #[test]
+    fn back_three_and_last_two_are_paid_together() {
+        let (_, _, results) = check_thai(&draw(), &["100052".to_string()]).unwrap();
+        assert_eq!(won(&results, "100052"), ["รางวัลเลขท้าย 3 ตัว", "รางวัลเลขท้าย 2 ตัว"]);
+        assert_eq!(results[0].total, 6_000.0);
+    }
+
+    #[test]
+    fn rejects_tickets_of_the_wrong_length() {
+        assert!(check_thai(&draw(), &["12345".to_string()]).is_err());
+    }
+}
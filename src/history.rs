@@ -0,0 +1,36 @@
+use crate::models::{LottoResult, LottoType};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Scraped draws are saved here so the history survives restarts and can be analyzed server-side.
+const HISTORY_PATH: &str = "data/history.json";
+
+/// Held while a snapshot is taken and written, so a later save never loses to an older snapshot.
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// The latest scraped draws of each lottery type, in the order the scraper returned them (newest first).
+pub type StoredHistory = HashMap<LottoType, Vec<LottoResult>>;
+
+/// Reads the stored history, or starts empty when nothing has been saved yet.
+pub fn load() -> StoredHistory {
+    std::fs::read_to_string(HISTORY_PATH).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the stored history to disk.
+pub fn save(history: &StoredHistory) -> std::io::Result<()> {
+    if let Some(dir) = std::path::Path::new(HISTORY_PATH).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(HISTORY_PATH, serde_json::to_string(history)?)
+}
+
+/// Writes a copy of the shared history to disk. The copy is taken under the lock and written after
+/// releasing it, so analyses reading the history do not wait for the disk. Blocks; call it from
+/// `web::block`.
+pub fn save_snapshot(history: &Mutex<StoredHistory>) -> std::io::Result<()> {
+    let _writing = SAVE_LOCK.lock().unwrap();
+    let snapshot = history.lock().unwrap().clone();
+    save(&snapshot)
+}
@@ -0,0 +1,32 @@
+use crate::models::LedgerEntry;
+use std::sync::Mutex;
+
+/// Saved predictions are kept here so they can be scored after later scrapes, across restarts.
+const LEDGER_PATH: &str = "data/ledger.json";
+
+/// Held while a snapshot is taken and written, so a later save never loses to an older snapshot.
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Reads the saved predictions, or starts empty when nothing has been saved yet.
+pub fn load() -> Vec<LedgerEntry> {
+    std::fs::read_to_string(LEDGER_PATH).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Writes the saved predictions to disk.
+pub fn save(entries: &[LedgerEntry]) -> std::io::Result<()> {
+    if let Some(dir) = std::path::Path::new(LEDGER_PATH).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(LEDGER_PATH, serde_json::to_string(entries)?)
+}
+
+/// Writes a copy of the shared predictions to disk. The copy is taken under the lock and written
+/// after releasing it, so requests reading the ledger do not wait for the disk. Blocks; call it from
+/// `web::block`.
+pub fn save_snapshot(entries: &Mutex<Vec<LedgerEntry>>) -> std::io::Result<()> {
+    let _writing = SAVE_LOCK.lock().unwrap();
+    let snapshot = entries.lock().unwrap().clone();
+    save(&snapshot)
+}
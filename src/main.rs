@@ -1,100 +1,266 @@
-use actix_files::Files;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder, error};
-
-// --- Project Modules ---
-mod analysis;
-mod models;
-mod scraper;
-
-// --- Imports from Modules ---
-use models::{AnalyzeRequest, AppState, StartScrapeRequest};
-
-// --- Performance Optimization ---
-// Set mimalloc as the global memory allocator. This can improve performance
-// by replacing the system's default allocator with a faster one.
-#[global_allocator]
-static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
-
-// --- API Handlers ---
-
-/// Serves the main index.html file.
-async fn index() -> impl Responder {
-    match std::fs::read_to_string("templates/index.html") {
-        Ok(content) => HttpResponse::Ok().content_type("text/html; charset=utf-8").body(content),
-        Err(_) => HttpResponse::InternalServerError().body("ไม่สามารถอ่านไฟล์ index.html ได้"),
-    }
-}
-
-/// Starts the scraping process in a non-blocking background thread.
-async fn start_scrape(
-    req: web::Json<StartScrapeRequest>,
-    app_state: web::Data<AppState>,
-) -> impl Responder {
-    let mut status = app_state.task_status.lock().unwrap();
-    if status.is_running {
-        return HttpResponse::Conflict().json(serde_json::json!({
-            "error": "มีโปรแกรมดึงข้อมูลทำงานอยู่แล้ว"
-        }));
-    }
-
-    status.is_running = true;
-    status.lotto_type = Some(req.lotto_type.to_string());
-    status.progress = vec![format!("🚀 กำลังเริ่มโปรแกรมดึงข้อมูลสำหรับสลาก {}...", req.lotto_type)];
-    status.results.clear();
-
-    let lotto_type = req.lotto_type.clone();
-    let app_state_clone = app_state.clone();
-
-    tokio::spawn(async move {
-        scraper::run_scraper(lotto_type, app_state_clone).await;
-    });
-
-    HttpResponse::Accepted().json(serde_json::json!({
-        "message": format!("เริ่มกระบวนการดึงข้อมูลสำหรับสลาก {} แล้ว!", req.lotto_type)
-    }))
-}
-
-/// Returns the current status of the scraping task.
-async fn get_status(app_state: web::Data<AppState>) -> impl Responder {
-    let status = app_state.task_status.lock().unwrap();
-    HttpResponse::Ok().json(&*status)
-}
-
-/// Handles analysis requests by delegating to the analysis module.
-/// OPTIMIZATION: The analysis logic is wrapped in `web::block` to run it in a
-/// separate thread pool. This prevents the CPU-intensive analysis from blocking
-//  the main Actix-web server threads, ensuring the server remains responsive.
-async fn analyze_handler(req: web::Json<AnalyzeRequest>) -> Result<HttpResponse, error::Error> {
-    let result = web::block(move || analysis::run_analysis(&req))
-        .await
-        .map_err(error::ErrorInternalServerError)?; // Handle thread pool errors
-
-    match result {
-        Ok(response) => Ok(HttpResponse::Ok().json(response)),
-        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))),
-    }
-}
-
-// --- Server Setup ---
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    let port_str = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let port = port_str.parse::<u16>().expect("PORT must be a valid number");
-    let app_state = web::Data::new(AppState::new());
-
-    println!("✅ เซิร์ฟเวอร์กำลังเริ่มทำงานที่ http://0.0.0.0:{}", port);
-    println!("⚡️ ตัวจัดสรรหน่วยความจำ: mimalloc");
-
-    HttpServer::new(move || {
-        App::new()
-            .app_data(app_state.clone())
-            .route("/", web::get().to(index))
-            .route("/start-scrape", web::post().to(start_scrape))
-            .route("/status", web::get().to(get_status))
-            .route("/analyze", web::post().to(analyze_handler))
-            .service(Files::new("/static", "static"))
-    })
-    .bind(("0.0.0.0", port))?
-    .run()
-    .await
+use actix_files::Files;
+use actix_web::{web, App, HttpResponse, HttpServer, Responder, error};
+
+// --- Project Modules ---
+mod analysis;
+mod history;
+mod ledger;
+mod models;
+mod payouts;
+mod scraper;
+
+// --- Imports from Modules ---
+use models::{
+    AnalyzeRequest, AppState, DataQualityQuery, DreamLookupQuery, ExportFormat, LottoType, FeatureExportRequest, LedgerEntry, NoiseSimulationRequest,
+    StartScrapeRequest, StrategySimulationRequest, TicketCheckRequest,
+};
+
+// --- Performance Optimization ---
+// Set mimalloc as the global memory allocator. This can improve performance
+// by replacing the system's default allocator with a faster one.
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+// --- API Handlers ---
+
+/// Serves the main index.html file.
+async fn index() -> impl Responder {
+    match std::fs::read_to_string("templates/index.html") {
+        Ok(content) => HttpResponse::Ok().content_type("text/html; charset=utf-8").body(content),
+        Err(_) => HttpResponse::InternalServerError().body("ไม่สามารถอ่านไฟล์ index.html ได้"),
+    }
+}
+
+/// Starts the scraping process in a non-blocking background thread.
+async fn start_scrape(
+    req: web::Json<StartScrapeRequest>,
+    app_state: web::Data<AppState>,
+) -> impl Responder {
+    let mut status = app_state.task_status.lock().unwrap();
+    if status.is_running {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "มีโปรแกรมดึงข้อมูลทำงานอยู่แล้ว"
+        }));
+    }
+
+    status.is_running = true;
+    status.lotto_type = Some(req.lotto_type.to_string());
+    status.progress = vec![format!("🚀 กำลังเริ่มโปรแกรมดึงข้อมูลสำหรับสลาก {}...", req.lotto_type)];
+    status.results.clear();
+    status.quality = None;
+
+    let lotto_type = req.lotto_type.clone();
+    let app_state_clone = app_state.clone();
+
+    tokio::spawn(async move {
+        scraper::run_scraper(lotto_type, app_state_clone).await;
+    });
+
+    HttpResponse::Accepted().json(serde_json::json!({
+        "message": format!("เริ่มกระบวนการดึงข้อมูลสำหรับสลาก {} แล้ว!", req.lotto_type)
+    }))
+}
+
+/// Returns the current status of the scraping task.
+async fn get_status(app_state: web::Data<AppState>) -> impl Responder {
+    let status = app_state.task_status.lock().unwrap();
+    HttpResponse::Ok().json(&*status)
+}
+
+/// Handles analysis requests by delegating to the analysis module.
+/// OPTIMIZATION: The analysis logic is wrapped in `web::block` to run it in a
+/// separate thread pool. This prevents the CPU-intensive analysis from blocking
+//  the main Actix-web server threads, ensuring the server remains responsive.
+async fn analyze_handler(req: web::Json<AnalyzeRequest>, app_state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let mut req = req.into_inner();
+    if let Err(e) = analysis::resolve_history_reference(&mut req, &app_state.history.lock().unwrap()) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })));
+    }
+    let (req, result) = web::block(move || {
+            let result = analysis::run_analysis(&req);
+            (req, result)
+        })
+        .await
+        .map_err(error::ErrorInternalServerError)?; // Handle thread pool errors
+
+    match result {
+        Ok(response) => {
+            if let Some(entry) = analysis::record_prediction(&req, &response) {
+                record_prediction(app_state, entry).await;
+            }
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Saves the prediction to the ledger so a later scrape can score it. The file is written on the
+/// blocking thread pool. A failed save is only logged, since the analysis itself succeeded.
+async fn record_prediction(app_state: web::Data<AppState>, mut entry: LedgerEntry) {
+    {
+        let mut entries = app_state.ledger.lock().unwrap();
+        entry.id = entries.iter().map(|e| e.id).max().map_or(1, |max| max + 1);
+        entries.push(entry);
+    }
+    let saved = web::block(move || ledger::save_snapshot(&app_state.ledger)).await;
+    if let Err(e) = saved.map_err(anyhow::Error::from).and_then(|r| r.map_err(anyhow::Error::from)) {
+        eprintln!("⚠️ ไม่สามารถบันทึกคำทำนายลงดิสก์: {}", e);
+    }
+}
+
+/// Returns the cross-validated evaluation report of an ML method on its own.
+async fn evaluate_handler(req: web::Json<AnalyzeRequest>, app_state: web::Data<AppState>) -> Result<HttpResponse, error::Error> {
+    let mut req = req.into_inner();
+    if let Err(e) = analysis::resolve_history_reference(&mut req, &app_state.history.lock().unwrap()) {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })));
+    }
+    let result = web::block(move || analysis::run_evaluation(&req))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    match result {
+        Ok(report) => Ok(HttpResponse::Ok().json(report)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Exports the ML feature matrix as CSV (default) or JSON so it can be loaded into external notebooks.
+async fn export_features_handler(req: web::Json<FeatureExportRequest>) -> Result<HttpResponse, error::Error> {
+    let result = web::block(move || analysis::export_features(&req).map(|matrix| (matrix, req.format)))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    match result {
+        Ok((matrix, ExportFormat::Csv)) => Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(("Content-Disposition", "attachment; filename=\"features.csv\""))
+            .body(matrix.to_csv())),
+        Ok((matrix, ExportFormat::Json)) => Ok(HttpResponse::Ok().json(matrix)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Runs an analyzer on many random draw histories and compares its pattern measures with the real draws.
+async fn simulate_noise_handler(req: web::Json<NoiseSimulationRequest>) -> Result<HttpResponse, error::Error> {
+    let result = web::block(move || analysis::run_noise_simulation(&req))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    match result {
+        Ok(report) => Ok(HttpResponse::Ok().json(report)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Simulates a ticket-buying strategy under the official payout tables.
+async fn simulate_strategy_handler(req: web::Json<StrategySimulationRequest>) -> Result<HttpResponse, error::Error> {
+    let result = web::block(move || analysis::run_strategy_simulation(&req))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    match result {
+        Ok(report) => Ok(HttpResponse::Ok().json(report)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Checks one or many tickets against a stored draw and returns every prize tier they won.
+async fn check_tickets_handler(req: web::Json<TicketCheckRequest>, app_state: web::Data<AppState>) -> impl Responder {
+    let history = app_state.history.lock().unwrap();
+    match analysis::check_tickets(&req, &history) {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Looks up dream-book keywords, or returns the whole dream book when no keyword is given.
+async fn dream_book_handler(query: web::Query<DreamLookupQuery>) -> impl Responder {
+    match analysis::lookup_dream_numbers(query.q.as_deref()) {
+        Ok(matches) => HttpResponse::Ok().json(matches),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Reports duplicates, gaps and malformed prizes in the stored history of one or every lottery type.
+async fn data_quality_handler(query: web::Query<DataQualityQuery>, app_state: web::Data<AppState>) -> impl Responder {
+    let history = app_state.history.lock().unwrap();
+    HttpResponse::Ok().json(analysis::history_quality_reports(&history, query.lotto_type.as_ref()))
+}
+
+/// Lists the saved predictions, newest first.
+async fn ledger_handler(app_state: web::Data<AppState>) -> impl Responder {
+    let entries = app_state.ledger.lock().unwrap();
+    HttpResponse::Ok().json(entries.iter().rev().collect::<Vec<_>>())
+}
+
+/// Ranks the methods by the real-world hit rate of their scored predictions.
+async fn leaderboard_handler(app_state: web::Data<AppState>) -> impl Responder {
+    let entries = app_state.ledger.lock().unwrap();
+    HttpResponse::Ok().json(analysis::prediction_leaderboard(&entries))
+}
+
+// --- Command Line ---
+
+/// `lotto_analysis_rust quality [thai|laos]` prints the data-quality report of the stored history as
+/// JSON and exits with status 1 when any issue is found, so it can be used in scheduled checks.
+fn run_quality_command(args: &[String]) -> std::io::Result<()> {
+    let lotto_type = args.first().map(|arg| {
+        serde_json::from_value::<LottoType>(serde_json::json!(arg)).unwrap_or_else(|_| {
+            eprintln!("ไม่รู้จักประเภทสลาก '{}' (ใช้ thai หรือ laos)", arg);
+            std::process::exit(2);
+        })
+    });
+    let reports = analysis::history_quality_reports(&history::load(), lotto_type.as_ref());
+    println!("{}", serde_json::to_string_pretty(&reports)?);
+    if reports.iter().any(|r| !r.issues.is_empty()) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// --- Server Setup ---
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("quality") {
+        return run_quality_command(&args[1..]);
+    }
+
+    let port_str = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
+    let port = port_str.parse::<u16>().expect("PORT must be a valid number");
+    let history = history::load();
+    let mut entries = ledger::load();
+    // Catch up on predictions whose draws were stored without being scored, e.g. by an interrupted run.
+    let scored: usize = history.iter().map(|(lotto_type, results)| analysis::score_predictions(&mut entries, lotto_type, results)).sum();
+    if scored > 0 {
+        if let Err(e) = ledger::save(&entries) {
+            eprintln!("⚠️ ไม่สามารถบันทึกผลคะแนนคำทำนายลงดิสก์: {}", e);
+        }
+    }
+    let app_state = web::Data::new(AppState::new(history, entries));
+
+    println!("✅ เซิร์ฟเวอร์กำลังเริ่มทำงานที่ http://0.0.0.0:{}", port);
+    println!("⚡️ ตัวจัดสรรหน่วยความจำ: mimalloc");
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(app_state.clone())
+            .route("/", web::get().to(index))
+            .route("/start-scrape", web::post().to(start_scrape))
+            .route("/status", web::get().to(get_status))
+            .route("/analyze", web::post().to(analyze_handler))
+            .route("/evaluate", web::post().to(evaluate_handler))
+            .route("/features", web::post().to(export_features_handler))
+            .route("/simulate/noise", web::post().to(simulate_noise_handler))
+            .route("/simulate/strategy", web::post().to(simulate_strategy_handler))
+            .route("/check-tickets", web::post().to(check_tickets_handler))
+            .route("/dream-book", web::get().to(dream_book_handler))
+            .route("/data-quality", web::get().to(data_quality_handler))
+            .route("/ledger", web::get().to(ledger_handler))
+            .route("/ledger/leaderboard", web::get().to(leaderboard_handler))
+            .service(Files::new("/static", "static"))
+    })
+    .bind(("0.0.0.0", port))?
+    .run()
+    .await
 }
\ No newline at end of file
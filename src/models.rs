@@ -1,107 +1,689 @@
-use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
-
-// --- Core Data Structures ---
-
-/// Represents a single lottery result, generic for any country.
-/// Prize1 and Prize2 are used generically to accommodate different lottery formats.
-/// For Thai: prize1 = First Prize, prize2 = Last 2 Digits.
-/// For Laos: prize1 = 3-Digit Prize, prize2 = 2-Digit Prize.
-#[derive(Serialize, Clone, Debug)]
-pub struct LottoResult {
-    #[serde(rename = "Draw Date")]
-    pub draw_date: String,
-    #[serde(rename = "Prize 1")]
-    pub prize1: String,
-    #[serde(rename = "Prize 2")]
-    pub prize2: String,
-}
-
-/// Holds the state of the current or last scraping task. This is shared across threads.
-#[derive(Serialize, Clone)]
-pub struct TaskStatus {
-    pub is_running: bool,
-    pub lotto_type: Option<String>,
-    pub progress: Vec<String>,
-    pub results: Vec<LottoResult>,
-}
-
-impl TaskStatus {
-    /// Creates a new, default task status.
-    fn new() -> Self {
-        TaskStatus {
-            is_running: false,
-            lotto_type: None,
-            progress: Vec::new(),
-            results: Vec::new(),
-        }
-    }
-}
-
-/// Shared application state accessible by all API handlers.
-/// The Mutex ensures safe concurrent access to the task status.
-pub struct AppState {
-    pub task_status: Mutex<TaskStatus>,
-}
-
-impl AppState {
-    /// Creates a new AppState.
-    pub fn new() -> Self {
-        AppState {
-            task_status: Mutex::new(TaskStatus::new()),
-        }
-    }
-}
-
-
-// --- API Request & Response Structs ---
-
-/// Type of lottery to scrape, chosen by the user in the frontend.
-#[derive(Deserialize, Serialize, Clone, Debug)]
-#[serde(rename_all = "lowercase")]
-pub enum LottoType {
-    Thai,
-    Laos,
-}
-
-/// Allows LottoType to be easily converted to a string for display.
-impl std::fmt::Display for LottoType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            LottoType::Thai => write!(f, "ไทย"),
-            LottoType::Laos => write!(f, "ลาว"),
-        }
-    }
-}
-
-/// Request from the frontend to start a new scraping task.
-#[derive(Deserialize)]
-pub struct StartScrapeRequest {
-    pub lotto_type: LottoType,
-}
-
-/// The analysis method chosen by the user.
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "snake_case")]
-pub enum AnalysisMethod {
-    ComprehensiveStatistics,
-    Numerology,
-    MlDecisionTree,
-    MarkovChain,
-}
-
-/// Request from the frontend to perform an analysis.
-#[derive(Deserialize)]
-pub struct AnalyzeRequest {
-    pub numbers: Vec<String>,
-    pub method: AnalysisMethod,
-}
-
-/// The structure of the JSON response for a successful analysis request.
-#[derive(Serialize)]
-pub struct AnalysisResponse {
-    pub statistical_summary: std::collections::HashMap<String, String>,
-    pub pattern_analysis: std::collections::HashMap<String, serde_json::Value>,
-    pub prediction_output: std::collections::HashMap<String, serde_json::Value>,
-    pub detailed_explanation: std::collections::HashMap<String, String>,
+use crate::history::StoredHistory;
+use crate::payouts::THAI_PRIZES;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+// --- Core Data Structures ---
+
+/// Represents a single lottery result, generic for any country.
+/// Prize1 and Prize2 are used generically to accommodate different lottery formats.
+/// For Thai: prize1 = First Prize, prize2 = Last 2 Digits.
+/// For Laos: prize1 = 3-Digit Prize, prize2 = 2-Digit Prize.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LottoResult {
+    #[serde(rename = "Draw Date")]
+    pub draw_date: String,
+    #[serde(rename = "Prize 1")]
+    pub prize1: String,
+    #[serde(rename = "Prize 2")]
+    pub prize2: String,
+    /// Winning numbers of the remaining Thai prize tiers, keyed by their name in the prize table
+    /// (e.g. "รางวัลเลขท้าย 3 ตัว"). Empty for Laos and for history scraped before they were kept.
+    #[serde(rename = "Other Prizes", default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub other_prizes: std::collections::BTreeMap<String, Vec<String>>,
+}
+
+/// Holds the state of the current or last scraping task. This is shared across threads.
+#[derive(Serialize, Clone)]
+pub struct TaskStatus {
+    pub is_running: bool,
+    pub lotto_type: Option<String>,
+    pub progress: Vec<String>,
+    pub results: Vec<LottoResult>,
+    /// Data-quality report of the results, filled in when the scrape completes.
+    pub quality: Option<DataQualityReport>,
+}
+
+impl TaskStatus {
+    /// Creates a new, default task status.
+    fn new() -> Self {
+        TaskStatus {
+            is_running: false,
+            lotto_type: None,
+            progress: Vec::new(),
+            results: Vec::new(),
+            quality: None,
+        }
+    }
+}
+
+/// Shared application state accessible by all API handlers.
+/// The Mutexes ensure safe concurrent access to the task status and the stored history.
+pub struct AppState {
+    pub task_status: Mutex<TaskStatus>,
+    /// The latest completed scrape of each lottery type, which `/analyze` can refer to.
+    pub history: Mutex<StoredHistory>,
+    /// Every prediction made by `/analyze`, saved so it can be scored when its draw is scraped.
+    pub ledger: Mutex<Vec<LedgerEntry>>,
+}
+
+impl AppState {
+    /// Creates a new AppState around the history and predictions saved by earlier runs.
+    pub fn new(history: StoredHistory, ledger: Vec<LedgerEntry>) -> Self {
+        AppState {
+            task_status: Mutex::new(TaskStatus::new()),
+            history: Mutex::new(history),
+            ledger: Mutex::new(ledger),
+        }
+    }
+}
+
+
+// --- API Request & Response Structs ---
+
+/// Type of lottery to scrape, chosen by the user in the frontend.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum LottoType {
+    Thai,
+    Laos,
+}
+
+impl LottoType {
+    /// Display names of the two prizes kept per draw, in the order of `prize1` and `prize2`. Thai names
+    /// come from the prize table, so they match the tier names used by `other_prizes` and the ticket checker.
+    pub fn prize_names(&self) -> [&'static str; 2] {
+        match self {
+            LottoType::Thai => [THAI_PRIZES[0].name, THAI_PRIZES[THAI_PRIZES.len() - 1].name],
+            LottoType::Laos => ["รางวัล 3 ตัว", "รางวัล 2 ตัว"],
+        }
+    }
+
+    /// How many digits each of the two prizes has.
+    pub fn prize_digits(&self) -> [usize; 2] {
+        match self {
+            LottoType::Thai => [6, 2],
+            LottoType::Laos => [3, 2],
+        }
+    }
+}
+
+/// Allows LottoType to be easily converted to a string for display.
+impl std::fmt::Display for LottoType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LottoType::Thai => write!(f, "ไทย"),
+            LottoType::Laos => write!(f, "ลาว"),
+        }
+    }
+}
+
+/// Request from the frontend to start a new scraping task.
+#[derive(Deserialize)]
+pub struct StartScrapeRequest {
+    pub lotto_type: LottoType,
+}
+
+/// The analysis method chosen by the user.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisMethod {
+    ComprehensiveStatistics,
+    Numerology,
+    MlDecisionTree,
+    MarkovChain,
+    RandomnessTests,
+    DigitHeatmap,
+    HotCold,
+    SerialDependence,
+    MlRandomForest,
+    MlGradientBoosting,
+    Consensus,
+    BayesianDirichlet,
+    Cooccurrence,
+    Seasonal,
+    DreamNumbers,
+    EntropyDiagnostics,
+}
+
+/// Optional tuning parameters for the analysis methods. Every field may be omitted,
+/// in which case the method falls back to its own default.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default)]
+pub struct AnalysisParams {
+    /// Number of most recent draws counted as the "recent" window by the hot/cold analysis.
+    pub recent_window: Option<usize>,
+    /// Largest lag used by the serial-dependence autocorrelation.
+    pub max_lag: Option<usize>,
+    /// Number of previous states the Markov chain conditions on.
+    pub markov_order: Option<usize>,
+    pub markov_state_space: Option<MarkovStateSpace>,
+    pub smoothing: Option<Smoothing>,
+    /// Pseudo-count for Dirichlet smoothing.
+    pub smoothing_alpha: Option<f64>,
+    /// Pseudo-count per category of the Bayesian method's symmetric Dirichlet prior.
+    pub prior_alpha: Option<f64>,
+    /// Trees in a random forest, or boosting rounds for gradient boosting.
+    pub n_trees: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub learning_rate: Option<f64>,
+    /// Share of features each random-forest tree is grown on.
+    pub feature_fraction: Option<f64>,
+    /// Share of the most recent samples held out for evaluation.
+    pub test_fraction: Option<f64>,
+    /// Seed for every random choice, so results are reproducible.
+    pub seed: Option<u64>,
+    /// Number of previous draws the ML feature builder includes as lagged features.
+    pub feature_lags: Option<usize>,
+    /// Also cross-validates the ML methods on /analyze, which refits the model once per fold; /evaluate always does.
+    pub cross_validate: Option<bool>,
+    pub cv_strategy: Option<CvStrategy>,
+    pub cv_folds: Option<usize>,
+    pub consensus_weighting: Option<ConsensusWeighting>,
+    /// Manual weight per method for the consensus vote; methods left out get no vote.
+    pub consensus_weights: Option<std::collections::HashMap<AnalysisMethod, f64>>,
+    /// Length of the ending the consensus votes on, e.g. 2 for the last two digits.
+    pub consensus_digits: Option<usize>,
+    /// Number of most recent draws replayed when weighting the consensus by backtest performance (at most 12).
+    pub backtest_draws: Option<usize>,
+    pub cooccurrence_unit: Option<CooccurrenceUnit>,
+    /// Dream or omen keywords (e.g. "งู") looked up in the dream book by the dream-number method.
+    pub dream_keywords: Option<Vec<String>>,
+    /// When set, the last this-many digits of the prediction and its alternatives are expanded into
+    /// their permutations (กลับเลข), returned as "PERMUTATIONS" in the prediction output.
+    pub permutation_digits: Option<usize>,
+}
+
+/// What the co-occurrence analysis compares between prize tiers.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CooccurrenceUnit {
+    /// Every digit that appears anywhere in a tier's numbers.
+    #[default]
+    Digit,
+    /// The last two digits of each of a tier's numbers.
+    LastTwo,
+}
+
+/// How much say each method gets in the consensus vote.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusWeighting {
+    /// Every participating method has the same weight.
+    #[default]
+    Equal,
+    /// Weights come from `consensus_weights`.
+    Manual,
+    /// Weights come from how much probability each method gave the actual result over recent draws.
+    Backtest,
+}
+
+/// How the ML evaluation report splits the history into folds.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CvStrategy {
+    /// Contiguous, unshuffled blocks; each block is tested once while the rest is used for training.
+    KFold,
+    /// Expanding window: train on everything before a block, then test on that block.
+    #[default]
+    TimeSeries,
+}
+
+/// What the Markov chain treats as a single state.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkovStateSpace {
+    #[default]
+    FullNumber,
+    PerDigit,
+    LastTwo,
+    LastThree,
+}
+
+/// How unseen transitions are given probability mass.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Smoothing {
+    #[default]
+    None,
+    Laplace,
+    Dirichlet,
+}
+
+/// One draw with the winning numbers of every prize tier, keyed by tier name,
+/// e.g. `{"date": "2024-01-16", "prizes": {"รางวัลที่ 1": ["123456"], "รางวัลเลขท้าย 2 ตัว": ["45"]}}`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct DrawRecord {
+    /// Draw date (YYYY-MM-DD), if known.
+    #[serde(default)]
+    pub date: Option<String>,
+    pub prizes: std::collections::BTreeMap<String, Vec<String>>,
+}
+
+/// Request from the frontend to perform an analysis.
+#[derive(Deserialize)]
+pub struct AnalyzeRequest {
+    #[serde(default)]
+    pub numbers: Vec<String>,
+    /// Multi-prize draws, oldest first, for methods that compare prize tiers.
+    #[serde(default)]
+    pub draws: Vec<DrawRecord>,
+    /// Optional draw dates (YYYY-MM-DD), one per number, used by the seasonal method and as calendar
+    /// features by the ML methods.
+    #[serde(default)]
+    pub dates: Option<Vec<String>>,
+    /// Analyzes the server's stored history instead of the posted numbers, dates and draws.
+    #[serde(default)]
+    pub history: Option<HistoryReference>,
+    /// How `numbers` are listed. Ignored when `dates` are given, since the dates decide the order.
+    #[serde(default)]
+    pub order: Option<InputOrder>,
+    /// The part of each number to analyze. Defaults to the whole number.
+    #[serde(default)]
+    pub target: Option<TargetSpec>,
+    pub method: AnalysisMethod,
+    #[serde(default)]
+    pub params: AnalysisParams,
+}
+
+/// The part of each number an analysis works on, e.g. `{"kind": "last", "digits": 2}` for the last two
+/// digits or `{"kind": "position", "index": 1}` for the leftmost digit.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TargetSpec {
+    Whole,
+    Last { digits: usize },
+    First { digits: usize },
+    /// Counted from 1 at the leftmost digit.
+    Position { index: usize },
+}
+
+impl std::fmt::Display for TargetSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetSpec::Whole => write!(f, "ทั้งตัวเลข"),
+            TargetSpec::Last { digits } => write!(f, "{} ตัวท้าย", digits),
+            TargetSpec::First { digits } => write!(f, "{} ตัวหน้า", digits),
+            TargetSpec::Position { index } => write!(f, "หลักที่ {}", index),
+        }
+    }
+}
+
+/// The order in which posted numbers are listed. The sanook archive, for example, lists the newest draw first.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputOrder {
+    OldestFirst,
+    NewestFirst,
+}
+
+/// Which of the two prizes kept per draw to analyze (see `LottoType::prize_names`).
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryPrize {
+    #[serde(alias = "first")]
+    Prize1,
+    #[serde(alias = "second")]
+    Prize2,
+}
+
+/// A slice of the stored history, e.g. `{"lotto_type": "thai", "prize": "prize1", "last": 200}`
+/// for the Thai first prize of the latest 200 draws.
+#[derive(Deserialize, Debug)]
+pub struct HistoryReference {
+    pub lotto_type: LottoType,
+    pub prize: HistoryPrize,
+    /// Number of most recent draws to analyze; all stored draws when not set.
+    pub last: Option<usize>,
+}
+
+/// The structure of the JSON response for a successful analysis request.
+#[derive(Serialize)]
+pub struct AnalysisResponse {
+    pub statistical_summary: std::collections::HashMap<String, String>,
+    pub pattern_analysis: std::collections::HashMap<String, serde_json::Value>,
+    pub prediction_output: std::collections::HashMap<String, serde_json::Value>,
+    pub detailed_explanation: std::collections::HashMap<String, String>,
+    /// Cross-validated evaluation, attached by the ML methods only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evaluation: Option<EvaluationReport>,
+    /// Ranked candidates with probabilities, attached by every method that predicts a number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prediction_distribution: Option<PredictionDistribution>,
+    /// The part of each number that was analyzed, echoed from the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<TargetSpec>,
+}
+
+/// A candidate outcome with the method's raw probability and the calibrated confidence,
+/// which is shrunk towards the uniform rate according to how much data backs the estimate.
+#[derive(Serialize)]
+pub struct CandidateProbability {
+    pub candidate: String,
+    pub probability: f64,
+    pub calibrated_confidence: f64,
+}
+
+/// The ranked probability distribution behind a prediction.
+#[derive(Serialize)]
+pub struct PredictionDistribution {
+    /// The most likely candidates, highest probability first.
+    pub candidates: Vec<CandidateProbability>,
+    /// Number of possible outcomes, e.g. 10 for a single digit.
+    pub support_size: u64,
+    /// Shannon entropy of the calibrated distribution over every possible outcome.
+    pub entropy_bits: f64,
+    /// Entropy of the uniform distribution over the same outcomes.
+    pub max_entropy_bits: f64,
+    /// `entropy_bits / max_entropy_bits`; 1.0 means the method is no better informed than a uniform guess.
+    pub normalized_entropy: f64,
+    /// Per-position digit probabilities, when the method predicts every digit independently.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digit_probabilities: Option<Vec<[f64; 10]>>,
+    /// Every outcome with a non-zero raw probability, not only the listed candidates.
+    #[serde(skip)]
+    pub outcomes: Vec<(String, f64)>,
+}
+
+/// Precision and recall of a single digit class. Undefined ratios are `None`.
+#[derive(Serialize)]
+pub struct ClassMetrics {
+    pub class: usize,
+    pub precision: Option<f64>,
+    pub recall: Option<f64>,
+    pub support: usize,
+}
+
+/// Out-of-fold accuracy for one predicted digit position.
+#[derive(Serialize)]
+pub struct TargetAccuracy {
+    pub target: String,
+    pub accuracy: f64,
+}
+
+/// Cross-validated evaluation of an ML method, pooled over every predicted digit position.
+#[derive(Serialize)]
+pub struct EvaluationReport {
+    pub model: String,
+    pub strategy: CvStrategy,
+    pub folds: usize,
+    pub predictions: usize,
+    pub accuracy: f64,
+    pub log_loss: f64,
+    pub majority_baseline_accuracy: f64,
+    pub majority_baseline_log_loss: f64,
+    pub chance_accuracy: f64,
+    pub uniform_log_loss: f64,
+    /// Rows are the actual digit, columns the predicted digit.
+    pub confusion_matrix: Vec<Vec<usize>>,
+    pub per_class: Vec<ClassMetrics>,
+    pub per_target: Vec<TargetAccuracy>,
+    /// The part of each number that was evaluated, echoed from the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<TargetSpec>,
+}
+
+/// Output format of an exported feature matrix.
+#[derive(Deserialize, Default, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// Request to export the ML feature matrix built from a list of draws.
+#[derive(Deserialize)]
+pub struct FeatureExportRequest {
+    pub numbers: Vec<String>,
+    /// Optional draw dates (YYYY-MM-DD), one per number, used for calendar features and ordering.
+    pub dates: Option<Vec<String>>,
+    /// How `numbers` are listed when no dates are given.
+    pub order: Option<InputOrder>,
+    pub lags: Option<usize>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Request to measure how often an analyzer reports "patterns" in purely random draw histories.
+#[derive(Deserialize)]
+pub struct NoiseSimulationRequest {
+    /// The real history. Synthetic histories have the same number of draws and digits.
+    pub numbers: Vec<String>,
+    pub method: AnalysisMethod,
+    #[serde(default)]
+    pub params: AnalysisParams,
+    pub simulations: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+/// How a pattern measure of the real history compares with the same measure on random histories.
+#[derive(Serialize)]
+pub struct NoiseMetric {
+    pub name: String,
+    /// The value on the real history, if the analyzer produced it.
+    pub observed: Option<f64>,
+    pub noise_mean: f64,
+    pub noise_p05: f64,
+    pub noise_p95: f64,
+    /// Share of random histories that look at least as patterned as the real one.
+    pub share_as_extreme: Option<f64>,
+}
+
+/// Result of running an analyzer on many uniformly random draw histories.
+#[derive(Serialize)]
+pub struct NoiseSimulationReport {
+    pub method: AnalysisMethod,
+    pub simulations: usize,
+    /// Random histories on which the analyzer returned an error.
+    pub failed_simulations: usize,
+    pub draws_per_history: usize,
+    pub digits: usize,
+    pub seed: u64,
+    pub metrics: Vec<NoiseMetric>,
+}
+
+/// Request to simulate buying the same tickets on every draw.
+#[derive(Deserialize)]
+pub struct StrategySimulationRequest {
+    pub lotto_type: LottoType,
+    /// Thai tickets are 6-digit numbers; Laos tickets are 2- or 3-digit endings.
+    pub tickets: Vec<String>,
+    /// Stake per Laos ticket, in kip. Thai tickets always cost the official price.
+    pub stake: Option<f64>,
+    /// Draws played in one simulated run.
+    pub draws: Option<usize>,
+    pub simulations: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+/// How often a prize tier was won in the simulation.
+#[derive(Serialize)]
+pub struct PrizeTierHits {
+    pub tier: String,
+    pub amount: f64,
+    pub hits_per_draw: f64,
+}
+
+/// Distribution of the net return of a ticket strategy over many simulated runs.
+#[derive(Serialize)]
+pub struct StrategySimulationReport {
+    pub lotto_type: LottoType,
+    pub tickets: usize,
+    pub draws_per_run: usize,
+    pub simulations: usize,
+    pub seed: u64,
+    pub cost_per_run: f64,
+    pub mean_winnings: f64,
+    pub mean_net_return: f64,
+    pub variance_net_return: f64,
+    pub std_dev_net_return: f64,
+    /// Simulated winnings divided by cost.
+    pub return_to_player: f64,
+    /// Winnings divided by cost computed exactly from the payout table.
+    pub exact_return_to_player: f64,
+    pub probability_any_win: f64,
+    pub probability_profit: f64,
+    pub tiers: Vec<PrizeTierHits>,
+}
+
+/// Request to check tickets against a stored draw.
+#[derive(Deserialize)]
+pub struct TicketCheckRequest {
+    pub lotto_type: LottoType,
+    /// Date of the draw to check against; the latest stored draw when not set.
+    pub draw_date: Option<String>,
+    /// Thai tickets are 6-digit numbers; Laos tickets are 2- or 3-digit endings.
+    pub tickets: Vec<String>,
+    /// Stake per Laos ticket, in kip, used to work out its payout.
+    pub stake: Option<f64>,
+}
+
+/// The winning numbers of one prize tier of a draw.
+#[derive(Serialize)]
+pub struct TierNumbers {
+    pub tier: String,
+    pub numbers: Vec<String>,
+}
+
+/// A prize tier won by a ticket.
+#[derive(Serialize)]
+pub struct TicketPrize {
+    pub tier: String,
+    /// The winning number of the tier that the ticket matched.
+    pub winning_number: String,
+    pub amount: f64,
+}
+
+/// Every prize won by one ticket.
+#[derive(Serialize)]
+pub struct TicketResult {
+    pub ticket: String,
+    pub prizes: Vec<TicketPrize>,
+    pub total: f64,
+}
+
+/// Result of checking tickets against one stored draw.
+#[derive(Serialize)]
+pub struct TicketCheckReport {
+    pub lotto_type: LottoType,
+    pub draw_date: String,
+    /// The winning numbers of every tier that was checked, in the order of the prize table.
+    pub winning_numbers: Vec<TierNumbers>,
+    /// Tiers of the official table whose numbers the stored history does not hold, so no ticket
+    /// was checked against them.
+    pub unchecked_tiers: Vec<String>,
+    pub tickets: Vec<TicketResult>,
+    pub winning_tickets: usize,
+    pub total_winnings: f64,
+}
+
+/// Query string of a dream-book lookup. `q` holds keywords separated by commas or spaces;
+/// without it the whole dictionary is returned.
+#[derive(Deserialize)]
+pub struct DreamLookupQuery {
+    pub q: Option<String>,
+}
+
+/// A dream-book keyword and the numbers it maps to.
+#[derive(Serialize)]
+pub struct DreamMatch {
+    pub keyword: String,
+    pub numbers: Vec<String>,
+}
+
+/// Query of `GET /data-quality`. Without a lottery type, every stored type is checked.
+#[derive(Deserialize)]
+pub struct DataQualityQuery {
+    pub lotto_type: Option<LottoType>,
+}
+
+/// The kinds of problems a scraper bug usually leaves in the stored draws.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataQualityIssueKind {
+    /// The same row (date and both prizes) stored more than once, e.g. a page read twice.
+    DuplicateRow,
+    /// Several rows with different prizes on the same date.
+    DuplicateDate,
+    /// Consecutive draws on different dates with identical prizes.
+    RepeatedPrizes,
+    /// A gap in the calendar much longer than the usual interval between draws.
+    MissingDraws,
+    /// A prize that is not made of the expected number of digits.
+    WrongDigitCount,
+    /// A draw date that cannot be read.
+    UnparsableDate,
+}
+
+/// One problem found in the stored draws, with the dates it concerns.
+#[derive(Serialize, Clone, Debug)]
+pub struct DataQualityIssue {
+    pub kind: DataQualityIssueKind,
+    pub dates: Vec<String>,
+    pub detail: String,
+}
+
+/// Every problem found in the stored draws of one lottery type.
+#[derive(Serialize, Clone, Debug)]
+pub struct DataQualityReport {
+    pub lotto_type: LottoType,
+    pub draws: usize,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    /// Usual number of days between consecutive draws, used to find gaps.
+    pub typical_interval_days: Option<i64>,
+    pub issues: Vec<DataQualityIssue>,
+}
+
+/// A prediction made by `/analyze`, kept with everything needed to score it once its draw is scraped.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LedgerEntry {
+    pub id: u64,
+    pub created_at: String,
+    pub method: AnalysisMethod,
+    /// The parameters set in the request; the rest used the method's defaults.
+    pub params: serde_json::Value,
+    pub target: TargetSpec,
+    /// The lottery and prize predicted. Only known, and so only scored, when the analysis referred to
+    /// the stored history.
+    pub lotto_type: Option<LottoType>,
+    pub prize: Option<HistoryPrize>,
+    /// The input window: how many draws were analyzed and the dates of the first and last one.
+    pub input_draws: usize,
+    pub input_first_date: Option<String>,
+    pub input_last_date: Option<String>,
+    /// Estimated date of the predicted draw. Scoring uses the first draw after `input_last_date`,
+    /// so a draw moved by a holiday is still found.
+    pub target_draw_date: Option<String>,
+    pub prediction: String,
+    pub alternatives: Vec<String>,
+    pub outcome: Option<LedgerOutcome>,
+}
+
+/// The real draw a ledger entry was scored against.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LedgerOutcome {
+    pub draw_date: String,
+    /// The drawn prize, reduced to the entry's target.
+    pub actual: String,
+    /// Whether the prediction matched the end of the actual number.
+    pub hit: bool,
+    /// Whether the prediction or any alternative matched.
+    pub hit_any: bool,
+    pub scored_at: String,
+}
+
+/// Real-world accuracy of one method's saved predictions.
+#[derive(Serialize)]
+pub struct LeaderboardRow {
+    pub method: AnalysisMethod,
+    pub predictions: usize,
+    pub scored: usize,
+    pub hits: usize,
+    pub hit_rate: Option<f64>,
+    /// Hit rate a random guess of the same length would have had on the same predictions.
+    pub chance_hit_rate: Option<f64>,
+    pub hits_any: usize,
+    /// Scored predictions grouped by the month of the predicted draw, oldest first.
+    pub by_month: Vec<LeaderboardPeriod>,
+}
+
+/// Hits of one method in one month.
+#[derive(Serialize)]
+pub struct LeaderboardPeriod {
+    pub month: String,
+    pub scored: usize,
+    pub hits: usize,
+    pub hit_rate: f64,
 }
\ No newline at end of file
@@ -0,0 +1,125 @@
+/// Price of one Thai government lottery ticket, in baht.
+pub const THAI_TICKET_PRICE: f64 = 80.0;
+
+/// Which part of a ticket a prize number is compared with.
+#[derive(Clone, Copy)]
+pub enum PrizeMatch {
+    /// All six digits.
+    Whole,
+    /// The first `n` digits.
+    First(usize),
+    /// The last `n` digits.
+    Last(usize),
+    /// One more or one less than the first prize.
+    AdjacentToFirst,
+}
+
+impl PrizeMatch {
+    /// Whether the 6-digit `ticket` wins against the prize number `prize`, where the adjacent tier is
+    /// checked against the first prize.
+    pub fn wins(self, ticket: u32, prize: u32) -> bool {
+        match self {
+            PrizeMatch::Whole => ticket == prize,
+            PrizeMatch::First(n) => ticket / 10u32.pow(6 - n as u32) == prize,
+            PrizeMatch::Last(n) => ticket % 10u32.pow(n as u32) == prize,
+            PrizeMatch::AdjacentToFirst => ticket == (prize + 1) % 1_000_000 || ticket == (prize + 999_999) % 1_000_000,
+        }
+    }
+
+    /// How many digits the prize numbers of the tier have.
+    pub fn digits(self) -> usize {
+        match self {
+            PrizeMatch::Whole | PrizeMatch::AdjacentToFirst => 6,
+            PrizeMatch::First(n) | PrizeMatch::Last(n) => n,
+        }
+    }
+}
+
+/// One tier of the Thai prize table. `count` is how many prize numbers the tier draws.
+pub struct PrizeTier {
+    pub name: &'static str,
+    pub count: usize,
+    pub matches: PrizeMatch,
+    pub amount: f64,
+}
+
+/// The official Thai government lottery prize table, per 80-baht ticket.
+pub const THAI_PRIZES: [PrizeTier; 9] = [
+    PrizeTier { name: "รางวัลที่ 1", count: 1, matches: PrizeMatch::Whole, amount: 6_000_000.0 },
+    PrizeTier { name: "รางวัลข้างเคียงรางวัลที่ 1", count: 2, matches: PrizeMatch::AdjacentToFirst, amount: 100_000.0 },
+    PrizeTier { name: "รางวัลที่ 2", count: 5, matches: PrizeMatch::Whole, amount: 200_000.0 },
+    PrizeTier { name: "รางวัลที่ 3", count: 10, matches: PrizeMatch::Whole, amount: 80_000.0 },
+    PrizeTier { name: "รางวัลที่ 4", count: 50, matches: PrizeMatch::Whole, amount: 40_000.0 },
+    PrizeTier { name: "รางวัลที่ 5", count: 100, matches: PrizeMatch::Whole, amount: 20_000.0 },
+    PrizeTier { name: "รางวัลเลขหน้า 3 ตัว", count: 2, matches: PrizeMatch::First(3), amount: 4_000.0 },
+    PrizeTier { name: "รางวัลเลขท้าย 3 ตัว", count: 2, matches: PrizeMatch::Last(3), amount: 4_000.0 },
+    PrizeTier { name: "รางวัลเลขท้าย 2 ตัว", count: 1, matches: PrizeMatch::Last(2), amount: 2_000.0 },
+];
+
+/// Laos lottery payouts as (digits bet on, multiple of the stake paid when the draw's ending matches).
+/// A Laos ticket is a 2- or 3-digit number matched against the last digits of the draw.
+pub const LAOS_PAYOUT_MULTIPLIERS: [(usize, f64); 2] = [(2, 60.0), (3, 500.0)];
+
+/// Stake per Laos ticket, in kip, when the request does not set one.
+pub const DEFAULT_LAOS_STAKE: f64 = 1_000.0;
+
+/// The payout multiplier for a Laos ticket of `digits` digits, if such a bet exists.
+pub fn laos_multiplier(digits: usize) -> Option<f64> {
+    LAOS_PAYOUT_MULTIPLIERS.iter().find(|(d, _)| *d == digits).map(|(_, m)| *m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thai_table_lists_the_official_tiers() {
+        assert_eq!(THAI_PRIZES.len(), 9);
+        assert_eq!(THAI_PRIZES.iter().map(|t| t.count).sum::<usize>(), 173);
+        let first = &THAI_PRIZES[0];
+        assert_eq!((first.name, first.amount), ("รางวัลที่ 1", 6_000_000.0));
+        assert!(matches!(first.matches, PrizeMatch::Whole));
+        let last_two = &THAI_PRIZES[8];
+        assert_eq!((last_two.name, last_two.count, last_two.amount), ("รางวัลเลขท้าย 2 ตัว", 1, 2_000.0));
+    }
+
+    #[test]
+    fn laos_bets_pay_a_multiple_of_the_stake() {
+        assert_eq!(laos_multiplier(2), Some(60.0));
+        assert_eq!(laos_multiplier(3), Some(500.0));
+        assert_eq!(laos_multiplier(4), None);
+        assert_eq!(laos_multiplier(1), None);
+    }
+
+    #[test]
+    fn tier_digits_match_the_ticket_part_compared() {
+        assert_eq!(PrizeMatch::Whole.digits(), 6);
+        assert_eq!(PrizeMatch::AdjacentToFirst.digits(), 6);
+        assert_eq!(PrizeMatch::First(3).digits(), 3);
+        assert_eq!(PrizeMatch::Last(2).digits(), 2);
+    }
+
+    #[test]
+    fn whole_matches_all_six_digits() {
+        assert!(PrizeMatch::Whole.wins(123456, 123456));
+        assert!(!PrizeMatch::Whole.wins(123456, 123457));
+    }
+
+    #[test]
+    fn first_and_last_compare_the_leading_and_trailing_digits() {
+        assert!(PrizeMatch::First(3).wins(123456, 123));
+        assert!(!PrizeMatch::First(3).wins(123456, 456));
+        assert!(PrizeMatch::Last(3).wins(123456, 456));
+        assert!(PrizeMatch::Last(2).wins(100005, 5));
+        assert!(!PrizeMatch::Last(2).wins(100050, 5));
+    }
+
+    #[test]
+    fn adjacent_prizes_wrap_around_the_number_range() {
+        assert!(PrizeMatch::AdjacentToFirst.wins(123455, 123456));
+        assert!(PrizeMatch::AdjacentToFirst.wins(123457, 123456));
+        assert!(!PrizeMatch::AdjacentToFirst.wins(123456, 123456));
+        assert!(PrizeMatch::AdjacentToFirst.wins(999_999, 0));
+        assert!(PrizeMatch::AdjacentToFirst.wins(0, 999_999));
+    }
+}
@@ -1,160 +1,276 @@
-use crate::models::{AppState, LottoResult, LottoType};
-use actix_web::web;
-use scraper::{Html, Selector};
-use tokio::time::{sleep, Duration};
-use anyhow::{Result, anyhow};
-
-
-/// Main scraper function that builds a tolerant HTTP client and dispatches to the correct scraper.
-pub async fn run_scraper(lotto_type: LottoType, app_state: web::Data<AppState>) {
-    // Create a custom reqwest client that ignores SSL certificate errors.
-    let client = match reqwest::Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()
-    {
-        Ok(client) => client,
-        Err(e) => {
-            let mut status = app_state.task_status.lock().unwrap();
-            status.progress.push(format!("⚠️ ข้อผิดพลาดร้ายแรง: ไม่สามารถสร้าง HTTP client: {}", e));
-            status.is_running = false;
-            return;
-        }
-    };
-
-    let start_url = match lotto_type {
-        LottoType::Thai => "https://news.sanook.com/lotto/archive/".to_string(),
-        LottoType::Laos => "https://expserve.com/backward/laosdevelops".to_string(),
-    };
-
-    let mut all_results = Vec::new();
-    let mut current_url = Some(start_url);
-
-    // Loop through all pages until there is no "Next Page" link.
-    while let Some(url) = current_url {
-        {
-            let mut status = app_state.task_status.lock().unwrap();
-            status.progress.push(format!("📄 กำลังดึงข้อมูลหน้า: {}", url));
-        }
-
-        // Dispatch to the correct scraper function based on the selected lotto type.
-        let scrape_result = match lotto_type {
-            LottoType::Thai => scrape_thai_page(&client, &url).await,
-            LottoType::Laos => scrape_laos_page(&client, &url).await,
-        };
-
-        match scrape_result {
-            Ok((mut page_results, next_url)) => {
-                all_results.append(&mut page_results);
-                current_url = next_url; // This will be `None` on the last page, stopping the loop.
-            }
-            Err(e) => {
-                let mut status = app_state.task_status.lock().unwrap();
-                status.progress.push(format!("⚠️ เกิดข้อผิดพลาดในการดึงข้อมูลหน้า {}: {}", url, e));
-                current_url = None; // Stop on error.
-            }
-        }
-        sleep(Duration::from_millis(500)).await; // Be polite to the server.
-    }
-
-    // Update the final status once scraping is complete.
-    let mut status = app_state.task_status.lock().unwrap();
-    status.results = all_results;
-    status.progress.push(format!("✅ การดึงข้อมูลสลาก {}เสร็จสมบูรณ์", lotto_type));
-    status.is_running = false;
-}
-
-/// Scrapes a single page of Thai lottery results from news.sanook.com.
-async fn scrape_thai_page(
-    client: &reqwest::Client,
-    url: &str,
-) -> Result<(Vec<LottoResult>, Option<String>)> {
-    let resp_text = client.get(url).send().await?.text().await?;
-    let document = Html::parse_document(&resp_text);
-
-    let article_selector = Selector::parse(r#"article.archive--lotto"#).unwrap();
-    let date_selector = Selector::parse(r#"time.archive--lotto__date"#).unwrap();
-    let li_selector = Selector::parse(r#"ul.archive--lotto__result-list li"#).unwrap();
-    let label_selector = Selector::parse(r#"em.archive--lotto__result-txt"#).unwrap();
-    let number_selector = Selector::parse(r#"strong.archive--lotto__result-number"#).unwrap();
-    let next_button_selector = Selector::parse(r#"a.pagination__item--next"#).unwrap();
-
-    let mut page_results = Vec::new();
-    for article in document.select(&article_selector) {
-        let draw_date = article.select(&date_selector).next()
-            .and_then(|time| time.value().attr("datetime")).unwrap_or("Unknown").to_string();
-
-        let mut first_prize = None;
-        let mut last_2_digits = None;
-
-        for li in article.select(&li_selector) {
-            let label = li.select(&label_selector).next().map(|em| em.text().collect::<String>());
-            let prize = li.select(&number_selector).next().map(|s| s.text().collect::<String>());
-            if let (Some(label_text), Some(prize_text)) = (label, prize) {
-                if label_text.contains("รางวัลที่ 1") { first_prize = Some(prize_text.trim().to_string()); }
-                else if label_text.contains(r#"เลขท้าย 2 ตัว"#) { last_2_digits = Some(prize_text.trim().to_string()); }
-            }
-        }
-
-        if let (Some(fp), Some(l2d)) = (first_prize, last_2_digits) {
-            page_results.push(LottoResult { draw_date, prize1: fp, prize2: l2d });
-        }
-    }
-    let next_page_url = document.select(&next_button_selector).next()
-        .and_then(|a| a.value().attr("href")).map(String::from);
-
-    Ok((page_results, next_page_url))
-}
-
-/// Scrapes a single page of Laos lottery results from expserve.com.
-async fn scrape_laos_page(
-    client: &reqwest::Client,
-    url: &str,
-) -> Result<(Vec<LottoResult>, Option<String>)> {
-    let resp_text = client.get(url).send().await?.text().await?;
-    let document = Html::parse_document(&resp_text);
-
-    let row_selector = Selector::parse(r#"div.m_410352e9.mantine-Grid-root"#).unwrap();
-
-    let mut page_results = Vec::new();
-    for row in document.select(&row_selector).skip(1) { // Skip header row
-        let cols: Vec<String> = row.select(&Selector::parse("div.mantine-Grid-col").unwrap())
-                                  .map(|div| div.text().collect::<String>())
-                                  .collect();
-        
-        if cols.len() >= 3 {
-            let date_text = cols[0].split('|').last().unwrap_or("").trim().to_string();
-            let prize1 = cols[1].trim().to_string(); // 3-digit prize
-            let prize2 = cols[2].trim().to_string(); // 2-digit prize
-
-            if date_text.is_empty() || prize1.is_empty() || prize2.is_empty() || prize1 == "งดออกผล" {
-                continue;
-            }
-
-            page_results.push(LottoResult { draw_date: date_text, prize1, prize2 });
-        }
-    }
-
-    if page_results.is_empty() && document.select(&row_selector).count() > 1 {
-         return Err(anyhow!("ไม่สามารถแยกวิเคราะห์ผลลัพธ์ใดๆ ได้ แม้ว่าจะพบแถวก็ตาม โครงสร้าง HTML อาจมีการเปลี่ยนแปลง"));
-    }
-
-    // --- Automatic Next Page Logic ---
-    let mut next_page_url: Option<String> = None;
-    let anchor_selector = Selector::parse("a").unwrap();
-    let label_selector = Selector::parse(r#"span.mantine-Button-label"#).unwrap();
-
-    for link_element in document.select(&anchor_selector) {
-        if let Some(label_span) = link_element.select(&label_selector).next() {
-            if label_span.text().collect::<String>().trim() == "หน้าต่อไป" {
-                if let Some(href) = link_element.value().attr("href") {
-                    let base_url = reqwest::Url::parse(url)?;
-                    let next_url = base_url.join(href)?;
-                    next_page_url = Some(next_url.to_string());
-                    break;
-                }
-            }
-        }
-    }
-
-    Ok((page_results, next_page_url))
+use crate::analysis;
+use crate::history;
+use crate::ledger;
+use crate::models::{AppState, LottoResult, LottoType};
+use crate::payouts::THAI_PRIZES;
+use actix_web::web;
+use scraper::{Html, Selector};
+use tokio::time::{sleep, Duration};
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+
+/// Data-quality issues listed in the progress log after a scrape; the rest are in the full report.
+const MAX_LOGGED_ISSUES: usize = 10;
+
+/// Main scraper function that builds a tolerant HTTP client and dispatches to the correct scraper.
+pub async fn run_scraper(lotto_type: LottoType, app_state: web::Data<AppState>) {
+    // Create a custom reqwest client that ignores SSL certificate errors.
+    let client = match reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            let mut status = app_state.task_status.lock().unwrap();
+            status.progress.push(format!("⚠️ ข้อผิดพลาดร้ายแรง: ไม่สามารถสร้าง HTTP client: {}", e));
+            status.is_running = false;
+            return;
+        }
+    };
+
+    let start_url = match lotto_type {
+        LottoType::Thai => "https://news.sanook.com/lotto/archive/".to_string(),
+        LottoType::Laos => "https://expserve.com/backward/laosdevelops".to_string(),
+    };
+
+    let mut all_results = Vec::new();
+    let mut current_url = Some(start_url);
+
+    // Loop through all pages until there is no "Next Page" link.
+    while let Some(url) = current_url {
+        {
+            let mut status = app_state.task_status.lock().unwrap();
+            status.progress.push(format!("📄 กำลังดึงข้อมูลหน้า: {}", url));
+        }
+
+        // Dispatch to the correct scraper function based on the selected lotto type.
+        let scrape_result = match lotto_type {
+            LottoType::Thai => scrape_thai_page(&client, &url).await,
+            LottoType::Laos => scrape_laos_page(&client, &url).await,
+        };
+
+        match scrape_result {
+            Ok((mut page_results, next_url)) => {
+                all_results.append(&mut page_results);
+                current_url = next_url; // This will be `None` on the last page, stopping the loop.
+            }
+            Err(e) => {
+                let mut status = app_state.task_status.lock().unwrap();
+                status.progress.push(format!("⚠️ เกิดข้อผิดพลาดในการดึงข้อมูลหน้า {}: {}", url, e));
+                current_url = None; // Stop on error.
+            }
+        }
+        sleep(Duration::from_millis(500)).await; // Be polite to the server.
+    }
+
+    // Keep the draws on the server so they can be analyzed without posting them back.
+    // A failed scrape that found nothing leaves the previous history in place.
+    let save_result = if all_results.is_empty() {
+        Ok(())
+    } else {
+        app_state.history.lock().unwrap().insert(lotto_type.clone(), all_results.clone());
+        let state = app_state.clone();
+        web::block(move || history::save_snapshot(&state.history)).await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r.map_err(anyhow::Error::from))
+    };
+
+    let quality = (!all_results.is_empty()).then(|| analysis::check_data_quality(&lotto_type, &all_results));
+
+    // Predictions waiting for one of the new draws can now be scored.
+    let scored = analysis::score_predictions(&mut app_state.ledger.lock().unwrap(), &lotto_type, &all_results);
+    let ledger_result = if scored > 0 {
+        let state = app_state.clone();
+        web::block(move || ledger::save_snapshot(&state.ledger)).await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r.map_err(anyhow::Error::from))
+    } else {
+        Ok(())
+    };
+
+    // Update the final status once scraping is complete.
+    let mut status = app_state.task_status.lock().unwrap();
+    if let Err(e) = save_result {
+        status.progress.push(format!("⚠️ ไม่สามารถบันทึกประวัติลงดิสก์: {}", e));
+    }
+    if let Some(report) = &quality {
+        if report.issues.is_empty() {
+            status.progress.push("🔎 ตรวจคุณภาพข้อมูล: ไม่พบปัญหา".to_string());
+        } else {
+            status.progress.push(format!("⚠️ ตรวจคุณภาพข้อมูล: พบ {} ปัญหา (ดูรายละเอียดที่ /data-quality)", report.issues.len()));
+            for issue in report.issues.iter().take(MAX_LOGGED_ISSUES) {
+                status.progress.push(format!("• {}: {}", issue.dates.join(" - "), issue.detail));
+            }
+        }
+    }
+    if scored > 0 {
+        status.progress.push(format!("📒 ให้คะแนนคำทำนายที่รอผลแล้ว {} รายการ (ดูที่ /ledger/leaderboard)", scored));
+    }
+    if let Err(e) = ledger_result {
+        status.progress.push(format!("⚠️ ไม่สามารถบันทึกผลคะแนนคำทำนายลงดิสก์: {}", e));
+    }
+    status.quality = quality;
+    status.results = all_results;
+    status.progress.push(format!("✅ การดึงข้อมูลสลาก {}เสร็จสมบูรณ์", lotto_type));
+    status.is_running = false;
+}
+
+/// Scrapes a single page of Thai lottery results from news.sanook.com.
+async fn scrape_thai_page(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(Vec<LottoResult>, Option<String>)> {
+    let resp_text = client.get(url).send().await?.text().await?;
+    Ok(parse_thai_page(&resp_text))
+}
+
+/// Reads the draws and the next-page link from one page of the sanook archive.
+fn parse_thai_page(html: &str) -> (Vec<LottoResult>, Option<String>) {
+    let document = Html::parse_document(html);
+
+    let article_selector = Selector::parse(r#"article.archive--lotto"#).unwrap();
+    let date_selector = Selector::parse(r#"time.archive--lotto__date"#).unwrap();
+    let li_selector = Selector::parse(r#"ul.archive--lotto__result-list li"#).unwrap();
+    let label_selector = Selector::parse(r#"em.archive--lotto__result-txt"#).unwrap();
+    let number_selector = Selector::parse(r#"strong.archive--lotto__result-number"#).unwrap();
+    let next_button_selector = Selector::parse(r#"a.pagination__item--next"#).unwrap();
+
+    let mut page_results = Vec::new();
+    for article in document.select(&article_selector) {
+        let draw_date = article.select(&date_selector).next()
+            .and_then(|time| time.value().attr("datetime")).unwrap_or("Unknown").to_string();
+
+        let mut first_prize = None;
+        let mut last_2_digits = None;
+        let mut other_prizes = BTreeMap::new();
+
+        for li in article.select(&li_selector) {
+            let label = li.select(&label_selector).next().map(|em| em.text().collect::<String>());
+            let prize = li.select(&number_selector).next().map(|s| s.text().collect::<String>());
+            if let (Some(label_text), Some(prize_text)) = (label, prize) {
+                // The adjacent prizes follow from the first prize, so their line is not kept.
+                if label_text.contains("ข้างเคียง") { continue; }
+                if label_text.contains("รางวัลที่ 1") { first_prize = Some(prize_text.trim().to_string()); }
+                else if label_text.contains(r#"เลขท้าย 2 ตัว"#) { last_2_digits = Some(prize_text.trim().to_string()); }
+                else if let Some(tier) = thai_tier_name(&label_text) {
+                    // Tiers with several numbers list them in one or more elements, separated by spaces or commas.
+                    let numbers: Vec<String> = li.select(&number_selector)
+                        .flat_map(|s| s.text().collect::<String>()
+                            .split(|c: char| !c.is_ascii_digit())
+                            .filter(|n| !n.is_empty())
+                            .map(String::from)
+                            .collect::<Vec<_>>())
+                        .collect();
+                    if !numbers.is_empty() {
+                        other_prizes.insert(tier.to_string(), numbers);
+                    }
+                }
+            }
+        }
+
+        if let (Some(fp), Some(l2d)) = (first_prize, last_2_digits) {
+            page_results.push(LottoResult { draw_date, prize1: fp, prize2: l2d, other_prizes });
+        }
+    }
+    let next_page_url = document.select(&next_button_selector).next()
+        .and_then(|a| a.value().attr("href")).map(String::from);
+
+    (page_results, next_page_url)
+}
+
+/// The Thai prize-table tier named by a label of the archive, for the tiers other than the first prize
+/// and the last two digits. The archive drops the leading "รางวัล" of some names, e.g. "เลขหน้า 3 ตัว".
+fn thai_tier_name(label: &str) -> Option<&'static str> {
+    THAI_PRIZES[2..THAI_PRIZES.len() - 1].iter()
+        .map(|tier| tier.name)
+        .find(|name| label.contains(name) || label.contains(name.trim_start_matches("รางวัล")))
+}
+
+/// Scrapes a single page of Laos lottery results from expserve.com.
+async fn scrape_laos_page(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(Vec<LottoResult>, Option<String>)> {
+    let resp_text = client.get(url).send().await?.text().await?;
+    let document = Html::parse_document(&resp_text);
+
+    let row_selector = Selector::parse(r#"div.m_410352e9.mantine-Grid-root"#).unwrap();
+
+    let mut page_results = Vec::new();
+    for row in document.select(&row_selector).skip(1) { // Skip header row
+        let cols: Vec<String> = row.select(&Selector::parse("div.mantine-Grid-col").unwrap())
+                                  .map(|div| div.text().collect::<String>())
+                                  .collect();
+        
+        if cols.len() >= 3 {
+            let date_text = cols[0].split('|').next_back().unwrap_or("").trim().to_string();
+            let prize1 = cols[1].trim().to_string(); // 3-digit prize
+            let prize2 = cols[2].trim().to_string(); // 2-digit prize
+
+            if date_text.is_empty() || prize1.is_empty() || prize2.is_empty() || prize1 == "งดออกผล" {
+                continue;
+            }
+
+            page_results.push(LottoResult { draw_date: date_text, prize1, prize2, other_prizes: BTreeMap::new() });
+        }
+    }
+
+    if page_results.is_empty() && document.select(&row_selector).count() > 1 {
+         return Err(anyhow!("ไม่สามารถแยกวิเคราะห์ผลลัพธ์ใดๆ ได้ แม้ว่าจะพบแถวก็ตาม โครงสร้าง HTML อาจมีการเปลี่ยนแปลง"));
+    }
+
+    // --- Automatic Next Page Logic ---
+    let mut next_page_url: Option<String> = None;
+    let anchor_selector = Selector::parse("a").unwrap();
+    let label_selector = Selector::parse(r#"span.mantine-Button-label"#).unwrap();
+
+    for link_element in document.select(&anchor_selector) {
+        if let Some(label_span) = link_element.select(&label_selector).next() {
+            if label_span.text().collect::<String>().trim() == "หน้าต่อไป" {
+                if let Some(href) = link_element.value().attr("href") {
+                    let base_url = reqwest::Url::parse(url)?;
+                    let next_url = base_url.join(href)?;
+                    next_page_url = Some(next_url.to_string());
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok((page_results, next_page_url))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_three_digit_prizes_of_the_archive() {
+        let html = r#"
+            <article class="archive--lotto">
+                <time class="archive--lotto__date" datetime="2025-05-16"></time>
+                <ul class="archive--lotto__result-list">
+                    <li><em class="archive--lotto__result-txt">รางวัลที่ 1</em><strong class="archive--lotto__result-number">807952</strong></li>
+                    <li><em class="archive--lotto__result-txt">เลขหน้า 3 ตัว</em><strong class="archive--lotto__result-number">123 456</strong></li>
+                    <li><em class="archive--lotto__result-txt">เลขท้าย 3 ตัว</em><strong class="archive--lotto__result-number">789</strong><strong class="archive--lotto__result-number">052</strong></li>
+                    <li><em class="archive--lotto__result-txt">เลขท้าย 2 ตัว</em><strong class="archive--lotto__result-number">52</strong></li>
+                </ul>
+            </article>
+            <a class="pagination__item--next" href="/lotto/archive/page/2/">ถัดไป</a>"#;
+        let (results, next) = parse_thai_page(html);
+
+        assert_eq!(next.as_deref(), Some("/lotto/archive/page/2/"));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].prize1, "807952");
+        assert_eq!(results[0].prize2, "52");
+        assert_eq!(results[0].other_prizes["รางวัลเลขหน้า 3 ตัว"], ["123", "456"]);
+        assert_eq!(results[0].other_prizes["รางวัลเลขท้าย 3 ตัว"], ["789", "052"]);
+    }
+
+    #[test]
+    fn maps_archive_labels_to_prize_tiers() {
+        assert_eq!(thai_tier_name("รางวัลที่ 2"), Some("รางวัลที่ 2"));
+        assert_eq!(thai_tier_name("เลขหน้า 3 ตัว"), Some("รางวัลเลขหน้า 3 ตัว"));
+        assert_eq!(thai_tier_name("รางวัลเลขท้าย 3 ตัว"), Some("รางวัลเลขท้าย 3 ตัว"));
+        assert_eq!(thai_tier_name("เลขท้าย 2 ตัว"), None);
+    }
 }
\ No newline at end of file
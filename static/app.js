@@ -1,276 +1,561 @@
-document.addEventListener('DOMContentLoaded', () => {
-    // --- Element References ---
-    const elements = {
-        lottoTypeSelect: document.getElementById('lotto-type-select'),
-        scrapeBtn: document.getElementById('scrape-btn'),
-        progressContainer: document.getElementById('progress-container'),
-        tableContainer: document.getElementById('table-container'),
-        resultsHead: document.getElementById('results-head'),
-        resultsBody: document.getElementById('results-body'),
-        analysisSection: document.getElementById('analysis-section'),
-        predictionTypeGroup: document.getElementById('prediction-type-group'),
-        numberInput: document.getElementById('number-input'),
-        analysisMethodSelect: document.getElementById('analysis-method-select'),
-        analyzeBtn: document.getElementById('analyze-btn'),
-        analysisResultsContainer: document.getElementById('analysis-results-container'),
-    };
-
-    // --- Application State ---
-    const state = {
-        scrapedResultsData: [],
-        scrapeStatusInterval: null,
-        // Default to 'thai', will be updated on change.
-        currentLottoType: 'thai',
-    };
-
-    // --- Configuration for Different Lottery Types ---
-    const LOTTO_CONFIG = {
-        thai: {
-            headers: ['วันที่ออกรางวัล', 'รางวัลที่ 1', 'เลขท้าย 2 ตัว'],
-            prizes: {
-                'prize1': { name: 'รางวัลที่ 1', key: 'Prize 1' },
-                'prize2': { name: 'เลขท้าย 2 ตัว', key: 'Prize 2' },
-            }
-        },
-        laos: {
-            headers: ['วันที่ออกรางวัล', 'รางวัล 3 ตัว', 'รางวัล 2 ตัว'],
-            prizes: {
-                'prize1': { name: 'รางวัล 3 ตัว', key: 'Prize 1' },
-                'prize2': { name: 'รางวัล 2 ตัว', key: 'Prize 2' },
-            }
-        }
-    };
-
-    // --- Event Listeners ---
-    elements.scrapeBtn.addEventListener('click', handleScrapeButtonClick);
-    elements.analyzeBtn.addEventListener('click', handleAnalyzeButtonClick);
-    elements.lottoTypeSelect.addEventListener('change', (e) => {
-        state.currentLottoType = e.target.value;
-        // Reset UI when switching types
-        resetUIForNewType();
-    });
-
-    // --- Functions ---
-
-    function resetUIForNewType() {
-        state.scrapedResultsData = [];
-        elements.tableContainer.style.display = 'none';
-        elements.analysisSection.style.display = 'none';
-        elements.resultsBody.innerHTML = '';
-        elements.resultsHead.innerHTML = '';
-        elements.progressContainer.innerHTML = '';
-        elements.progressContainer.style.display = 'none';
-        elements.analysisResultsContainer.innerHTML = '';
-    }
-
-    async function handleScrapeButtonClick() {
-        setScraperUIState(true);
-        try {
-            const response = await fetch('/start-scrape', {
-                method: 'POST',
-                headers: { 'Content-Type': 'application/json' },
-                body: JSON.stringify({ lotto_type: state.currentLottoType })
-            });
-
-            if (!response.ok) {
-                const errData = await response.json();
-                throw new Error(errData.error || `ข้อผิดพลาดจากเซิร์ฟเวอร์: ${response.status}`);
-            }
-            // Start polling for status updates.
-            state.scrapeStatusInterval = setInterval(checkScrapeStatus, 2000);
-        } catch (error) {
-            showError(elements.progressContainer, error.message);
-            setScraperUIState(false);
-        }
-    }
-
-    async function checkScrapeStatus() {
-        try {
-            const response = await fetch('/status');
-            const data = await response.json();
-            
-            // Display progress logs.
-            elements.progressContainer.innerHTML = data.progress.join('<br>');
-            elements.progressContainer.scrollTop = elements.progressContainer.scrollHeight;
-
-            // If the task is no longer running, process the results.
-            if (!data.is_running) {
-                clearInterval(state.scrapeStatusInterval);
-                state.scrapedResultsData = data.results;
-                displayScrapeResults(data.results);
-                setScraperUIState(false);
-            }
-        } catch (error) {
-            showError(elements.progressContainer, `เกิดข้อผิดพลาดในการตรวจสอบสถานะ: ${error.message}`);
-            clearInterval(state.scrapeStatusInterval);
-            setScraperUIState(false);
-        }
-    }
-
-    function displayScrapeResults(results) {
-        if (!results || results.length === 0) {
-            elements.progressContainer.innerHTML += '<br><br><strong>ไม่พบข้อมูลจากการดึงข้อมูล</strong>';
-            return;
-        }
-
-        const config = LOTTO_CONFIG[state.currentLottoType];
-        // Update Table Headers
-        elements.resultsHead.innerHTML = `<tr>${config.headers.map(h => `<th>${h}</th>`).join('')}</tr>`;
-
-        // Update Table Body
-        elements.resultsBody.innerHTML = results.map(result => `
-            <tr>
-                <td>${result['Draw Date']}</td>
-                <td><strong>${result['Prize 1']}</strong></td>
-                <td><strong>${result['Prize 2'] || ''}</strong></td>
-            </tr>
-        `).join('');
-
-        // Show table and analysis section
-        elements.tableContainer.style.display = 'block';
-        elements.analysisSection.style.display = 'block';
-
-        // Update the analysis UI based on the new data
-        updateAnalysisUI();
-    }
-    
-    function updateAnalysisUI() {
-        const config = LOTTO_CONFIG[state.currentLottoType];
-        const prizeKeys = Object.keys(config.prizes);
-
-        // Update Prediction Target Radio Buttons
-        elements.predictionTypeGroup.innerHTML = prizeKeys.map((key, index) => `
-            <input type="radio" id="predict-${key}" name="prediction_type" value="${key}" ${index === 0 ? 'checked' : ''}>
-            <label for="predict-${key}">${config.prizes[key].name}</label>
-        `).join('');
-        
-        // Add event listeners to the newly created radio buttons
-        document.querySelectorAll('input[name="prediction_type"]').forEach(radio => {
-            radio.addEventListener('change', (e) => updateAnalysisInput(e.target.value));
-        });
-
-        // Trigger the change event to populate the textarea with the default prize type
-        updateAnalysisInput(prizeKeys[0]);
-    }
-
-    function updateAnalysisInput(prizeId) {
-        if (state.scrapedResultsData.length === 0) return;
-        
-        // Get the data key (e.g., 'Prize 1') from the config using the prizeId (e.g., 'prize1')
-        const dataKey = LOTTO_CONFIG[state.currentLottoType].prizes[prizeId].key;
-        
-        // Map the results to get the correct prize numbers, reverse for chronological order, and filter out any empty values.
-        const numbersForAnalysis = [...state.scrapedResultsData].reverse()
-            .map(result => result[dataKey]?.replace(/[^0-9]/g, ''))
-            .filter(Boolean);
-            
-        elements.numberInput.value = numbersForAnalysis.join(', ');
-        // Clear previous analysis results when the input changes
-        elements.analysisResultsContainer.innerHTML = '';
-    }
-
-    async function handleAnalyzeButtonClick() {
-        const numbersText = elements.numberInput.value;
-        if (!numbersText.trim()) {
-            showError(elements.analysisResultsContainer, 'กรุณาใส่ชุดตัวเลขเพื่อวิเคราะห์');
-            return;
-        }
-        const numbersArray = numbersText.split(',').map(s => s.trim()).filter(s => s);
-        
-        setAnalyzerUIState(true);
-        try {
-            const response = await fetch('/analyze', {
-                method: 'POST',
-                headers: { 'Content-Type': 'application/json' },
-                body: JSON.stringify({ 
-                    numbers: numbersArray, 
-                    method: elements.analysisMethodSelect.value 
-                })
-            });
-            const resultData = await response.json();
-            if (!response.ok || resultData.error) {
-                throw new Error(resultData.error || 'การวิเคราะห์ล้มเหลว');
-            }
-            displayAnalysisResults(resultData);
-        } catch (error) {
-            showError(elements.analysisResultsContainer, `ข้อผิดพลาดในการวิเคราะห์: ${error.message}`);
-        } finally {
-            setAnalyzerUIState(false, true); // Keep results visible after analysis
-        }
-    }
-
-    function displayAnalysisResults(data) {
-        const { statistical_summary, pattern_analysis, prediction_output, detailed_explanation } = data;
-        
-        // Helper function to create a list block, now handles both objects and arrays
-        const createListHtml = (title, dataObj) => {
-            if (!dataObj || Object.keys(dataObj).length === 0) return '';
-            
-            let items;
-            if (Array.isArray(dataObj)) {
-                items = dataObj.map(value => `<li>${value}</li>`).join('');
-            } else {
-                items = Object.entries(dataObj)
-                    .map(([key, value]) => `<li><strong>${key}:</strong> ${Array.isArray(value) ? value.join(', ') : JSON.stringify(value).replace(/"/g, '')}</li>`)
-                    .join('');
-            }
-            return `<div class="result-block"><h3>${title}</h3><ul>${items}</ul></div>`;
-        };
-        
-        const createParagraphHtml = (title, dataObj) => {
-            if (!dataObj || Object.keys(dataObj).length === 0) return '';
-             const items = Object.entries(dataObj)
-                .map(([key, value]) => `<h4>${key}</h4><p>${value}</p>`)
-                .join('');
-            return `<div class="result-block"><h3>${title}</h3>${items}</div>`;
-        };
-
-        const predictionHtml = `
-            <div class="result-block prediction-block">
-                <h3>🔮 คำทำนายจาก AI</h3>
-                <div class="prediction-value">${prediction_output.PREDICTION}</div>
-                <div class="confidence"><strong>โมเดล:</strong> ${prediction_output.METHOD || 'N/A'}</div>
-            </div>`;
-
-        // Generate HTML for alternative predictions if they exist
-        const alternativesHtml = prediction_output['ทางเลือกอื่นๆ'] ? 
-            createListHtml('🎲 ทางเลือกอื่นๆ', prediction_output['ทางเลือกอื่นๆ']) : '';
-
-        elements.analysisResultsContainer.innerHTML = [
-            predictionHtml,
-            alternativesHtml, // Add alternatives right after the main prediction
-            createListHtml('📊 สรุปสถิติ', statistical_summary),
-            createListHtml('🧩 การวิเคราะห์รูปแบบ', pattern_analysis),
-            createParagraphHtml('📝 คำอธิบายโดยละเอียด', detailed_explanation)
-        ].join('');
-    }
-    
-    // --- UI State Management ---
-    function setScraperUIState(isScraping) {
-        elements.scrapeBtn.disabled = isScraping;
-        elements.lottoTypeSelect.disabled = isScraping;
-        elements.progressContainer.style.display = isScraping ? 'block' : 'none';
-        if (isScraping) {
-            resetUIForNewType();
-            elements.progressContainer.style.display = 'block';
-        }
-    }
-
-    function setAnalyzerUIState(isAnalyzing, keepContent = false) {
-        elements.analyzeBtn.disabled = isAnalyzing;
-        if (isAnalyzing) {
-            elements.analysisResultsContainer.innerHTML = `
-                <div class="spinner-container">
-                    <div class="spinner"></div>
-                    <p>AI กำลังประมวลผลข้อมูล...</p>
-                </div>`;
-        } else if (!keepContent) {
-            elements.analysisResultsContainer.innerHTML = '';
-        }
-    }
-
-    function showError(container, message) {
-        container.innerHTML = `<p class="error">${message}</p>`;
-        container.style.display = 'block';
-    }
+document.addEventListener('DOMContentLoaded', () => {
+    // --- Element References ---
+    const elements = {
+        lottoTypeSelect: document.getElementById('lotto-type-select'),
+        scrapeBtn: document.getElementById('scrape-btn'),
+        progressContainer: document.getElementById('progress-container'),
+        tableContainer: document.getElementById('table-container'),
+        resultsHead: document.getElementById('results-head'),
+        resultsBody: document.getElementById('results-body'),
+        analysisSection: document.getElementById('analysis-section'),
+        predictionTypeGroup: document.getElementById('prediction-type-group'),
+        numberInput: document.getElementById('number-input'),
+        analysisMethodSelect: document.getElementById('analysis-method-select'),
+        permutationSelect: document.getElementById('permutation-select'),
+        targetSelect: document.getElementById('target-select'),
+        crossValidateGroup: document.getElementById('cross-validate-group'),
+        crossValidateInput: document.getElementById('cross-validate-input'),
+        dreamKeywordsGroup: document.getElementById('dream-keywords-group'),
+        dreamKeywordsInput: document.getElementById('dream-keywords-input'),
+        dreamLookupResult: document.getElementById('dream-lookup-result'),
+        analyzeBtn: document.getElementById('analyze-btn'),
+        analysisResultsContainer: document.getElementById('analysis-results-container'),
+        ticketLottoTypeSelect: document.getElementById('ticket-lotto-type-select'),
+        ticketDateInput: document.getElementById('ticket-date-input'),
+        ticketInput: document.getElementById('ticket-input'),
+        ticketStakeGroup: document.getElementById('ticket-stake-group'),
+        ticketStakeInput: document.getElementById('ticket-stake-input'),
+        checkTicketsBtn: document.getElementById('check-tickets-btn'),
+        ticketResultsContainer: document.getElementById('ticket-results-container'),
+    };
+
+    // --- Application State ---
+    const state = {
+        scrapedResultsData: [],
+        // Draw dates aligned with the numbers placed in the analysis input.
+        analysisDates: [],
+        // The prize tier and text placed in the analysis input, so an unedited input can be
+        // analyzed from the server's stored history instead of being posted back.
+        analysisPrizeId: null,
+        generatedInput: '',
+        scrapeStatusInterval: null,
+        // Default to 'thai', will be updated on change.
+        currentLottoType: 'thai',
+    };
+
+    // --- Configuration for Different Lottery Types ---
+    const LOTTO_CONFIG = {
+        thai: {
+            headers: ['วันที่ออกรางวัล', 'รางวัลที่ 1', 'รางวัลเลขท้าย 2 ตัว'],
+            // Tiers kept under 'Other Prizes', shown as extra columns when scraped.
+            otherPrizes: ['รางวัลเลขหน้า 3 ตัว', 'รางวัลเลขท้าย 3 ตัว'],
+            prizes: {
+                'prize1': { name: 'รางวัลที่ 1', key: 'Prize 1' },
+                // Tier names match the server's prize table, so draws sent for co-occurrence line up with stored ones.
+                'prize2': { name: 'รางวัลเลขท้าย 2 ตัว', key: 'Prize 2' },
+            }
+        },
+        laos: {
+            headers: ['วันที่ออกรางวัล', 'รางวัล 3 ตัว', 'รางวัล 2 ตัว'],
+            otherPrizes: [],
+            prizes: {
+                'prize1': { name: 'รางวัล 3 ตัว', key: 'Prize 1' },
+                'prize2': { name: 'รางวัล 2 ตัว', key: 'Prize 2' },
+            }
+        }
+    };
+
+    // --- Event Listeners ---
+    elements.scrapeBtn.addEventListener('click', handleScrapeButtonClick);
+    elements.analyzeBtn.addEventListener('click', handleAnalyzeButtonClick);
+    elements.lottoTypeSelect.addEventListener('change', (e) => {
+        state.currentLottoType = e.target.value;
+        // Reset UI when switching types
+        resetUIForNewType();
+    });
+    elements.analysisMethodSelect.addEventListener('change', (e) => {
+        elements.dreamKeywordsGroup.style.display = e.target.value === 'dream_numbers' ? 'block' : 'none';
+        elements.crossValidateGroup.style.display = isMlMethod(e.target.value) ? 'block' : 'none';
+    });
+    elements.dreamKeywordsInput.addEventListener('change', handleDreamKeywordsChange);
+    elements.checkTicketsBtn.addEventListener('click', handleCheckTicketsClick);
+    elements.ticketLottoTypeSelect.addEventListener('change', (e) => {
+        // Thai tickets have a fixed price; only a Laos bet has a stake to choose.
+        elements.ticketStakeGroup.style.display = e.target.value === 'laos' ? 'block' : 'none';
+        elements.ticketResultsContainer.innerHTML = '';
+    });
+
+    // --- Functions ---
+
+    function resetUIForNewType() {
+        state.scrapedResultsData = [];
+        state.analysisDates = [];
+        state.analysisPrizeId = null;
+        state.generatedInput = '';
+        elements.tableContainer.style.display = 'none';
+        elements.analysisSection.style.display = 'none';
+        elements.resultsBody.innerHTML = '';
+        elements.resultsHead.innerHTML = '';
+        elements.progressContainer.innerHTML = '';
+        elements.progressContainer.style.display = 'none';
+        elements.analysisResultsContainer.innerHTML = '';
+    }
+
+    async function handleScrapeButtonClick() {
+        setScraperUIState(true);
+        try {
+            const response = await fetch('/start-scrape', {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ lotto_type: state.currentLottoType })
+            });
+
+            if (!response.ok) {
+                const errData = await response.json();
+                throw new Error(errData.error || `ข้อผิดพลาดจากเซิร์ฟเวอร์: ${response.status}`);
+            }
+            // Start polling for status updates.
+            state.scrapeStatusInterval = setInterval(checkScrapeStatus, 2000);
+        } catch (error) {
+            showError(elements.progressContainer, error.message);
+            setScraperUIState(false);
+        }
+    }
+
+    async function checkScrapeStatus() {
+        try {
+            const response = await fetch('/status');
+            const data = await response.json();
+            
+            // Display progress logs.
+            elements.progressContainer.innerHTML = data.progress.join('<br>');
+            elements.progressContainer.scrollTop = elements.progressContainer.scrollHeight;
+
+            // If the task is no longer running, process the results.
+            if (!data.is_running) {
+                clearInterval(state.scrapeStatusInterval);
+                state.scrapedResultsData = data.results;
+                displayScrapeResults(data.results);
+                setScraperUIState(false);
+            }
+        } catch (error) {
+            showError(elements.progressContainer, `เกิดข้อผิดพลาดในการตรวจสอบสถานะ: ${error.message}`);
+            clearInterval(state.scrapeStatusInterval);
+            setScraperUIState(false);
+        }
+    }
+
+    function displayScrapeResults(results) {
+        if (!results || results.length === 0) {
+            elements.progressContainer.innerHTML += '<br><br><strong>ไม่พบข้อมูลจากการดึงข้อมูล</strong>';
+            return;
+        }
+
+        const config = LOTTO_CONFIG[state.currentLottoType];
+        // Update Table Headers
+        const headers = [...config.headers, ...config.otherPrizes];
+        elements.resultsHead.innerHTML = `<tr>${headers.map(h => `<th>${h}</th>`).join('')}</tr>`;
+
+        // Update Table Body
+        elements.resultsBody.innerHTML = results.map(result => `
+            <tr>
+                <td>${result['Draw Date']}</td>
+                <td><strong>${result['Prize 1']}</strong></td>
+                <td><strong>${result['Prize 2'] || ''}</strong></td>
+                ${config.otherPrizes.map(tier => `<td>${(result['Other Prizes']?.[tier] || []).join(', ')}</td>`).join('')}
+            </tr>
+        `).join('');
+
+        // Show table and analysis section
+        elements.tableContainer.style.display = 'block';
+        elements.analysisSection.style.display = 'block';
+
+        // Update the analysis UI based on the new data
+        updateAnalysisUI();
+    }
+    
+    function updateAnalysisUI() {
+        const config = LOTTO_CONFIG[state.currentLottoType];
+        const prizeKeys = Object.keys(config.prizes);
+
+        // Update Prediction Target Radio Buttons
+        elements.predictionTypeGroup.innerHTML = prizeKeys.map((key, index) => `
+            <input type="radio" id="predict-${key}" name="prediction_type" value="${key}" ${index === 0 ? 'checked' : ''}>
+            <label for="predict-${key}">${config.prizes[key].name}</label>
+        `).join('');
+        
+        // Add event listeners to the newly created radio buttons
+        document.querySelectorAll('input[name="prediction_type"]').forEach(radio => {
+            radio.addEventListener('change', (e) => updateAnalysisInput(e.target.value));
+        });
+
+        // Trigger the change event to populate the textarea with the default prize type
+        updateAnalysisInput(prizeKeys[0]);
+    }
+
+    function updateAnalysisInput(prizeId) {
+        if (state.scrapedResultsData.length === 0) return;
+        
+        // Get the data key (e.g., 'Prize 1') from the config using the prizeId (e.g., 'prize1')
+        const dataKey = LOTTO_CONFIG[state.currentLottoType].prizes[prizeId].key;
+        
+        // Map the results to get the correct prize numbers, reverse for chronological order, and filter out any empty values.
+        const draws = [...state.scrapedResultsData].reverse()
+            .map(result => ({ number: result[dataKey]?.replace(/[^0-9]/g, ''), date: result['Draw Date'] }))
+            .filter(draw => draw.number);
+        const numbersForAnalysis = draws.map(draw => draw.number);
+        state.analysisDates = draws.map(draw => draw.date);
+        state.analysisPrizeId = prizeId;
+        state.generatedInput = numbersForAnalysis.join(', ');
+            
+        elements.numberInput.value = state.generatedInput;
+        // Clear previous analysis results when the input changes
+        elements.analysisResultsContainer.innerHTML = '';
+    }
+
+    // Every scraped draw with all of its prize tiers, oldest first, for methods that compare tiers.
+    function buildDrawRecords() {
+        const config = LOTTO_CONFIG[state.currentLottoType];
+        return [...state.scrapedResultsData].reverse().map(result => ({
+            date: result['Draw Date'],
+            prizes: Object.fromEntries([
+                ...Object.values(config.prizes)
+                    .map(prize => [prize.name, [result[prize.key]?.replace(/[^0-9]/g, '')].filter(Boolean)]),
+                ...config.otherPrizes
+                    .filter(tier => result['Other Prizes']?.[tier]?.length)
+                    .map(tier => [tier, result['Other Prizes'][tier]]),
+            ]),
+        }));
+    }
+
+    async function handleAnalyzeButtonClick() {
+        const numbersText = elements.numberInput.value;
+        if (!numbersText.trim()) {
+            showError(elements.analysisResultsContainer, 'กรุณาใส่ชุดตัวเลขเพื่อวิเคราะห์');
+            return;
+        }
+        const numbersArray = numbersText.split(',').map(s => s.trim()).filter(s => s);
+        
+        setAnalyzerUIState(true);
+        try {
+            const response = await fetch('/analyze', {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ 
+                    ...buildAnalysisData(numbersText, numbersArray),
+                    target: buildTarget(),
+                    method: elements.analysisMethodSelect.value,
+                    params: buildAnalysisParams(),
+                })
+            });
+            const resultData = await response.json();
+            if (!response.ok || resultData.error) {
+                throw new Error(resultData.error || 'การวิเคราะห์ล้มเหลว');
+            }
+            displayAnalysisResults(resultData);
+        } catch (error) {
+            showError(elements.analysisResultsContainer, `ข้อผิดพลาดในการวิเคราะห์: ${error.message}`);
+        } finally {
+            setAnalyzerUIState(false, true); // Keep results visible after analysis
+        }
+    }
+
+    function displayAnalysisResults(data) {
+        const { statistical_summary, pattern_analysis, prediction_output, detailed_explanation, evaluation, prediction_distribution, target } = data;
+        
+        // Helper function to create a list block, now handles both objects and arrays
+        const createListHtml = (title, dataObj) => {
+            if (!dataObj || Object.keys(dataObj).length === 0) return '';
+            
+            const formatValue = (value) => typeof value === 'object' ? JSON.stringify(value).replace(/"/g, '') : value;
+            let items;
+            if (Array.isArray(dataObj)) {
+                items = dataObj.map(value => `<li>${formatValue(value)}</li>`).join('');
+            } else {
+                items = Object.entries(dataObj)
+                    .map(([key, value]) => `<li><strong>${key}:</strong> ${Array.isArray(value) ? value.map(formatValue).join(', ') : formatValue(value)}</li>`)
+                    .join('');
+            }
+            return `<div class="result-block"><h3>${title}</h3><ul>${items}</ul></div>`;
+        };
+        
+        const createParagraphHtml = (title, dataObj) => {
+            if (!dataObj || Object.keys(dataObj).length === 0) return '';
+             const items = Object.entries(dataObj)
+                .map(([key, value]) => `<h4>${key}</h4><p>${value}</p>`)
+                .join('');
+            return `<div class="result-block"><h3>${title}</h3>${items}</div>`;
+        };
+
+        // Renders a position-by-digit probability matrix, shading each cell by its share.
+        const createHeatmapHtml = (heatmap) => {
+            if (!heatmap || !heatmap.probabilities) return '';
+            const maxProb = Math.max(...heatmap.probabilities.flat(), 0.0001);
+            const headerRow = `<tr><th>หลัก</th>${[...Array(10).keys()].map(d => `<th>${d}</th>`).join('')}</tr>`;
+            const bodyRows = heatmap.probabilities.map((row, pos) => `
+                <tr>
+                    <th>${pos + 1}</th>
+                    ${row.map((prob, digit) => `
+                        <td style="background-color: rgba(74, 144, 226, ${(prob / maxProb).toFixed(2)})" title="${heatmap.counts[pos][digit]} ครั้ง">
+                            ${(prob * 100).toFixed(1)}%
+                        </td>`).join('')}
+                </tr>`).join('');
+            return `<div class="result-block"><h3>🌡️ Heatmap ความถี่รายหลัก</h3><div class="heatmap-container"><table class="heatmap">${headerRow}${bodyRows}</table></div></div>`;
+        };
+
+        // Summarises the cross-validated report of the ML methods, with the confusion matrix as a table.
+        const createEvaluationHtml = (report) => {
+            if (!report) return '';
+            const pct = (value) => `${(value * 100).toFixed(1)}%`;
+            const summary = {
+                'การตรวจสอบไขว้': `${report.strategy === 'k_fold' ? 'K-Fold' : 'Time Series'} (${report.folds} folds, ${report.predictions} การทำนาย)`,
+                'ความแม่นยำ': `${pct(report.accuracy)} (Majority class: ${pct(report.majority_baseline_accuracy)}, สุ่ม: ${pct(report.chance_accuracy)})`,
+                'Log-loss': `${report.log_loss.toFixed(3)} (Majority class: ${report.majority_baseline_log_loss.toFixed(3)}, สุ่ม: ${report.uniform_log_loss.toFixed(3)})`,
+            };
+            report.per_target.forEach(t => { summary[`ความแม่นยำ ${t.target}`] = pct(t.accuracy); });
+            const maxCount = Math.max(...report.confusion_matrix.flat(), 1);
+            const headerRow = `<tr><th>จริง / ทำนาย</th>${[...Array(10).keys()].map(d => `<th>${d}</th>`).join('')}<th>Precision</th><th>Recall</th></tr>`;
+            const bodyRows = report.confusion_matrix.map((row, actual) => `
+                <tr>
+                    <th>${actual}</th>
+                    ${row.map(count => `<td style="background-color: rgba(74, 144, 226, ${(count / maxCount).toFixed(2)})">${count}</td>`).join('')}
+                    <td>${report.per_class[actual].precision === null ? '-' : pct(report.per_class[actual].precision)}</td>
+                    <td>${report.per_class[actual].recall === null ? '-' : pct(report.per_class[actual].recall)}</td>
+                </tr>`).join('');
+            return createListHtml(`🧪 ผลการประเมินโมเดล (${report.model})`, summary) +
+                `<div class="result-block"><h3>🔢 Confusion Matrix</h3><div class="heatmap-container"><table class="heatmap">${headerRow}${bodyRows}</table></div></div>`;
+        };
+
+        // Lists the ranked candidates with raw probability and calibrated confidence, shaded by probability.
+        const createDistributionHtml = (distribution) => {
+            if (!distribution || distribution.candidates.length === 0) return '';
+            const pct = (value) => `${(value * 100).toPrecision(3)}%`;
+            const maxProb = Math.max(...distribution.candidates.map(c => c.probability), 0.0001);
+            const headerRow = '<tr><th>อันดับ</th><th>ตัวเลข</th><th>ความน่าจะเป็น</th><th>ความเชื่อมั่นที่ปรับเทียบแล้ว</th></tr>';
+            const bodyRows = distribution.candidates.map((c, rank) => `
+                <tr>
+                    <th>${rank + 1}</th>
+                    <td>${c.candidate}</td>
+                    <td style="background-color: rgba(74, 144, 226, ${(c.probability / maxProb).toFixed(2)})">${pct(c.probability)}</td>
+                    <td>${pct(c.calibrated_confidence)}</td>
+                </tr>`).join('');
+            return `<div class="result-block"><h3>🎯 การแจกแจงความน่าจะเป็น (จาก ${distribution.support_size.toLocaleString()} ผลลัพธ์ที่เป็นไปได้)</h3><div class="heatmap-container"><table class="heatmap">${headerRow}${bodyRows}</table></div></div>`;
+        };
+
+        // Shows the consensus ranking with the methods that voted for each ending.
+        const createConsensusHtml = (ranking) => {
+            if (!ranking || ranking.length === 0) return '';
+            const headerRow = '<tr><th>อันดับ</th><th>เลขท้าย</th><th>คะแนน</th><th>วิธีที่เลือกเป็นอันดับหนึ่ง</th><th>วิธีที่มีเลขนี้ในตัวเลือก</th></tr>';
+            const bodyRows = ranking.map((row, rank) => `
+                <tr>
+                    <th>${rank + 1}</th>
+                    <td>${row.candidate}</td>
+                    <td style="background-color: rgba(74, 144, 226, ${(row.score / ranking[0].score).toFixed(2)})">${(row.score * 100).toFixed(1)}%</td>
+                    <td>${row.top_pick_of.join(', ') || '-'}</td>
+                    <td>${row.agreeing_methods.join(', ')}</td>
+                </tr>`).join('');
+            return `<div class="result-block"><h3>🤝 การจัดอันดับฉันทามติ</h3><div class="heatmap-container"><table class="heatmap">${headerRow}${bodyRows}</table></div></div>`;
+        };
+
+        // Lists every permutation of each predicted number, most frequent in the history first.
+        const createPermutationsHtml = (expanded) => {
+            if (!expanded || expanded.length === 0) return '';
+            const items = expanded.map(entry => `
+                <li><strong>${entry.source}:</strong> ${entry.permutations
+                    .map(p => `${p.number} (${p.historical_count})`).join(', ')}</li>`).join('');
+            return `<div class="result-block"><h3>🔄 กลับเลข (จำนวนครั้งที่เคยออก)</h3><ul>${items}</ul></div>`;
+        };
+
+        // The heatmap matrix is drawn as a table, so keep it out of the generic pattern list.
+        const { heatmap, 'การจัดอันดับฉันทามติ': consensusRanking, ...otherPatterns } = pattern_analysis || {};
+
+        const predictionHtml = `
+            <div class="result-block prediction-block">
+                <h3>🔮 คำทำนายจาก AI</h3>
+                <div class="prediction-value">${prediction_output.PREDICTION}</div>
+                <div class="confidence"><strong>โมเดล:</strong> ${prediction_output.METHOD || 'N/A'}</div>
+                ${prediction_distribution && prediction_distribution.candidates.length > 0 ? `
+                <div class="confidence">
+                    <strong>ความเชื่อมั่น:</strong> ${(prediction_distribution.candidates[0].calibrated_confidence * 100).toPrecision(3)}%
+                    (สุ่ม: ${(100 / prediction_distribution.support_size).toPrecision(3)}%)
+                    · <strong>เอนโทรปี:</strong> ${prediction_distribution.entropy_bits.toFixed(2)} / ${prediction_distribution.max_entropy_bits.toFixed(2)} บิต
+                </div>` : ''}
+            </div>`;
+
+        // Generate HTML for alternative predictions if they exist
+        const alternativesHtml = prediction_output['ทางเลือกอื่นๆ'] ? 
+            createListHtml('🎲 ทางเลือกอื่นๆ', prediction_output['ทางเลือกอื่นๆ']) : '';
+
+        elements.analysisResultsContainer.innerHTML = [
+            predictionHtml,
+            alternativesHtml, // Add alternatives right after the main prediction
+            createPermutationsHtml(prediction_output.PERMUTATIONS),
+            createDistributionHtml(prediction_distribution),
+            createHeatmapHtml(heatmap),
+            createConsensusHtml(consensusRanking),
+            createListHtml('📊 สรุปสถิติ', { 'ส่วนของตัวเลขที่วิเคราะห์': describeTarget(target), ...statistical_summary }),
+            createListHtml('🧩 การวิเคราะห์รูปแบบ', otherPatterns),
+            createEvaluationHtml(evaluation),
+            createParagraphHtml('📝 คำอธิบายโดยละเอียด', detailed_explanation)
+        ].join('');
+    }
+    
+    // --- UI State Management ---
+    function setScraperUIState(isScraping) {
+        elements.scrapeBtn.disabled = isScraping;
+        elements.lottoTypeSelect.disabled = isScraping;
+        elements.progressContainer.style.display = isScraping ? 'block' : 'none';
+        if (isScraping) {
+            resetUIForNewType();
+            elements.progressContainer.style.display = 'block';
+        }
+    }
+
+    function setAnalyzerUIState(isAnalyzing, keepContent = false) {
+        elements.analyzeBtn.disabled = isAnalyzing;
+        if (isAnalyzing) {
+            elements.analysisResultsContainer.innerHTML = `
+                <div class="spinner-container">
+                    <div class="spinner"></div>
+                    <p>AI กำลังประมวลผลข้อมูล...</p>
+                </div>`;
+        } else if (!keepContent) {
+            elements.analysisResultsContainer.innerHTML = '';
+        }
+    }
+
+    // An unedited input refers to the server's stored history; edited numbers are posted as they are.
+    function buildAnalysisData(numbersText, numbersArray) {
+        if (state.analysisPrizeId && numbersText === state.generatedInput) {
+            return { history: { lotto_type: state.currentLottoType, prize: state.analysisPrizeId } };
+        }
+        return {
+            numbers: numbersArray,
+            // The input lists the draws oldest first, as generated from the scraped results.
+            order: 'oldest_first',
+            draws: buildDrawRecords(),
+            // Dates are only meaningful while the input still holds the scraped numbers.
+            dates: numbersArray.length === state.analysisDates.length ? state.analysisDates : undefined,
+        };
+    }
+
+    // Option values look like "last:2" or "position:1"; an empty value analyzes the whole number.
+    function buildTarget() {
+        const [kind, value] = elements.targetSelect.value.split(':');
+        if (!kind) return undefined;
+        return kind === 'position' ? { kind, index: Number(value) } : { kind, digits: Number(value) };
+    }
+
+    function describeTarget(target) {
+        switch (target?.kind) {
+            case 'last': return `${target.digits} ตัวท้าย`;
+            case 'first': return `${target.digits} ตัวหน้า`;
+            case 'position': return `หลักที่ ${target.index}`;
+            default: return 'ทั้งตัวเลข';
+        }
+    }
+
+    function buildAnalysisParams() {
+        const params = {};
+        if (elements.analysisMethodSelect.value === 'dream_numbers') {
+            params.dream_keywords = parseDreamKeywords();
+        }
+        if (elements.permutationSelect.value) {
+            params.permutation_digits = Number(elements.permutationSelect.value);
+        }
+        if (isMlMethod(elements.analysisMethodSelect.value) && elements.crossValidateInput.checked) {
+            params.cross_validate = true;
+        }
+        return params;
+    }
+
+    function isMlMethod(method) {
+        return method.startsWith('ml_');
+    }
+
+    function parseDreamKeywords() {
+        return elements.dreamKeywordsInput.value.split(',').map(s => s.trim()).filter(s => s);
+    }
+
+    // Shows the dream-book numbers of the typed keywords before the analysis is run.
+    async function handleDreamKeywordsChange() {
+        const keywords = parseDreamKeywords();
+        if (keywords.length === 0) {
+            elements.dreamLookupResult.innerHTML = '';
+            return;
+        }
+        try {
+            const response = await fetch(`/dream-book?q=${encodeURIComponent(keywords.join(','))}`);
+            const matches = await response.json();
+            if (!response.ok || matches.error) throw new Error(matches.error || 'ค้นหาตำราทำนายฝันไม่สำเร็จ');
+            elements.dreamLookupResult.innerHTML = matches.length === 0
+                ? 'ไม่พบคำนี้ในตำราทำนายฝัน'
+                : matches.map(m => `<strong>${m.keyword}</strong>: ${m.numbers.join(', ')}`).join('<br>');
+        } catch (error) {
+            showError(elements.dreamLookupResult, error.message);
+        }
+    }
+
+    async function handleCheckTicketsClick() {
+        const tickets = elements.ticketInput.value.split(/[\s,]+/).filter(s => s);
+        if (tickets.length === 0) {
+            showError(elements.ticketResultsContainer, 'กรุณาใส่หมายเลขสลากที่ต้องการตรวจ');
+            return;
+        }
+        const lottoType = elements.ticketLottoTypeSelect.value;
+        elements.checkTicketsBtn.disabled = true;
+        try {
+            const response = await fetch('/check-tickets', {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({
+                    lotto_type: lottoType,
+                    draw_date: elements.ticketDateInput.value || undefined,
+                    tickets,
+                    stake: lottoType === 'laos' ? Number(elements.ticketStakeInput.value) : undefined,
+                })
+            });
+            const report = await response.json();
+            if (!response.ok || report.error) throw new Error(report.error || 'ตรวจสลากไม่สำเร็จ');
+            displayTicketResults(report);
+        } catch (error) {
+            showError(elements.ticketResultsContainer, `ข้อผิดพลาดในการตรวจสลาก: ${error.message}`);
+        } finally {
+            elements.checkTicketsBtn.disabled = false;
+        }
+    }
+
+    // Lists the winning numbers of the draw, then every ticket with the tiers it won, winners first.
+    function displayTicketResults(report) {
+        const money = (amount) => amount.toLocaleString('th-TH');
+        const unit = report.lotto_type === 'laos' ? 'กีบ' : 'บาท';
+        const winningItems = report.winning_numbers
+            .map(w => `<li><strong>${w.tier}:</strong> ${w.numbers.join(', ')}</li>`).join('');
+        const uncheckedNote = report.unchecked_tiers.length > 0
+            ? `<p>ไม่ได้ตรวจ ${report.unchecked_tiers.join(', ')} เพราะประวัติที่บันทึกไว้ไม่มีเลขของรางวัลเหล่านี้</p>` : '';
+        const ordered = [...report.tickets].sort((a, b) => b.total - a.total);
+        const bodyRows = ordered.map(t => `
+            <tr>
+                <td>${t.ticket}</td>
+                <td>${t.prizes.length === 0 ? 'ไม่ถูกรางวัล' : t.prizes.map(p => `${p.tier} (${p.winning_number})`).join(', ')}</td>
+                <td>${t.total > 0 ? `<strong>${money(t.total)}</strong>` : '-'}</td>
+            </tr>`).join('');
+        elements.ticketResultsContainer.innerHTML = `
+            <div class="result-block">
+                <h3>🏆 ผลรางวัลงวดวันที่ ${report.draw_date}</h3>
+                <ul>${winningItems}</ul>
+                ${uncheckedNote}
+            </div>
+            <div class="result-block">
+                <h3>🎫 ถูกรางวัล ${report.winning_tickets} จาก ${report.tickets.length} ใบ รวม ${money(report.total_winnings)} ${unit}</h3>
+                <div class="heatmap-container"><table class="heatmap">
+                    <tr><th>หมายเลข</th><th>รางวัลที่ถูก</th><th>เงินรางวัล (${unit})</th></tr>${bodyRows}
+                </table></div>
+            </div>`;
+    }
+
+    function showError(container, message) {
+        container.innerHTML = `<p class="error">${message}</p>`;
+        container.style.display = 'block';
+    }
 });
\ No newline at end of file